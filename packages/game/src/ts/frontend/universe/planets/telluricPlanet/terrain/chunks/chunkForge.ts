@@ -32,7 +32,12 @@ export type ChunkForgeCompletedOutput = {
     scatteredInstances: ScatteredInstanceBuffers;
 };
 
-export type ChunkForgeOutput = ChunkForgePendingOutput | ChunkForgeCompletedOutput;
+type ChunkForgeFailedOutput = {
+    status: "failed";
+    error: string;
+};
+
+export type ChunkForgeOutput = ChunkForgePendingOutput | ChunkForgeCompletedOutput | ChunkForgeFailedOutput;
 
 export interface ChunkForge {
     addTask(task: BuildTask): void;
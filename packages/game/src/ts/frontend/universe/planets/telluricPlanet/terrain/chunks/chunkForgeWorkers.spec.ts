@@ -0,0 +1,89 @@
+//  This file is part of Cosmos Journeyer
+//
+//  Copyright (C) 2024 Barthélemy Paléologue <barth.paleologue@cosmosjourneyer.com>
+//
+//  This program is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU Affero General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU Affero General Public License for more details.
+//
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+import { Vector3 } from "@babylonjs/core/Maths/math.vector";
+import { describe, expect, it } from "vitest";
+
+import { ChunkForgeWorkers, MaxChunkBuildAttempts } from "./chunkForgeWorkers";
+import { type BuildTask } from "./taskTypes";
+
+/** A build worker answering the tasks on demand */
+class FakeWorker {
+    public readonly tasks: Array<unknown> = [];
+    public onmessage: ((event: MessageEvent<unknown>) => void) | null = null;
+    public onerror: ((event: ErrorEvent) => void) | null = null;
+    public onmessageerror: ((event: MessageEvent<unknown>) => void) | null = null;
+
+    public postMessage(task: unknown): void {
+        this.tasks.push(task);
+    }
+
+    public reply(data: unknown): void {
+        this.onmessage?.(new MessageEvent("message", { data }));
+    }
+}
+
+const chunkId = "chunk";
+
+function createForge(): { forge: ChunkForgeWorkers; worker: FakeWorker } {
+    const worker = new FakeWorker();
+    const forge = new ChunkForgeWorkers([worker as unknown as Worker], 8);
+
+    const task: BuildTask = {
+        chunkId,
+        planetModel: {} as unknown as BuildTask["planetModel"],
+        depth: 3,
+        faceIndex: 0,
+        position: new Vector3(1, 0, 0),
+    };
+    forge.addTask(task);
+    forge.update();
+
+    return { forge, worker };
+}
+
+describe("ChunkForgeWorkers", () => {
+    it("builds a failed chunk again", () => {
+        const { forge, worker } = createForge();
+
+        worker.reply({ chunkId, error: "transient failure" });
+        expect(worker.tasks.length).toBe(2);
+        expect(forge.getOutput(chunkId)?.status).toBe("pending");
+
+        worker.reply({
+            chunkId,
+            positions: new Float32Array(3),
+            normals: new Float32Array(3),
+            indices: new Uint16Array(3),
+            scatteredInstances: {},
+        });
+        expect(forge.getOutput(chunkId)?.status).toBe("completed");
+    });
+
+    it("reports a chunk as failed once it failed on every attempt", () => {
+        const { forge, worker } = createForge();
+
+        for (let attempt = 0; attempt < MaxChunkBuildAttempts; attempt++) {
+            expect(forge.getOutput(chunkId)?.status).toBe("pending");
+            worker.reply({ chunkId, error: "invalid inputs" });
+        }
+
+        expect(worker.tasks.length).toBe(MaxChunkBuildAttempts);
+        expect(forge.getOutput(chunkId)).toEqual({ status: "failed", error: "invalid inputs" });
+        expect(forge.isIdle()).toBe(true);
+    });
+});
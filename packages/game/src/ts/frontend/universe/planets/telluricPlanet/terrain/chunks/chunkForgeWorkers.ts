@@ -22,10 +22,13 @@ import { LRUMap } from "@/utils/dataStructures/lruMap";
 import { Settings } from "@/settings";
 
 import { type ChunkForge, type ChunkForgeOutput, type ChunkId } from "./chunkForge";
-import { ReturnedChunkDataSchema, type BuildTask } from "./taskTypes";
+import { FailedChunkDataSchema, ReturnedChunkDataSchema, type BuildTask, type FailedChunkData } from "./taskTypes";
 import { type TransferBuildData } from "./workerDataTypes";
 import { WorkerPool } from "./workerPool";
 
+/** The number of times a chunk is built before it is reported as failed */
+export const MaxChunkBuildAttempts = 3;
+
 export class ChunkForgeWorkers implements ChunkForge {
     /** the number vertices per row of the chunk (total number of vertices = nbVerticesPerRow * nbVerticesPerRow) */
     private readonly nbVerticesPerRow: number;
@@ -34,7 +37,14 @@ export class ChunkForgeWorkers implements ChunkForge {
 
     private readonly output = new LRUMap<ChunkId, ChunkForgeOutput>(Settings.MAX_CACHED_CHUNKS);
 
-    private constructor(workers: ReadonlyArray<Worker>, nbVerticesPerRow: number) {
+    /** the tasks being built, with the number of failed attempts, so that failed builds can be retried */
+    private readonly pendingTasks = new Map<ChunkId, { task: BuildTask; nbFailures: number }>();
+
+    /**
+     * @param workers The build workers, ready to receive tasks
+     * @param nbVerticesPerRow The number vertices per row of the chunks
+     */
+    public constructor(workers: ReadonlyArray<Worker>, nbVerticesPerRow: number) {
         this.workerPool = new WorkerPool(
             workers,
             (task) => {
@@ -49,7 +59,9 @@ export class ChunkForgeWorkers implements ChunkForge {
     }
 
     public static async New(nbVerticesPerRow: number): Promise<Result<ChunkForgeWorkers, Error>> {
-        const nbMaxWorkers = Math.max(1, navigator.hardwareConcurrency - 1); // -1 because the main thread is also used
+        const nbThreads = Math.max(1, navigator.hardwareConcurrency - 1); // -1 because the main thread is also used
+        // when cross-origin isolated, each worker builds its chunks on a pool of threads
+        const nbMaxWorkers = self.crossOriginIsolated ? Math.ceil(nbThreads / Settings.THREADS_PER_BUILD_WORKER) : nbThreads;
 
         const workerResults = await Promise.all(Array.from({ length: nbMaxWorkers }, () => this.CreateBuildWorker()));
 
@@ -111,6 +123,7 @@ export class ChunkForgeWorkers implements ChunkForge {
 
     public addTask(task: BuildTask) {
         this.output.set(task.chunkId, { status: "pending" });
+        this.pendingTasks.set(task.chunkId, { task, nbFailures: 0 });
         this.workerPool.submitTask(task);
     }
 
@@ -130,12 +143,19 @@ export class ChunkForgeWorkers implements ChunkForge {
     }
 
     private handleWorkerResult(e: MessageEvent) {
+        const failureResult = FailedChunkDataSchema.safeParse(e.data);
+        if (failureResult.success) {
+            this.handleFailure(failureResult.data);
+            return;
+        }
+
         const dataResult = ReturnedChunkDataSchema.safeParse(e.data);
         if (!dataResult.success) {
             return;
         }
 
         const data = dataResult.data;
+        this.pendingTasks.delete(data.chunkId);
 
         const existingOutput = this.output.get(data.chunkId);
         if (existingOutput === undefined) {
@@ -151,6 +171,30 @@ export class ChunkForgeWorkers implements ChunkForge {
         });
     }
 
+    /**
+     * Builds a failed chunk again, or reports it as failed once it has failed too many times
+     * so that its parent chunk stays displayed in its place
+     * @param failure The error reported by the worker
+     */
+    private handleFailure(failure: FailedChunkData) {
+        const pendingTask = this.pendingTasks.get(failure.chunkId);
+        if (pendingTask === undefined || this.output.get(failure.chunkId) === undefined) {
+            this.pendingTasks.delete(failure.chunkId);
+            return;
+        }
+
+        pendingTask.nbFailures++;
+        if (pendingTask.nbFailures < MaxChunkBuildAttempts) {
+            console.warn(`Failed to build chunk ${failure.chunkId}, retrying: ${failure.error}`);
+            this.workerPool.submitTask(pendingTask.task);
+            return;
+        }
+
+        console.error(`Failed to build chunk ${failure.chunkId}: ${failure.error}`);
+        this.pendingTasks.delete(failure.chunkId);
+        this.output.set(failure.chunkId, { status: "failed", error: failure.error });
+    }
+
     /**
      * Updates the state of the forge : dispatch tasks to workers, remove useless chunks, apply vertexData to new chunks
      */
@@ -165,5 +209,6 @@ export class ChunkForgeWorkers implements ChunkForge {
     public reset() {
         this.workerPool.reset();
         this.output.clear();
+        this.pendingTasks.clear();
     }
 }
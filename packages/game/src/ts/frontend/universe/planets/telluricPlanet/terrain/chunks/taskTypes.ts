@@ -41,3 +41,10 @@ export const ReturnedChunkDataSchema = z.object({
 });
 
 export type ReturnedChunkData = z.infer<typeof ReturnedChunkDataSchema>;
+
+export const FailedChunkDataSchema = z.object({
+    chunkId: z.string(),
+    error: z.string(),
+});
+
+export type FailedChunkData = z.infer<typeof FailedChunkDataSchema>;
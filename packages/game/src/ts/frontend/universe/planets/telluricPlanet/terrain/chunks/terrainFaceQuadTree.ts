@@ -235,8 +235,9 @@ export class TerrainFaceQuadTree implements Cullable {
             this.scene,
         );
 
+        // the chunks that failed to build are built again when their node is recreated
         const chunkOutput = chunkForge.getOutput(chunk.id);
-        if (chunkOutput === undefined) {
+        if (chunkOutput === undefined || chunkOutput.status === "failed") {
             const buildTask: BuildTask = {
                 chunkId: chunk.id,
                 planetModel: this.planetModel,
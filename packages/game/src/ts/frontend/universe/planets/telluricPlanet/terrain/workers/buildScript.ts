@@ -17,7 +17,7 @@
 
 import { Axis } from "@babylonjs/core/Maths/math.axis";
 import { Quaternion, Vector3 } from "@babylonjs/core/Maths/math.vector";
-import { build_chunk_vertex_data, BuildData, TerrainSettings } from "terrain-generation";
+import type { ReturnData } from "terrain-generation";
 
 import { AvailableRockSizes } from "@/frontend/assets/objects/rockSizes";
 
@@ -28,11 +28,14 @@ import { Settings } from "@/settings";
 import { filterPoints, MaxScatterDensity, type ScatteringLayer } from "../../../../../helpers/instancing";
 import { BeachElevationSpan } from "../../telluricPlanetMaterial";
 import type { ScatteredInstanceBuffers } from "../chunks/scatteringSystem";
-import { type ReturnedChunkData } from "../chunks/taskTypes";
+import { type FailedChunkData, type ReturnedChunkData } from "../chunks/taskTypes";
 import { type TransferBuildData } from "../chunks/workerDataTypes";
+import { loadTerrainGeneration } from "./terrainGenerationModule";
 
 const SKIRT_GENERATION_VERTEX_SPACING_THRESHOLD = 512;
 
+const { build_chunk_vertex_data, BuildData, ChunkLayout, Seed, TerrainSettings } = await loadTerrainGeneration();
+
 function handle_build(data: TransferBuildData): void {
     const nbVerticesPerSide = data.nbVerticesPerSide;
     const nbSubdivisions = nbVerticesPerSide - 1;
@@ -46,17 +49,6 @@ function handle_build(data: TransferBuildData): void {
         space_between_vertices < Settings.MIN_DISTANCE_BETWEEN_VERTICES ? MaxScatterDensity : 0;
 
     const shouldGenerateSkirt = space_between_vertices < SKIRT_GENERATION_VERTEX_SPACING_THRESHOLD;
-    const skirtVertexCount = shouldGenerateSkirt ? 4 * nbVerticesPerSide : 0;
-    const skirtIndexCount = shouldGenerateSkirt ? 4 * nbSubdivisions * 2 * 3 : 0;
-
-    const verticesPositions = new Float32Array((nbVerticesPerSide * nbVerticesPerSide + skirtVertexCount) * 3);
-    const indices = new Uint16Array(nbSubdivisions * nbSubdivisions * 2 * 3 + skirtIndexCount);
-    const normals = new Float32Array(verticesPositions.length);
-
-    const flat_area = size * size;
-    const max_nb_instances = Math.floor(flat_area * scatter_per_square_meter * 2.0);
-
-    let scattered_point_buffer = new Float32Array(6 * max_nb_instances);
 
     const terrain_settings = new TerrainSettings();
     terrain_settings.continent_base_height = planetModel.terrainSettings.continent_base_height;
@@ -69,26 +61,48 @@ function handle_build(data: TransferBuildData): void {
     terrain_settings.bumps_frequency = planetModel.terrainSettings.bumps_frequency;
     terrain_settings.max_bump_height = planetModel.terrainSettings.max_bump_height;
 
-    const buildData: BuildData = new BuildData(
+    terrain_settings.sea_level = planetModel.ocean?.depth ?? 0;
+
+    // integer seeds give every layer its own sub-seed, the layers of an offset seed all share the same noise
+    const offsetSeed = Seed.new_offset(planetModel.seed);
+    const planetSeed = offsetSeed.to_integer();
+    offsetSeed.free();
+
+    const buildData = new BuildData(
         planetDiameter,
         data.depth,
         data.faceIndex,
         data.position[0],
         data.position[1],
         data.position[2],
-        planetModel.seed,
+        planetSeed,
         data.nbVerticesPerSide,
         terrain_settings,
     );
 
-    const result = build_chunk_vertex_data(
-        buildData,
-        verticesPositions,
-        indices,
-        normals,
-        scattered_point_buffer,
-        scatter_per_square_meter,
-    );
+    // the buffer sizes come from the chunk builder so that they always match what it expects
+    const layout = ChunkLayout.for_chunk(buildData, shouldGenerateSkirt, scatter_per_square_meter);
+    const verticesPositions = new Float32Array(layout.positions_length);
+    const indices = new Uint16Array(layout.indices_length);
+    const normals = new Float32Array(layout.normals_length);
+    let scattered_point_buffer = new Float32Array(layout.scatter_length);
+    layout.free();
+
+    let result: ReturnData;
+    try {
+        result = build_chunk_vertex_data(
+            buildData,
+            verticesPositions,
+            indices,
+            normals,
+            new Float32Array(0),
+            new Float32Array(0),
+            scattered_point_buffer,
+            scatter_per_square_meter,
+        );
+    } finally {
+        buildData.free();
+    }
 
     const transfer: Array<Transferable> = [verticesPositions.buffer, indices.buffer, normals.buffer];
 
@@ -230,12 +244,19 @@ function handle_build(data: TransferBuildData): void {
         { transfer },
     );
 
-    buildData.free();
     result.free();
 }
 
 self.onmessage = (e: MessageEvent<TransferBuildData>) => {
-    handle_build(e.data);
+    try {
+        handle_build(e.data);
+    } catch (error) {
+        // the chunk builder throws on invalid inputs: report the failure so that the worker is handed a new task
+        self.postMessage({
+            chunkId: e.data.chunkId,
+            error: error instanceof Error ? error.message : String(error),
+        } satisfies FailedChunkData);
+    }
 };
 
 self.postMessage("ready");
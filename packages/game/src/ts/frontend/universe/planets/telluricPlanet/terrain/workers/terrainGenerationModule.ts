@@ -0,0 +1,43 @@
+//  This file is part of Cosmos Journeyer
+//
+//  Copyright (C) 2024 Barthélemy Paléologue <barth.paleologue@cosmosjourneyer.com>
+//
+//  This program is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU Affero General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU Affero General Public License for more details.
+//
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+import type * as TerrainGeneration from "terrain-generation";
+
+import { Settings } from "@/settings";
+
+export type TerrainGenerationModule = typeof TerrainGeneration;
+
+/**
+ * Loads the chunk builder of a build worker.
+ * The multithreaded build needs SharedArrayBuffer, so it is only used when the page is cross-origin isolated:
+ * the single-threaded build is loaded otherwise, or when the thread pool fails to start.
+ */
+export async function loadTerrainGeneration(): Promise<TerrainGenerationModule> {
+    if (self.crossOriginIsolated) {
+        try {
+            const threaded = await import("terrain-generation/threads");
+            await threaded.default();
+            await threaded.initThreadPool(Settings.THREADS_PER_BUILD_WORKER);
+            // both builds are generated from the same sources and share their API
+            return threaded as unknown as TerrainGenerationModule;
+        } catch (error) {
+            console.warn("Failed to start the terrain generation threads, falling back to a single thread", error);
+        }
+    }
+
+    return await import("terrain-generation");
+}
@@ -28,6 +28,7 @@ export const Settings = {
     MIN_DISTANCE_BETWEEN_VERTICES: 1.5,
     MAX_DISTANCE_BETWEEN_PHYSICS_VERTICES: 24,
     MAX_CACHED_CHUNKS: 1000,
+    THREADS_PER_BUILD_WORKER: 4, // only when the page is cross-origin isolated
 
     CLOUD_LAYER_HEIGHT: 7e3,
     EARTH_ATMOSPHERE_THICKNESS: 100e3,
@@ -0,0 +1,138 @@
+use crate::landscape::simplex_noise_layer::simplex_noise_layer;
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::{NoiseSeed, Salt};
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+const LUMP_OCTAVES: i32 = 3;
+
+/// The undisplaced surface of a body, described by its radius in every direction.
+/// The terrain is displaced radially on top of it.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[wasm_bindgen]
+pub struct BaseShape {
+    /// The semi-axis along x relative to the planet radius
+    pub scale_x: f32,
+    /// The semi-axis along y (the rotation axis) relative to the planet radius
+    pub scale_y: f32,
+    /// The semi-axis along z relative to the planet radius
+    pub scale_z: f32,
+    /// The relative amplitude of the low frequency lumps in [0, 1[
+    pub lump_amplitude: f32,
+    /// The frequency of the lumps on the unit sphere
+    pub lump_frequency: f32,
+}
+
+impl BaseShape {
+    pub const SPHERE: BaseShape = BaseShape {
+        scale_x: 1.0,
+        scale_y: 1.0,
+        scale_z: 1.0,
+        lump_amplitude: 0.0,
+        lump_frequency: 1.0,
+    };
+
+    /// The distance from the center to the surface in the given direction, relative to the planet radius
+    /// * `unit_sample_point` - The direction on the unit sphere
+    /// * `seed` - The seed of the planet
+    /// * `gradient` - The gradient of the radius w.r.t. the unit sphere coordinates WILL BE ADDED TO
+    pub fn radius(
+        &self,
+        unit_sample_point: &Vector3,
+        seed: &NoiseSeed,
+        gradient: &mut Vector3,
+    ) -> f32 {
+        self.precise_radius(&Vector3d::from(unit_sample_point), seed, gradient) as f32
+    }
+
+    /// Same as `radius` in double precision, as the result is scaled by the radius of the planet
+    /// * `unit_sample_point` - The direction on the unit sphere
+    /// * `seed` - The seed of the planet
+    /// * `gradient` - The gradient of the radius w.r.t. the unit sphere coordinates WILL BE ADDED TO
+    pub fn precise_radius(
+        &self,
+        unit_sample_point: &Vector3d,
+        seed: &NoiseSeed,
+        gradient: &mut Vector3,
+    ) -> f64 {
+        // the radius of an ellipsoid is 1 / sqrt(sum(u_i^2 / s_i^2))
+        let inverse_squared_scale = [self.scale_x, self.scale_y, self.scale_z]
+            .map(|scale| 1.0 / (scale as f64 * scale as f64));
+        let weighted_point = Vector3d::new(
+            unit_sample_point.x * inverse_squared_scale[0],
+            unit_sample_point.y * inverse_squared_scale[1],
+            unit_sample_point.z * inverse_squared_scale[2],
+        );
+        let ellipsoid_radius = 1.0 / f64::sqrt(Vector3d::dot(unit_sample_point, &weighted_point));
+        let ellipsoid_gradient = (&weighted_point * -ellipsoid_radius.powi(3)).to_f32();
+
+        if self.lump_amplitude <= 0.0 {
+            *gradient += &ellipsoid_gradient;
+            return ellipsoid_radius;
+        }
+
+        let lumps = simplex_noise_layer(self.lump_frequency, LUMP_OCTAVES, 2.0, 2.0, 1.0);
+        let mut lump_gradient = Vector3::zero();
+        let lump = lumps.sample(
+            unit_sample_point,
+            seed.derive(Salt::Lumps),
+            &mut lump_gradient,
+        );
+
+        // the noise in [0, 1] is centered so that the mean radius is kept
+        let lump_offset = self.lump_amplitude * (2.0 * lump - 1.0);
+        lump_gradient *= 2.0 * self.lump_amplitude;
+
+        *gradient +=
+            ellipsoid_gradient * (1.0 + lump_offset) + lump_gradient * ellipsoid_radius as f32;
+        ellipsoid_radius * (1.0 + lump_offset as f64)
+    }
+}
+
+#[wasm_bindgen]
+impl BaseShape {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BaseShape {
+        BaseShape::default()
+    }
+
+    pub fn sphere() -> BaseShape {
+        BaseShape::SPHERE
+    }
+
+    /// A triaxial ellipsoid with the given semi-axes relative to the planet radius
+    pub fn ellipsoid(scale_x: f32, scale_y: f32, scale_z: f32) -> BaseShape {
+        BaseShape {
+            scale_x,
+            scale_y,
+            scale_z,
+            ..BaseShape::SPHERE
+        }
+    }
+
+    /// A body flattened at the poles by its rotation
+    /// * `flattening` - The relative difference between the equatorial and polar radii
+    pub fn oblate(flattening: f32) -> BaseShape {
+        BaseShape::ellipsoid(1.0, 1.0 - flattening, 1.0)
+    }
+
+    /// A lumpy body such as an asteroid or a comet nucleus
+    /// * `amplitude` - The relative amplitude of the lumps in [0, 1[
+    /// * `frequency` - The frequency of the lumps on the unit sphere
+    pub fn lumpy(amplitude: f32, frequency: f32) -> BaseShape {
+        BaseShape {
+            lump_amplitude: amplitude,
+            lump_frequency: frequency,
+            ..BaseShape::SPHERE
+        }
+    }
+}
+
+impl Default for BaseShape {
+    fn default() -> Self {
+        BaseShape::SPHERE
+    }
+}
@@ -0,0 +1,323 @@
+use crate::landscape::continent_layer::{continent_layer, ContinentLayer};
+use crate::landscape::simple_fractal_layer::SimpleFractalLayer;
+use crate::landscape::simplex_noise_layer::{simplex_noise_layer, SimplexLayer};
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::{NoiseSeed, Salt};
+use crate::terrain_settings::TerrainSettings;
+use crate::utils::math::smoothstep;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// The number of temperature bands of the Whittaker table (from cold to hot)
+pub const TEMPERATURE_BANDS: usize = 4;
+/// The number of moisture bands of the Whittaker table (from arid to wet)
+pub const MOISTURE_BANDS: usize = 4;
+/// The number of floats written per vertex in the biome buffer: dominant id, dominant weight, secondary id, secondary weight
+pub const BIOME_STRIDE: usize = 4;
+
+/// The biome identifiers of the default table
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Ocean = 0,
+    Ice = 1,
+    Tundra = 2,
+    Taiga = 3,
+    Grassland = 4,
+    TemperateForest = 5,
+    TemperateRainforest = 6,
+    Desert = 7,
+    Savanna = 8,
+    TropicalSeasonalForest = 9,
+    TropicalRainforest = 10,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[wasm_bindgen]
+pub struct BiomeSettings {
+    /// The temperature at sea level on the equator (°C)
+    pub equator_temperature: f32,
+    /// The temperature at sea level on the poles (°C)
+    pub pole_temperature: f32,
+    /// The temperature drop per meter of altitude (°C/m)
+    pub temperature_lapse_rate: f32,
+    /// The frequency of the moisture noise on the unit sphere
+    pub moisture_frequency: f32,
+    /// How much the proximity of the ocean contributes to the moisture, in [0, 1]
+    pub ocean_moisture_weight: f32,
+    /// The temperature at the center of the coldest band of the table (°C)
+    pub min_temperature: f32,
+    /// The temperature at the center of the hottest band of the table (°C)
+    pub max_temperature: f32,
+    table: [[u8; MOISTURE_BANDS]; TEMPERATURE_BANDS],
+}
+
+impl BiomeSettings {
+    /// An Earth-like climate mapped through the Whittaker biome diagram
+    pub const WHITTAKER: BiomeSettings = BiomeSettings {
+        equator_temperature: 30.0,
+        pole_temperature: -30.0,
+        temperature_lapse_rate: 6.5e-3,
+        moisture_frequency: 2.0,
+        ocean_moisture_weight: 0.4,
+        min_temperature: -15.0,
+        max_temperature: 25.0,
+        table: [
+            [
+                Biome::Tundra as u8,
+                Biome::Tundra as u8,
+                Biome::Ice as u8,
+                Biome::Ice as u8,
+            ],
+            [
+                Biome::Grassland as u8,
+                Biome::Grassland as u8,
+                Biome::Taiga as u8,
+                Biome::Taiga as u8,
+            ],
+            [
+                Biome::Desert as u8,
+                Biome::Grassland as u8,
+                Biome::TemperateForest as u8,
+                Biome::TemperateRainforest as u8,
+            ],
+            [
+                Biome::Desert as u8,
+                Biome::Savanna as u8,
+                Biome::TropicalSeasonalForest as u8,
+                Biome::TropicalRainforest as u8,
+            ],
+        ],
+    };
+
+    /// The biome identifier of a cell of the Whittaker table
+    pub fn biome(
+        &self,
+        temperature_band: usize,
+        moisture_band: usize,
+    ) -> Result<u8, BiomeCellError> {
+        self.table
+            .get(temperature_band)
+            .and_then(|row| row.get(moisture_band))
+            .copied()
+            .ok_or(BiomeCellError {
+                temperature_band,
+                moisture_band,
+            })
+    }
+
+    /// Sets the biome identifier of a cell of the Whittaker table
+    pub fn set_biome(
+        &mut self,
+        temperature_band: usize,
+        moisture_band: usize,
+        biome: u8,
+    ) -> Result<(), BiomeCellError> {
+        let cell = self
+            .table
+            .get_mut(temperature_band)
+            .and_then(|row| row.get_mut(moisture_band))
+            .ok_or(BiomeCellError {
+                temperature_band,
+                moisture_band,
+            })?;
+        *cell = biome;
+        Ok(())
+    }
+}
+
+/// A cell outside of the Whittaker table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BiomeCellError {
+    pub temperature_band: usize,
+    pub moisture_band: usize,
+}
+
+impl fmt::Display for BiomeCellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the biome table has {TEMPERATURE_BANDS} temperature bands and {MOISTURE_BANDS} moisture bands, got the cell ({}, {})",
+            self.temperature_band, self.moisture_band
+        )
+    }
+}
+
+impl std::error::Error for BiomeCellError {}
+
+#[wasm_bindgen]
+impl BiomeSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BiomeSettings {
+        BiomeSettings::default()
+    }
+
+    /// See `BiomeSettings::biome`, throws when the cell is outside of the table
+    #[wasm_bindgen(js_name = biome)]
+    pub fn biome_js(&self, temperature_band: usize, moisture_band: usize) -> Result<u8, JsError> {
+        Ok(self.biome(temperature_band, moisture_band)?)
+    }
+
+    /// See `BiomeSettings::set_biome`, throws when the cell is outside of the table
+    #[wasm_bindgen(js_name = set_biome)]
+    pub fn set_biome_js(
+        &mut self,
+        temperature_band: usize,
+        moisture_band: usize,
+        biome: u8,
+    ) -> Result<(), JsError> {
+        Ok(self.set_biome(temperature_band, moisture_band, biome)?)
+    }
+}
+
+impl Default for BiomeSettings {
+    fn default() -> Self {
+        BiomeSettings::WHITTAKER
+    }
+}
+
+/// The climate and biomes at a vertex
+pub struct BiomeSample {
+    /// The temperature (°C)
+    pub temperature: f32,
+    /// The moisture in [0, 1]
+    pub moisture: f32,
+    /// The two most represented biomes and their weights, which sum to 1
+    pub biomes: [(u8, f32); 2],
+}
+
+/// Classifies points of the surface using their climate
+pub struct BiomeClassifier {
+    settings: BiomeSettings,
+    sea_level: f32,
+    continents: ContinentLayer,
+    moisture: SimpleFractalLayer<SimplexLayer>,
+}
+
+impl BiomeClassifier {
+    pub fn new(terrain_settings: &TerrainSettings) -> BiomeClassifier {
+        let settings = terrain_settings.biomes;
+        BiomeClassifier {
+            settings,
+            sea_level: terrain_settings.sea_level,
+            continents: continent_layer(
+                terrain_settings.continents_frequency,
+                terrain_settings.continents_fragmentation,
+            ),
+            moisture: simplex_noise_layer(settings.moisture_frequency, 4, 2.0, 2.0, 1.0),
+        }
+    }
+
+    /// The temperature decreases toward the poles and with the altitude
+    /// * `unit_sample_point` - The position of the sample on the unit sphere (the poles are along the y axis)
+    /// * `elevation` - The elevation above the planet radius
+    pub fn temperature(&self, unit_sample_point: &Vector3, elevation: f32) -> f32 {
+        let cos_latitude = f32::sqrt(f32::max(
+            1.0 - unit_sample_point.y * unit_sample_point.y,
+            0.0,
+        ));
+        let sea_level_temperature = self.settings.pole_temperature
+            + (self.settings.equator_temperature - self.settings.pole_temperature) * cos_latitude;
+        let altitude = f32::max(elevation - self.sea_level, 0.0);
+        sea_level_temperature - self.settings.temperature_lapse_rate * altitude
+    }
+
+    /// The moisture is a noise field raised close to the ocean
+    /// * `unit_sample_point` - The position of the sample on the unit sphere
+    /// * `seed` - The seed of the planet
+    pub fn moisture(&self, unit_sample_point: &Vector3, seed: &NoiseSeed) -> f32 {
+        // the biomes vary over kilometers, single precision directions are enough
+        let unit_sample_point = &Vector3d::from(unit_sample_point);
+        let noise = self.moisture.sample(
+            unit_sample_point,
+            seed.derive(Salt::Moisture),
+            &mut Vector3::zero(),
+        );
+
+        // the continent noise keeps increasing inland past the coastline, which makes it a proxy for the distance to the ocean
+        let inland = smoothstep(
+            0.4,
+            0.8,
+            self.continents.sample(
+                unit_sample_point,
+                seed.derive(Salt::Continents),
+                &mut Vector3::zero(),
+            ),
+            &mut Vector3::zero(),
+        );
+
+        let weight = self.settings.ocean_moisture_weight.clamp(0.0, 1.0);
+        (noise * (1.0 - weight) + (1.0 - inland) * weight).clamp(0.0, 1.0)
+    }
+
+    /// Classifies a point of the surface
+    /// * `unit_sample_point` - The position of the sample on the unit sphere
+    /// * `elevation` - The elevation above the planet radius (vertices below sea level are ocean)
+    /// * `seed` - The seed of the planet
+    pub fn classify(
+        &self,
+        unit_sample_point: &Vector3,
+        elevation: f32,
+        seed: &NoiseSeed,
+    ) -> BiomeSample {
+        let temperature = self.temperature(unit_sample_point, elevation);
+        let moisture = self.moisture(unit_sample_point, seed);
+
+        if elevation < self.sea_level {
+            return BiomeSample {
+                temperature,
+                moisture,
+                biomes: [(Biome::Ocean as u8, 1.0), (Biome::Ocean as u8, 0.0)],
+            };
+        }
+
+        // continuous coordinates in the table, the cell centers being at integer values
+        let temperature_range = f32::max(
+            self.settings.max_temperature - self.settings.min_temperature,
+            1e-3,
+        );
+        let temperature_coordinate = ((temperature - self.settings.min_temperature)
+            / temperature_range
+            * (TEMPERATURE_BANDS - 1) as f32)
+            .clamp(0.0, (TEMPERATURE_BANDS - 1) as f32);
+        let moisture_coordinate =
+            (moisture * MOISTURE_BANDS as f32 - 0.5).clamp(0.0, (MOISTURE_BANDS - 1) as f32);
+
+        let t0 = usize::min(temperature_coordinate as usize, TEMPERATURE_BANDS - 2);
+        let m0 = usize::min(moisture_coordinate as usize, MOISTURE_BANDS - 2);
+        let ft = temperature_coordinate - t0 as f32;
+        let fm = moisture_coordinate - m0 as f32;
+
+        // bilinear weights of the 4 closest cells, merged by biome
+        let mut weights: Vec<(u8, f32)> = Vec::with_capacity(4);
+        for (biome, weight) in [
+            (self.settings.table[t0][m0], (1.0 - ft) * (1.0 - fm)),
+            (self.settings.table[t0 + 1][m0], ft * (1.0 - fm)),
+            (self.settings.table[t0][m0 + 1], (1.0 - ft) * fm),
+            (self.settings.table[t0 + 1][m0 + 1], ft * fm),
+        ] {
+            match weights.iter_mut().find(|(id, _)| *id == biome) {
+                Some((_, total)) => *total += weight,
+                None => weights.push((biome, weight)),
+            }
+        }
+        weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let dominant = weights[0];
+        let secondary = weights.get(1).cloned().unwrap_or((dominant.0, 0.0));
+        let total = dominant.1 + secondary.1;
+
+        BiomeSample {
+            temperature,
+            moisture,
+            biomes: [
+                (dominant.0, dominant.1 / total),
+                (secondary.0, secondary.1 / total),
+            ],
+        }
+    }
+}
@@ -1,5 +1,10 @@
+use crate::seed::Seed;
 use crate::terrain_settings::TerrainSettings;
 use crate::utils::direction::Direction;
+use crate::validation::{
+    ValidationError, ValidationErrors, MAX_CHUNK_DEPTH, MAX_RADIAL_LEVELS, MAX_RESOLUTION,
+    MIN_RESOLUTION,
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
@@ -17,13 +22,76 @@ pub struct BuildData {
     /// The z position of the chunk on the cube sphere
     pub chunk_cube_position_z: f32,
     /// The seed of the planet we are generating
-    pub planet_seed: f32,
+    pub planet_seed: Seed,
     /// The resolution of each chunk (x*x vertices)
     pub resolution: u32,
     /// The settings guiding the terrain generation
     pub terrain_settings: TerrainSettings,
 }
 
+impl BuildData {
+    /// Checks that a chunk can be built from the data, reporting every violated constraint (terrain settings included)
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = Vec::new();
+
+        if !(self.planet_diameter.is_finite() && self.planet_diameter > 0.0) {
+            errors.push(ValidationError::InvalidPlanetDiameter(self.planet_diameter));
+        }
+        if self.chunk_depth > MAX_CHUNK_DEPTH {
+            errors.push(ValidationError::ChunkDepthTooLarge(self.chunk_depth));
+        }
+        if ![
+            self.chunk_cube_position_x,
+            self.chunk_cube_position_y,
+            self.chunk_cube_position_z,
+        ]
+        .iter()
+        .all(|coordinate| coordinate.is_finite())
+        {
+            errors.push(ValidationError::InvalidChunkPosition);
+        }
+        if !self.planet_seed.offset().is_finite() {
+            errors.push(ValidationError::InvalidSeed(self.planet_seed.offset()));
+        }
+        if self.resolution < MIN_RESOLUTION {
+            errors.push(ValidationError::ResolutionTooLow(self.resolution));
+        } else if self.resolution > MAX_RESOLUTION {
+            errors.push(ValidationError::ResolutionTooHigh(self.resolution));
+        }
+
+        if let Err(ValidationErrors(settings_errors)) = self.terrain_settings.validate() {
+            errors.extend(settings_errors);
+        }
+
+        ValidationErrors::check(errors)
+    }
+
+    /// Checks that a volumetric chunk can be built from the data: on top of `validate`,
+    /// the caves must not span more radial levels than `MAX_RADIAL_LEVELS` at the spacing of the chunk
+    pub fn validate_volumetric(&self) -> Result<(), ValidationErrors> {
+        self.validate()?;
+
+        let nb_cave_levels =
+            (self.terrain_settings.caves.depth.max(0.0) / self.spacing()).ceil() as usize;
+        if nb_cave_levels > MAX_RADIAL_LEVELS {
+            return Err(ValidationErrors(vec![
+                ValidationError::TooManyRadialLevels(nb_cave_levels),
+            ]));
+        }
+        Ok(())
+    }
+
+    /// The distance between two vertices of the chunk on the cube sphere
+    pub(crate) fn spacing(&self) -> f32 {
+        self.chunk_size() / (self.resolution - 1) as f32
+    }
+
+    /// The length of the side of the chunk on the cube sphere
+    pub(crate) fn chunk_size(&self) -> f32 {
+        self.planet_diameter / (1_u64 << self.chunk_depth) as f32
+    }
+}
+
 #[wasm_bindgen]
 impl BuildData {
     #[wasm_bindgen(constructor)]
@@ -35,7 +103,7 @@ impl BuildData {
         chunk_cube_position_x: f32,
         chunk_cube_position_y: f32,
         chunk_cube_position_z: f32,
-        planet_seed: f32,
+        planet_seed: Seed,
         resolution: u32,
         terrain_settings: TerrainSettings,
     ) -> BuildData {
@@ -0,0 +1,113 @@
+use crate::biomes::BIOME_STRIDE;
+use crate::build_data::BuildData;
+use crate::splat::SPLAT_STRIDE;
+use wasm_bindgen::prelude::*;
+
+/// The number of floats written per scattered instance: position and normal
+pub const SCATTER_STRIDE: usize = 6;
+
+/// The lengths of the buffers given to `build_chunk_vertex_data` for a chunk.
+/// The vertices and the triangles of the skirt come after those of the grid, so the skirt can be drawn separately.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub struct ChunkLayout {
+    #[wasm_bindgen(readonly)]
+    pub nb_vertices_per_side: usize,
+    /// The number of vertices of the grid and the skirt
+    #[wasm_bindgen(readonly)]
+    pub nb_vertices: usize,
+    #[wasm_bindgen(readonly)]
+    pub positions_length: usize,
+    #[wasm_bindgen(readonly)]
+    pub normals_length: usize,
+    #[wasm_bindgen(readonly)]
+    pub indices_length: usize,
+    /// The length of the optional biome buffer
+    #[wasm_bindgen(readonly)]
+    pub biomes_length: usize,
+    /// The length of the optional splat weight buffer
+    #[wasm_bindgen(readonly)]
+    pub splat_weights_length: usize,
+    /// The number of instances the scatter buffer can hold
+    #[wasm_bindgen(readonly)]
+    pub max_nb_instances: usize,
+    #[wasm_bindgen(readonly)]
+    pub scatter_length: usize,
+    /// The first vertex of the skirt (the number of vertices of the grid)
+    #[wasm_bindgen(readonly)]
+    pub skirt_vertex_offset: usize,
+    /// The number of vertices of the skirt (0 without skirt)
+    #[wasm_bindgen(readonly)]
+    pub skirt_vertex_count: usize,
+    /// The first index of the triangles of the skirt in the index buffer (the number of indices of the grid)
+    #[wasm_bindgen(readonly)]
+    pub skirt_index_offset: usize,
+    /// The number of indices of the triangles of the skirt (0 without skirt)
+    #[wasm_bindgen(readonly)]
+    pub skirt_index_count: usize,
+}
+
+#[wasm_bindgen]
+impl ChunkLayout {
+    /// * `resolution` - The number of vertices along a side of the chunk
+    /// * `chunk_size` - The length of the side of the chunk
+    /// * `has_skirt` - Whether a skirt hides the cracks between the chunk and its neighbours
+    /// * `scatter_per_square_meter` - The density of the scattered instances (0 disables the scattering)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        resolution: u32,
+        chunk_size: f32,
+        has_skirt: bool,
+        scatter_per_square_meter: f32,
+    ) -> ChunkLayout {
+        let nb_vertices_per_side = resolution as usize;
+        let nb_subdivisions = nb_vertices_per_side.saturating_sub(1);
+
+        let grid_vertex_count = nb_vertices_per_side * nb_vertices_per_side;
+        let grid_index_count = nb_subdivisions * nb_subdivisions * 2 * 3;
+        let (skirt_vertex_count, skirt_index_count) = if has_skirt {
+            (4 * nb_vertices_per_side, 4 * nb_subdivisions * 2 * 3)
+        } else {
+            (0, 0)
+        };
+        let nb_vertices = grid_vertex_count + skirt_vertex_count;
+
+        // the displaced surface is larger than the flat chunk, twice its area leaves room for steep terrain
+        let flat_area = chunk_size * chunk_size;
+        let max_nb_instances = f32::floor(flat_area * scatter_per_square_meter * 2.0) as usize;
+
+        ChunkLayout {
+            nb_vertices_per_side,
+            nb_vertices,
+            positions_length: 3 * nb_vertices,
+            normals_length: 3 * nb_vertices,
+            indices_length: grid_index_count + skirt_index_count,
+            biomes_length: BIOME_STRIDE * nb_vertices,
+            splat_weights_length: SPLAT_STRIDE * nb_vertices,
+            max_nb_instances,
+            scatter_length: SCATTER_STRIDE * max_nb_instances,
+            skirt_vertex_offset: grid_vertex_count,
+            skirt_vertex_count,
+            skirt_index_offset: grid_index_count,
+            skirt_index_count,
+        }
+    }
+
+    /// The layout of the chunk described by the build data
+    pub fn for_chunk(
+        data: &BuildData,
+        has_skirt: bool,
+        scatter_per_square_meter: f32,
+    ) -> ChunkLayout {
+        ChunkLayout::new(
+            data.resolution,
+            data.chunk_size(),
+            has_skirt,
+            scatter_per_square_meter,
+        )
+    }
+
+    pub fn has_skirt(&self) -> bool {
+        self.skirt_vertex_count > 0
+    }
+}
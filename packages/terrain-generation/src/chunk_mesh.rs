@@ -0,0 +1,146 @@
+use crate::build_data::BuildData;
+use crate::chunk_layout::{ChunkLayout, SCATTER_STRIDE};
+use crate::fill_chunk_buffers;
+use crate::utils::vector3::Vector3;
+use crate::validation::BuildError;
+
+/// An instance scattered on the surface of a chunk
+#[derive(Clone, Debug)]
+pub struct ScatteredPoint {
+    pub position: Vector3,
+    pub normal: Vector3,
+}
+
+/// The axis-aligned box enclosing the vertices of a chunk
+#[derive(Clone, Debug)]
+pub struct Bounds {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Bounds {
+    fn enclosing(points: &[Vector3]) -> Bounds {
+        let mut bounds = Bounds {
+            min: Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        };
+        for point in points {
+            bounds.min = Vector3::new(
+                bounds.min.x.min(point.x),
+                bounds.min.y.min(point.y),
+                bounds.min.z.min(point.z),
+            );
+            bounds.max = Vector3::new(
+                bounds.max.x.max(point.x),
+                bounds.max.y.max(point.y),
+                bounds.max.z.max(point.z),
+            );
+        }
+        bounds
+    }
+
+    pub fn center(&self) -> Vector3 {
+        (&self.min + &self.max) * 0.5
+    }
+
+    pub fn contains(&self, point: &Vector3) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+}
+
+/// The mesh of a chunk owning its buffers, for native consumers.
+/// The positions are relative to the chunk like the buffers of `build_chunk_vertex_data`.
+#[derive(Clone, Debug)]
+pub struct ChunkMesh {
+    pub positions: Vec<Vector3>,
+    pub normals: Vec<Vector3>,
+    /// Three indices per triangle, the triangles of the skirt (if any) come after those of the grid
+    pub indices: Vec<u16>,
+    pub scatter: Vec<ScatteredPoint>,
+    pub bounds: Bounds,
+}
+
+impl ChunkMesh {
+    pub fn nb_triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// The vertex indices of each triangle
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|i| triangle[i] as usize))
+    }
+
+    /// The vertex positions of each triangle
+    pub fn triangle_positions(&self) -> impl Iterator<Item = [&Vector3; 3]> + '_ {
+        self.triangles()
+            .map(|triangle| triangle.map(|index| &self.positions[index]))
+    }
+}
+
+/// Builds the mesh of a chunk in buffers of its own
+/// * `data` - The data needed to guide the build process
+/// * `has_skirt` - Whether a skirt hides the cracks between the chunk and its neighbours
+/// * `scatter_per_square_meter` - The density of the scattered instances (0 disables the scattering)
+///
+/// Unlike the buffers of `build_chunk_vertex_data`, the scatter grows to hold all the instances of steep chunks
+pub fn build_chunk_mesh(
+    data: &BuildData,
+    has_skirt: bool,
+    scatter_per_square_meter: f32,
+) -> Result<ChunkMesh, BuildError> {
+    let layout = ChunkLayout::for_chunk(data, has_skirt, scatter_per_square_meter);
+    let mut positions = vec![0.0; layout.positions_length];
+    let mut normals = vec![0.0; layout.normals_length];
+    let mut indices = vec![0; layout.indices_length];
+    let mut scatter = vec![0.0; layout.scatter_length];
+
+    let mut fill = |scatter: &mut [f32]| {
+        fill_chunk_buffers(
+            data,
+            None,
+            &mut positions,
+            &mut indices,
+            &mut normals,
+            &mut [],
+            &mut [],
+            scatter,
+            scatter_per_square_meter,
+        )
+    };
+    let nb_instances = match fill(&mut scatter) {
+        // steep terrain has more area than the layout expects: build again with room for every instance
+        Err(BuildError::TooManyInstances { nb_instances, .. }) => {
+            scatter = vec![0.0; SCATTER_STRIDE * nb_instances];
+            fill(&mut scatter)?
+        }
+        result => result?,
+    };
+
+    let to_vectors = |buffer: &[f32]| -> Vec<Vector3> {
+        buffer
+            .chunks_exact(3)
+            .map(|v| Vector3::new(v[0], v[1], v[2]))
+            .collect()
+    };
+    let positions = to_vectors(&positions);
+    let scatter = scatter
+        .chunks_exact(SCATTER_STRIDE)
+        .take(nb_instances)
+        .map(|instance| ScatteredPoint {
+            position: Vector3::new(instance[0], instance[1], instance[2]),
+            normal: Vector3::new(instance[3], instance[4], instance[5]),
+        })
+        .collect();
+
+    Ok(ChunkMesh {
+        bounds: Bounds::enclosing(&positions),
+        normals: to_vectors(&normals),
+        positions,
+        indices,
+        scatter,
+    })
+}
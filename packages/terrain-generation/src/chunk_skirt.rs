@@ -88,10 +88,10 @@ pub fn append_chunk_skirt(
         for &border_vertex_index in border_loop {
             let source_position = get_vertex(positions, border_vertex_index);
             let source_normal = get_vertex(normals, border_vertex_index);
+            // the offset is applied relative to the chunk to keep the precision of the vertex
             let source_planet_position = chunk_sphere_position + &source_position;
             let inward_direction = source_planet_position.normalize_to_new();
-            let skirt_position = (source_planet_position - inward_direction * skirt_depth)
-                - chunk_sphere_position.clone();
+            let skirt_position = source_position - inward_direction * skirt_depth;
 
             positions[3 * next_vertex_index] = skirt_position.x;
             positions[3 * next_vertex_index + 1] = skirt_position.y;
@@ -120,3 +120,23 @@ pub fn append_chunk_skirt(
         }
     }
 }
+
+/// Copies the attributes of the border vertices to their skirt vertices, in the order used by `append_chunk_skirt`
+/// * `attributes` - The per-vertex attribute buffer, including the skirt vertices
+/// * `stride` - The number of floats per vertex
+pub fn copy_border_attributes(attributes: &mut [f32], stride: usize, nb_vertices_per_row: usize) {
+    let border_loops = build_border_loops(nb_vertices_per_row);
+    let base_vertex_count = nb_vertices_per_row * nb_vertices_per_row;
+    assert_eq!(
+        attributes.len(),
+        (base_vertex_count + border_loops.len() * nb_vertices_per_row) * stride
+    );
+
+    let border_vertices = border_loops.iter().flatten();
+    for (skirt_vertex_index, &border_vertex_index) in (base_vertex_count..).zip(border_vertices) {
+        attributes.copy_within(
+            stride * border_vertex_index..stride * (border_vertex_index + 1),
+            stride * skirt_vertex_index,
+        );
+    }
+}
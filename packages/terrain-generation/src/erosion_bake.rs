@@ -0,0 +1,627 @@
+use crate::landscape::make_terrain_function::make_terrain_row_function;
+use crate::seed::{NoiseSeed, Seed};
+use crate::terrain_settings::TerrainSettings;
+use crate::utils::random::pcg3d;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use crate::validation::ValidationErrors;
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use wasm_bindgen::prelude::*;
+
+const NB_FACES: usize = 6;
+
+// droplet parameters from Sebastian Lague's hydraulic erosion (https://github.com/SebLague/Hydraulic-Erosion)
+const INERTIA: f32 = 0.05;
+const SEDIMENT_CAPACITY_FACTOR: f32 = 4.0;
+const MIN_SEDIMENT_CAPACITY: f32 = 0.01;
+const ERODE_SPEED: f32 = 0.3;
+const DEPOSIT_SPEED: f32 = 0.3;
+const EVAPORATE_SPEED: f32 = 0.01;
+const GRAVITY: f32 = 4.0;
+
+/// The fraction of the material above the talus slope moved at each thermal iteration
+const THERMAL_RATE: f32 = 0.25;
+
+#[derive(Copy, Clone)]
+#[wasm_bindgen]
+pub struct ErosionBakeSettings {
+    /// The number of samples along each edge of a cube face
+    pub resolution: u32,
+    /// The number of water droplets simulated over the whole planet
+    pub nb_droplets: u32,
+    /// The maximum number of steps of a droplet
+    pub droplet_lifetime: u32,
+    /// The number of thermal erosion passes over the whole planet
+    pub thermal_iterations: u32,
+    /// The slope (height over horizontal distance) above which material slides down
+    pub talus_slope: f32,
+}
+
+#[wasm_bindgen]
+impl ErosionBakeSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ErosionBakeSettings {
+        ErosionBakeSettings::default()
+    }
+}
+
+impl Default for ErosionBakeSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 129,
+            nb_droplets: 100_000,
+            droplet_lifetime: 30,
+            thermal_iterations: 20,
+            talus_slope: 0.6,
+        }
+    }
+}
+
+/// The normal of the face and the directions of its u and v axes
+fn face_basis(face: usize) -> (Vector3, Vector3, Vector3) {
+    match face {
+        0 => (
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ),
+        1 => (
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ),
+        2 => (
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ),
+        3 => (
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ),
+        4 => (
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ),
+        _ => (
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ),
+    }
+}
+
+/// The face of the cube the given direction points to
+fn face_of(p: &Vector3) -> usize {
+    let (x, y, z) = (p.x.abs(), p.y.abs(), p.z.abs());
+    if x >= y && x >= z {
+        if p.x >= 0.0 {
+            0
+        } else {
+            1
+        }
+    } else if y >= z {
+        if p.y >= 0.0 {
+            2
+        } else {
+            3
+        }
+    } else if p.z >= 0.0 {
+        4
+    } else {
+        5
+    }
+}
+
+/// A scalar field stored on a grid over each face of the cube sphere. The samples along the edges of the faces are duplicated.
+struct CubeGrid {
+    resolution: usize,
+    faces: Vec<Vec<f32>>,
+}
+
+impl CubeGrid {
+    fn new(resolution: usize) -> CubeGrid {
+        CubeGrid {
+            resolution,
+            faces: vec![vec![0.0; resolution * resolution]; NB_FACES],
+        }
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        j * self.resolution + i
+    }
+
+    /// The position on the unit sphere of the given grid coordinates, which may lie outside of the face
+    fn grid_point(&self, face: usize, x: f32, y: f32) -> Vector3 {
+        let (normal, u_axis, v_axis) = face_basis(face);
+        let u = 2.0 * x / (self.resolution - 1) as f32 - 1.0;
+        let v = 2.0 * y / (self.resolution - 1) as f32 - 1.0;
+        (normal + &u_axis * u + &v_axis * v).normalize_to_new()
+    }
+
+    /// The grid coordinates of a point of the unit sphere on the given face and their gradients
+    fn project(&self, face: usize, p: &Vector3) -> (f32, f32, Vector3, Vector3) {
+        let (normal, u_axis, v_axis) = face_basis(face);
+        let height = Vector3::dot(p, &normal);
+        let u = Vector3::dot(p, &u_axis) / height;
+        let v = Vector3::dot(p, &v_axis) / height;
+
+        // d(p.t / p.n) = (t - (p.t / p.n) n) / p.n
+        let scale = (self.resolution - 1) as f32 / 2.0;
+        let x_gradient = (&u_axis - &(&normal * u)) * (scale / height);
+        let y_gradient = (&v_axis - &(&normal * v)) * (scale / height);
+
+        ((u + 1.0) * scale, (v + 1.0) * scale, x_gradient, y_gradient)
+    }
+
+    /// The cell containing the point and the bilinear weights of its corners
+    fn cell(&self, p: &Vector3) -> (usize, usize, usize, f32, f32, Vector3, Vector3) {
+        let face = face_of(p);
+        let (x, y, x_gradient, y_gradient) = self.project(face, p);
+        let last = (self.resolution - 1) as f32;
+        let (x, y) = (x.clamp(0.0, last), y.clamp(0.0, last));
+
+        let i = usize::min(x.floor() as usize, self.resolution - 2);
+        let j = usize::min(y.floor() as usize, self.resolution - 2);
+
+        (
+            face,
+            i,
+            j,
+            x - i as f32,
+            y - j as f32,
+            x_gradient,
+            y_gradient,
+        )
+    }
+
+    /// Bilinearly interpolates the field and adds its gradient with respect to the position on the unit sphere
+    fn sample(&self, p: &Vector3, gradient: &mut Vector3) -> f32 {
+        let (face, i, j, fx, fy, x_gradient, y_gradient) = self.cell(p);
+        let values = &self.faces[face];
+        let h00 = values[self.index(i, j)];
+        let h10 = values[self.index(i + 1, j)];
+        let h01 = values[self.index(i, j + 1)];
+        let h11 = values[self.index(i + 1, j + 1)];
+
+        let dx = (h10 - h00) * (1.0 - fy) + (h11 - h01) * fy;
+        let dy = (h01 - h00) * (1.0 - fx) + (h11 - h10) * fx;
+        *gradient += &x_gradient * dx + &y_gradient * dy;
+
+        h00 * (1.0 - fx) * (1.0 - fy)
+            + h10 * fx * (1.0 - fy)
+            + h01 * (1.0 - fx) * fy
+            + h11 * fx * fy
+    }
+
+    /// Distributes the amount over the corners of the cell containing the point
+    fn add(&mut self, p: &Vector3, amount: f32) {
+        let (face, i, j, fx, fy, _, _) = self.cell(p);
+        let (i00, i10) = (self.index(i, j), self.index(i + 1, j));
+        let (i01, i11) = (self.index(i, j + 1), self.index(i + 1, j + 1));
+        let values = &mut self.faces[face];
+        values[i00] += amount * (1.0 - fx) * (1.0 - fy);
+        values[i10] += amount * fx * (1.0 - fy);
+        values[i01] += amount * (1.0 - fx) * fy;
+        values[i11] += amount * fx * fy;
+    }
+
+    /// The sample next to the given one, crossing to the neighbouring face when needed
+    fn neighbor(&self, face: usize, i: usize, j: usize, di: i32, dj: i32) -> (usize, usize) {
+        let (x, y) = (i as i32 + di, j as i32 + dj);
+        let last = self.resolution as i32 - 1;
+        if (0..=last).contains(&x) && (0..=last).contains(&y) {
+            return (face, self.index(x as usize, y as usize));
+        }
+
+        let p = self.grid_point(face, x as f32, y as f32);
+        let neighbor_face = face_of(&p);
+        let (x, y, _, _) = self.project(neighbor_face, &p);
+        let x = (x.round() as i32).clamp(0, last) as usize;
+        let y = (y.round() as i32).clamp(0, last) as usize;
+        (neighbor_face, self.index(x, y))
+    }
+
+    /// Averages the duplicated samples along the edges of the faces so that the field is continuous
+    fn reconcile_edges(&mut self) {
+        let last = self.resolution - 1;
+        for face in 0..NB_FACES {
+            for j in 0..self.resolution {
+                for i in 0..self.resolution {
+                    if i != 0 && j != 0 && i != last && j != last {
+                        continue;
+                    }
+
+                    let p = self.grid_point(face, i as f32, j as f32);
+                    let largest = f32::max(p.x.abs(), f32::max(p.y.abs(), p.z.abs()));
+                    let copies = (0..NB_FACES)
+                        .filter(|&other| Vector3::dot(&p, &face_basis(other).0) >= largest - 1e-5)
+                        .map(|other| {
+                            let (x, y, _, _) = self.project(other, &p);
+                            let x = (x.round() as usize).min(last);
+                            let y = (y.round() as usize).min(last);
+                            (other, self.index(x, y))
+                        })
+                        .collect::<Vec<(usize, usize)>>();
+
+                    let average = copies
+                        .iter()
+                        .map(|&(other, index)| self.faces[other][index])
+                        .sum::<f32>()
+                        / copies.len() as f32;
+                    for (other, index) in copies {
+                        self.faces[other][index] = average;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A uniformly distributed point on the unit sphere for the given droplet
+fn droplet_start(index: u32, seed: u32) -> Vector3 {
+    let (seed_x, seed_y, seed_z) = pcg3d(seed, seed, seed);
+    let (a, b, _) = pcg3d(index ^ seed_x, seed_y, seed_z);
+    let z = 2.0 * (a as f32 / u32::MAX as f32) - 1.0;
+    let phi = 2.0 * PI * (b as f32 / u32::MAX as f32);
+    let radius = f32::sqrt(1.0 - z * z);
+    Vector3::new(radius * f32::cos(phi), radius * f32::sin(phi), z)
+}
+
+/// Removes the component of the vector along the normal of the sphere
+fn tangent(vector: &Vector3, normal: &Vector3) -> Vector3 {
+    vector - &(normal * Vector3::dot(vector, normal))
+}
+
+/// Simulates a single water droplet carving and depositing sediment
+/// * `heights` - The elevation in units of cell length
+/// * `cell_angle` - The angle between two neighbouring samples
+fn simulate_droplet(heights: &mut CubeGrid, start: Vector3, lifetime: u32, cell_angle: f32) {
+    let mut position = start;
+    let mut direction = Vector3::zero();
+    let mut speed = 1.0;
+    let mut water = 1.0;
+    let mut sediment = 0.0;
+
+    for _ in 0..lifetime {
+        let mut gradient = Vector3::zero();
+        let height = heights.sample(&position, &mut gradient);
+
+        // the slope per cell along the surface
+        let slope = tangent(&gradient, &position) * cell_angle;
+        direction = &direction * INERTIA - &slope * (1.0 - INERTIA);
+        let length = direction.length();
+        if length < 1e-6 {
+            break;
+        }
+        direction /= length;
+
+        let new_position = (&position + &(&direction * cell_angle)).normalize_to_new();
+        let delta_height = heights.sample(&new_position, &mut Vector3::zero()) - height;
+
+        let capacity = f32::max(
+            -delta_height * speed * water * SEDIMENT_CAPACITY_FACTOR,
+            MIN_SEDIMENT_CAPACITY,
+        );
+
+        if sediment > capacity || delta_height > 0.0 {
+            // fill the pit when going uphill, otherwise drop the excess sediment
+            let amount = if delta_height > 0.0 {
+                f32::min(delta_height, sediment)
+            } else {
+                (sediment - capacity) * DEPOSIT_SPEED
+            };
+            sediment -= amount;
+            heights.add(&position, amount);
+        } else {
+            // never dig deeper than the downhill step to avoid creating pits
+            let amount = f32::min((capacity - sediment) * ERODE_SPEED, -delta_height);
+            sediment += amount;
+            heights.add(&position, -amount);
+        }
+
+        speed = f32::sqrt(f32::max(speed * speed - delta_height * GRAVITY, 0.0));
+        water *= 1.0 - EVAPORATE_SPEED;
+
+        position = new_position;
+        direction = tangent(&direction, &position);
+    }
+}
+
+/// Moves material down the slopes steeper than the talus slope
+/// * `heights` - The elevation in units of cell length
+fn thermal_erosion(heights: &mut CubeGrid, iterations: u32, talus_slope: f32) {
+    for _ in 0..iterations {
+        for face in 0..NB_FACES {
+            for j in 0..heights.resolution {
+                for i in 0..heights.resolution {
+                    let index = heights.index(i, j);
+                    for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                        let (neighbor_face, neighbor_index) = heights.neighbor(face, i, j, di, dj);
+                        let difference = heights.faces[face][index]
+                            - heights.faces[neighbor_face][neighbor_index];
+                        if difference <= talus_slope {
+                            continue;
+                        }
+
+                        let amount = THERMAL_RATE * (difference - talus_slope) / 2.0;
+                        heights.faces[face][index] -= amount;
+                        heights.faces[neighbor_face][neighbor_index] += amount;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The smallest resolution of a bake: the edges of a face need two samples
+pub const MIN_BAKE_RESOLUTION: u32 = 2;
+
+/// Why a bake could not be created
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErosionBakeError {
+    InvalidSettings(ValidationErrors),
+    ResolutionTooLow(u32),
+    InvalidDeltaBufferSize { length: usize, expected: usize },
+}
+
+impl fmt::Display for ErosionBakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErosionBakeError::InvalidSettings(errors) => write!(f, "{errors}"),
+            ErosionBakeError::ResolutionTooLow(value) => write!(
+                f,
+                "the bake resolution must be at least {MIN_BAKE_RESOLUTION}, got {value}"
+            ),
+            ErosionBakeError::InvalidDeltaBufferSize { length, expected } => write!(
+                f,
+                "Invalid erosion delta buffer size: deltas={length}, expected={expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ErosionBakeError {}
+
+fn check_resolution(resolution: u32) -> Result<usize, ErosionBakeError> {
+    if resolution < MIN_BAKE_RESOLUTION {
+        return Err(ErosionBakeError::ResolutionTooLow(resolution));
+    }
+    Ok(resolution as usize)
+}
+
+/// The elevation change of a planet caused by hydraulic and thermal erosion, stored on a cubemap.
+/// A bake only applies to the planet it was computed for: same seed, terrain settings and radius.
+#[wasm_bindgen]
+pub struct ErosionBake {
+    seed: Seed,
+    terrain_settings: TerrainSettings,
+    planet_radius: f32,
+    deltas: CubeGrid,
+}
+
+#[wasm_bindgen]
+impl ErosionBake {
+    /// See `ErosionBake::bake`, throws when the resolution is too low
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        terrain_settings: &TerrainSettings,
+        seed: Seed,
+        planet_radius: f32,
+        bake_settings: &ErosionBakeSettings,
+    ) -> Result<ErosionBake, JsError> {
+        Ok(ErosionBake::bake(
+            terrain_settings,
+            seed,
+            planet_radius,
+            bake_settings,
+        )?)
+    }
+
+    /// See `ErosionBake::from_deltas`, throws when the resolution or the buffer size is invalid
+    #[wasm_bindgen(js_name = from_deltas)]
+    pub fn from_deltas_js(
+        terrain_settings: &TerrainSettings,
+        seed: Seed,
+        planet_radius: f32,
+        resolution: u32,
+        deltas: Vec<f32>,
+    ) -> Result<ErosionBake, JsError> {
+        Ok(ErosionBake::from_deltas(
+            terrain_settings,
+            seed,
+            planet_radius,
+            resolution,
+            deltas,
+        )?)
+    }
+
+    /// The elevation changes of the 6 faces one after the other
+    pub fn deltas(&self) -> Vec<f32> {
+        self.deltas.faces.concat()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn terrain_settings(&self) -> TerrainSettings {
+        self.terrain_settings
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn planet_radius(&self) -> f32 {
+        self.planet_radius
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn resolution(&self) -> u32 {
+        self.deltas.resolution as u32
+    }
+}
+
+impl ErosionBake {
+    /// Evaluates the terrain on the cubemap and erodes it. This is slow and meant to run in a worker.
+    /// * `terrain_settings` - The settings of the terrain to erode
+    /// * `seed` - The seed of the planet
+    /// * `planet_radius` - The radius of the planet
+    /// * `bake_settings` - The settings of the simulation
+    ///
+    /// Fails when the terrain settings are invalid (see `TerrainSettings::validate`) or the resolution too low
+    pub fn bake(
+        terrain_settings: &TerrainSettings,
+        seed: Seed,
+        planet_radius: f32,
+        bake_settings: &ErosionBakeSettings,
+    ) -> Result<ErosionBake, ErosionBakeError> {
+        terrain_settings
+            .validate()
+            .map_err(ErosionBakeError::InvalidSettings)?;
+        let resolution = check_resolution(bake_settings.resolution)?;
+        let terrain_function = make_terrain_row_function(*terrain_settings);
+        let noise_seed = NoiseSeed::new(seed);
+
+        // the simulation works with heights in units of cell length so that the slopes are dimensionless
+        let cell_angle = PI / 2.0 / (resolution - 1) as f32;
+        let cell_length = planet_radius * cell_angle;
+
+        let mut heights = CubeGrid::new(resolution);
+        for face in 0..NB_FACES {
+            for j in 0..resolution {
+                let unit_sample_points: Vec<Vector3d> = (0..resolution)
+                    .map(|i| Vector3d::from(&heights.grid_point(face, i as f32, j as f32)))
+                    .collect();
+                let mut positions: Vec<Vector3> =
+                    unit_sample_points.iter().map(Vector3d::to_f32).collect();
+                terrain_function(
+                    &unit_sample_points,
+                    &noise_seed,
+                    cell_angle,
+                    &mut positions,
+                    &mut vec![Vector3::zero(); resolution],
+                );
+                for (i, position) in positions.iter().enumerate() {
+                    let index = heights.index(i, j);
+                    heights.faces[face][index] = (position.length() - 1.0) / cell_length;
+                }
+            }
+        }
+        let initial_heights = heights.faces.clone();
+
+        for droplet in 0..bake_settings.nb_droplets {
+            let start = droplet_start(droplet, seed.bits());
+            simulate_droplet(
+                &mut heights,
+                start,
+                bake_settings.droplet_lifetime,
+                cell_angle,
+            );
+        }
+
+        thermal_erosion(
+            &mut heights,
+            bake_settings.thermal_iterations,
+            bake_settings.talus_slope,
+        );
+
+        heights.reconcile_edges();
+
+        for (face, initial_face) in heights.faces.iter_mut().zip(&initial_heights) {
+            for (height, initial_height) in face.iter_mut().zip(initial_face) {
+                *height = (*height - initial_height) * cell_length;
+            }
+        }
+
+        Ok(ErosionBake {
+            seed,
+            terrain_settings: *terrain_settings,
+            planet_radius,
+            deltas: heights,
+        })
+    }
+
+    /// Rebuilds a bake from the deltas of another one, to transfer it between workers
+    /// * `terrain_settings` - The settings of the eroded terrain
+    /// * `seed` - The seed of the planet
+    /// * `planet_radius` - The radius of the planet
+    /// * `resolution` - The number of samples along each edge of a cube face
+    /// * `deltas` - The elevation changes of the 6 faces one after the other
+    pub fn from_deltas(
+        terrain_settings: &TerrainSettings,
+        seed: Seed,
+        planet_radius: f32,
+        resolution: u32,
+        deltas: Vec<f32>,
+    ) -> Result<ErosionBake, ErosionBakeError> {
+        let resolution = check_resolution(resolution)?;
+        let expected = NB_FACES * resolution * resolution;
+        if deltas.len() != expected {
+            return Err(ErosionBakeError::InvalidDeltaBufferSize {
+                length: deltas.len(),
+                expected,
+            });
+        }
+
+        Ok(ErosionBake {
+            seed,
+            terrain_settings: *terrain_settings,
+            planet_radius,
+            deltas: CubeGrid {
+                resolution,
+                faces: deltas
+                    .chunks(resolution * resolution)
+                    .map(|face| face.to_vec())
+                    .collect(),
+            },
+        })
+    }
+
+    /// Returns the elevation change at the given point and adds its gradient to `gradient`
+    /// * `unit_sample_point` - The position of the sample on the unit sphere
+    /// * `gradient` - The recipient for the gradient of the elevation change
+    pub fn sample(&self, unit_sample_point: &Vector3, gradient: &mut Vector3) -> f32 {
+        self.deltas.sample(unit_sample_point, gradient)
+    }
+}
+
+/// Shared by all the threads so that the bake applies to the chunks built by any of them
+static EROSION_BAKE: RwLock<Option<Arc<ErosionBake>>> = RwLock::new(None);
+
+#[wasm_bindgen]
+/// Makes `build_chunk_vertex_data` apply the bake to the chunks of the planet it was computed for
+pub fn set_erosion_bake(bake: ErosionBake) {
+    *EROSION_BAKE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(bake));
+}
+
+#[wasm_bindgen]
+pub fn clear_erosion_bake() {
+    *EROSION_BAKE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// The current bake if it was computed for the planet with the given seed, terrain settings and radius
+pub fn erosion_bake(
+    seed: Seed,
+    terrain_settings: &TerrainSettings,
+    planet_radius: f32,
+) -> Option<Arc<ErosionBake>> {
+    EROSION_BAKE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_ref()
+        .filter(|bake| {
+            bake.seed == seed
+                && bake.terrain_settings == *terrain_settings
+                && bake.planet_radius == planet_radius
+        })
+        .cloned()
+}
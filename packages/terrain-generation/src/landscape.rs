@@ -1,7 +1,17 @@
+pub mod bathymetry_layer;
+pub mod combinator_layers;
 pub mod constant_layers;
 pub mod continent_layer;
+pub mod crater_layer;
+pub mod domain_warp_layer;
+pub mod erosion_layer;
+pub mod gradient_check;
 pub mod make_terrain_function;
 pub mod mountain_layer;
 pub mod simple_fractal_layer;
 pub mod simplex_noise_layer;
+pub mod tectonic_plates;
+pub mod terrace_layer;
+pub mod terrain_graph;
+pub mod terrain_layer;
 pub mod uber_noise_layer;
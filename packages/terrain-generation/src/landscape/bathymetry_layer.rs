@@ -0,0 +1,100 @@
+use crate::utils::math::{multiply, smoothstep};
+use crate::utils::vector3::Vector3;
+
+/// The value of the continent noise where the continent mask of the terrain function starts
+const CONTINENT_EDGE: f32 = 0.3;
+/// The width of the continental shelves in continent noise units
+const SHELF_WIDTH: f32 = 0.04;
+/// The width of the continental slopes in continent noise units
+const SLOPE_WIDTH: f32 = 0.06;
+
+/// Shapes the ocean floor away from the continents: shelves, slopes and abyssal plains
+pub struct BathymetryLayer {
+    /// The elevation of the sea above the planet radius
+    pub sea_level: f32,
+    /// The depth below sea level at the outer edge of the continental shelves
+    pub shelf_depth: f32,
+    /// The depth below sea level of the abyssal plains
+    pub abyssal_depth: f32,
+    pub shelf_width: f32,
+    pub slope_width: f32,
+}
+
+impl BathymetryLayer {
+    /// The depth of the sea floor below sea level
+    /// * `continent_noise` - The continent noise before masking, which decreases away from the coasts
+    /// * `gradient` - The gradient of the continent noise WILL STORE THE RESULT
+    pub fn depth(&self, continent_noise: f32, gradient: &mut Vector3) -> f32 {
+        let shelf_edge = CONTINENT_EDGE - self.shelf_width;
+
+        // the shelves deepen gently from the coast, then the slopes plunge down to the abyssal plains
+        let mut shelf_gradient = gradient.clone();
+        let shelf = 1.0
+            - smoothstep(
+                shelf_edge,
+                CONTINENT_EDGE,
+                continent_noise,
+                &mut shelf_gradient,
+            );
+
+        let mut slope_gradient = gradient.clone();
+        let slope = 1.0
+            - smoothstep(
+                shelf_edge - self.slope_width,
+                shelf_edge,
+                continent_noise,
+                &mut slope_gradient,
+            );
+
+        let depth = shelf * self.shelf_depth + slope * (self.abyssal_depth - self.shelf_depth);
+        *gradient = shelf_gradient * -self.shelf_depth
+            + slope_gradient * -(self.abyssal_depth - self.shelf_depth);
+        depth
+    }
+
+    /// Replaces the flat ocean floor under the given elevation with the sea floor
+    /// * `continent_noise` - The continent noise before masking
+    /// * `continent_noise_gradient` - The gradient of the continent noise
+    /// * `continent_mask` - The continent mask, 0 in the oceans and 1 on the continents
+    /// * `continent_mask_gradient` - The gradient of the continent mask
+    /// * `elevation` - The elevation of the continents
+    /// * `gradient` - The gradient of the elevation WILL STORE THE RESULT
+    pub fn apply(
+        &self,
+        continent_noise: f32,
+        continent_noise_gradient: &Vector3,
+        continent_mask: f32,
+        continent_mask_gradient: &Vector3,
+        elevation: f32,
+        gradient: &mut Vector3,
+    ) -> f32 {
+        if self.abyssal_depth <= 0.0 {
+            return elevation;
+        }
+
+        let mut sea_floor_gradient = continent_noise_gradient.clone();
+        let sea_floor = self.sea_level - self.depth(continent_noise, &mut sea_floor_gradient);
+        sea_floor_gradient *= -1.0;
+
+        // the sea floor fades out where the continents rise
+        let sea_floor = multiply(
+            sea_floor,
+            1.0 - continent_mask,
+            &mut sea_floor_gradient,
+            &(continent_mask_gradient * -1.0),
+        );
+
+        *gradient += &sea_floor_gradient;
+        elevation + sea_floor
+    }
+}
+
+pub fn bathymetry_layer(sea_level: f32, shelf_depth: f32, abyssal_depth: f32) -> BathymetryLayer {
+    BathymetryLayer {
+        sea_level,
+        shelf_depth,
+        abyssal_depth,
+        shelf_width: SHELF_WIDTH,
+        slope_width: SLOPE_WIDTH,
+    }
+}
@@ -0,0 +1,238 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::math::{add, multiply, s_max, s_max_gradient, scale, smoothstep};
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// Sum of all the inputs
+pub struct AddLayer<L: TerrainLayer> {
+    pub inputs: Vec<L>,
+}
+
+impl<L: TerrainLayer> TerrainLayer for AddLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut sum = 0.0;
+        let mut sum_gradient = Vector3::zero();
+        for input in &self.inputs {
+            let mut input_gradient = Vector3::zero();
+            let input_value = input.sample(coords, seed, &mut input_gradient);
+            sum = add(sum, input_value, &mut sum_gradient, &input_gradient);
+        }
+        *gradient += &sum_gradient;
+        sum
+    }
+
+    fn name(&self) -> &'static str {
+        "add"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        self.inputs.iter().fold((0.0, 0.0), |(low, high), input| {
+            let (input_low, input_high) = input.bounds();
+            (low + input_low, high + input_high)
+        })
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.inputs
+            .iter()
+            .map(|input| input.max_frequency())
+            .fold(0.0, f32::max)
+    }
+
+    fn cost(&self) -> u32 {
+        self.inputs
+            .iter()
+            .fold(0, |cost, input| cost.saturating_add(input.cost()))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+}
+
+/// Product of all the inputs
+pub struct MultiplyLayer<L: TerrainLayer> {
+    pub inputs: Vec<L>,
+}
+
+impl<L: TerrainLayer> TerrainLayer for MultiplyLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut product = 1.0;
+        let mut product_gradient = Vector3::zero();
+        for input in &self.inputs {
+            let mut input_gradient = Vector3::zero();
+            let input_value = input.sample(coords, seed, &mut input_gradient);
+            product = multiply(product, input_value, &mut product_gradient, &input_gradient);
+        }
+        *gradient += &product_gradient;
+        product
+    }
+
+    fn name(&self) -> &'static str {
+        "multiply"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        self.inputs.iter().fold((1.0, 1.0), |(low, high), input| {
+            let (input_low, input_high) = input.bounds();
+            let corners = [
+                low * input_low,
+                low * input_high,
+                high * input_low,
+                high * input_high,
+            ];
+            (
+                corners.iter().copied().fold(f32::INFINITY, f32::min),
+                corners.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+            )
+        })
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.inputs
+            .iter()
+            .map(|input| input.max_frequency())
+            .fold(0.0, f32::max)
+    }
+
+    fn cost(&self) -> u32 {
+        self.inputs
+            .iter()
+            .fold(0, |cost, input| cost.saturating_add(input.cost()))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+}
+
+/// The input multiplied by a constant factor
+pub struct ScaleLayer<L: TerrainLayer> {
+    pub input: L,
+    pub factor: f32,
+}
+
+impl<L: TerrainLayer> TerrainLayer for ScaleLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut input_gradient = Vector3::zero();
+        let value = scale(
+            self.input.sample(coords, seed, &mut input_gradient),
+            self.factor,
+            &mut input_gradient,
+        );
+        *gradient += &input_gradient;
+        value
+    }
+
+    fn name(&self) -> &'static str {
+        "scale"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        let (low, high) = self.input.bounds();
+        let (low, high) = (low * self.factor, high * self.factor);
+        (f32::min(low, high), f32::max(low, high))
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.input.max_frequency()
+    }
+
+    fn cost(&self) -> u32 {
+        self.input.cost()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![("factor", self.factor)]
+    }
+}
+
+pub struct SmoothstepLayer<L: TerrainLayer> {
+    pub input: L,
+    pub edge0: f32,
+    pub edge1: f32,
+}
+
+impl<L: TerrainLayer> TerrainLayer for SmoothstepLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut input_gradient = Vector3::zero();
+        let value = smoothstep(
+            self.edge0,
+            self.edge1,
+            self.input.sample(coords, seed, &mut input_gradient),
+            &mut input_gradient,
+        );
+        *gradient += &input_gradient;
+        value
+    }
+
+    fn name(&self) -> &'static str {
+        "smoothstep"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        let (low, high) = self.input.bounds();
+        let mut unused_gradient = Vector3::zero();
+        (
+            smoothstep(self.edge0, self.edge1, low, &mut unused_gradient),
+            smoothstep(self.edge0, self.edge1, high, &mut unused_gradient),
+        )
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.input.max_frequency()
+    }
+
+    fn cost(&self) -> u32 {
+        self.input.cost()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![("edge0", self.edge0), ("edge1", self.edge1)]
+    }
+}
+
+/// Smooth maximum of two inputs with smoothness factor `k`
+pub struct SMaxLayer<A: TerrainLayer, B: TerrainLayer> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+impl<A: TerrainLayer, B: TerrainLayer> TerrainLayer for SMaxLayer<A, B> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut a_gradient = Vector3::zero();
+        let a_value = self.a.sample(coords, seed, &mut a_gradient);
+        let mut b_gradient = Vector3::zero();
+        let b_value = self.b.sample(coords, seed, &mut b_gradient);
+
+        // s_max_gradient weights each gradient by its share of the maximum, the total gradient is their sum
+        let value = s_max_gradient(a_value, b_value, self.k, &mut a_gradient, &mut b_gradient);
+        *gradient += &a_gradient;
+        *gradient += &b_gradient;
+        value
+    }
+
+    fn name(&self) -> &'static str {
+        "s_max"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        let (a_low, a_high) = self.a.bounds();
+        let (b_low, b_high) = self.b.bounds();
+        (f32::max(a_low, b_low), s_max(a_high, b_high, self.k))
+    }
+
+    fn max_frequency(&self) -> f32 {
+        f32::max(self.a.max_frequency(), self.b.max_frequency())
+    }
+
+    fn cost(&self) -> u32 {
+        self.a.cost().saturating_add(self.b.cost())
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![("k", self.k)]
+    }
+}
@@ -1,13 +1,46 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub fn constant_layer(value: f32) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    move |_: &Vector3, _: f32, _: &mut Vector3| value
+pub struct ConstantLayer {
+    pub value: f32,
 }
 
-pub fn zero_layer() -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
+impl TerrainLayer for ConstantLayer {
+    fn sample(&self, _: &Vector3d, _: &NoiseSeed, _: &mut Vector3) -> f32 {
+        self.value
+    }
+
+    fn name(&self) -> &'static str {
+        "constant"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        (self.value, self.value)
+    }
+
+    fn max_frequency(&self) -> f32 {
+        0.0
+    }
+
+    fn cost(&self) -> u32 {
+        0
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![("value", self.value)]
+    }
+}
+
+pub fn constant_layer(value: f32) -> ConstantLayer {
+    ConstantLayer { value }
+}
+
+pub fn zero_layer() -> ConstantLayer {
     constant_layer(0.0)
 }
 
-pub fn one_layer() -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
+pub fn one_layer() -> ConstantLayer {
     constant_layer(1.0)
 }
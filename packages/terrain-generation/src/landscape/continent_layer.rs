@@ -1,24 +1,46 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
 use crate::utils::math::{multiply, smoothstep};
 use crate::utils::simplex::simplex401;
+use crate::utils::simplex_batch::{sample_octaves, simplex401_batch};
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub fn continent_layer(
-    frequency: f32,
-    continents_fragmentation: f32,
-) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    move |coords: &Vector3, seed: f32, gradient: &mut Vector3| {
-        let decay = 2.0;
-        let lacunarity = 2.0;
-        let nb_octaves = 6;
+const NB_OCTAVES: i32 = 6;
+const DECAY: f32 = 2.0;
+const LACUNARITY: f32 = 2.0;
+const DETAIL_FREQUENCY: f32 = 10.0;
+
+pub struct ContinentLayer {
+    pub frequency: f32,
+    pub continents_fragmentation: f32,
+}
+
+impl ContinentLayer {
+    /// The frequencies of the octaves followed by the frequency of the detail noise
+    fn noise_frequencies(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..NB_OCTAVES)
+            .map(|i| self.frequency * f32::powi(LACUNARITY, i))
+            .chain(std::iter::once(DETAIL_FREQUENCY))
+    }
+
+    /// Combines the octaves and the detail noise into the continents
+    /// * `gradient` - The gradient of the continents WILL BE ADDED TO
+    /// * `noise` - Samples the noise at the given index and frequency, overriding its gradient
+    fn combine_noises(
+        &self,
+        gradient: &mut Vector3,
+        mut noise: impl FnMut(usize, f32, &mut Vector3) -> f32,
+    ) -> f32 {
+        let mut frequencies = self.noise_frequencies().enumerate();
         let mut noise_value = 0.0;
         let mut total_amplitude = 0.0;
+        let mut noise_gradient = Vector3::zero();
         let mut local_gradient = Vector3::zero();
-        for i in 0..nb_octaves {
-            let local_frequency = frequency * f32::powi(lacunarity, i);
-            let local_amplitude = 1.0 / f32::powi(decay, i);
+        for (i, local_frequency) in frequencies.by_ref().take(NB_OCTAVES as usize) {
+            let local_amplitude = 1.0 / f32::powi(DECAY, i as i32);
 
-            let mut local_elevation =
-                simplex401(&(coords * local_frequency), seed, &mut local_gradient);
+            let mut local_elevation = noise(i, local_frequency, &mut local_gradient);
             local_gradient *= local_frequency;
 
             local_elevation = smoothstep(0.1, 1.0, local_elevation, &mut local_gradient);
@@ -29,25 +51,97 @@ pub fn continent_layer(
             total_amplitude += local_amplitude;
 
             noise_value += local_elevation;
-            *gradient += &local_gradient;
+            noise_gradient += &local_gradient;
         }
         noise_value /= total_amplitude;
-        *gradient /= total_amplitude;
+        noise_gradient /= total_amplitude;
 
-        noise_value = smoothstep(0.0, 1.0, noise_value, gradient);
+        noise_value = smoothstep(0.0, 1.0, noise_value, &mut noise_gradient);
 
+        let (detail_index, detail_frequency) = frequencies
+            .next()
+            .expect("the detail noise follows the octaves");
         let mut detail_gradient = Vector3::zero();
-        let mut detail_noise = simplex401(&(coords * 10.0), seed, &mut detail_gradient);
-        detail_gradient *= 10.0;
+        let mut detail_noise = noise(detail_index, detail_frequency, &mut detail_gradient);
+        detail_gradient *= detail_frequency;
 
-        detail_noise = multiply(detail_noise, noise_value, &mut detail_gradient, gradient);
+        detail_noise = multiply(
+            detail_noise,
+            noise_value,
+            &mut detail_gradient,
+            &noise_gradient,
+        );
 
         noise_value -= detail_noise * 0.3;
-        *gradient -= &(detail_gradient * 0.3);
+        noise_gradient -= &(detail_gradient * 0.3);
 
-        noise_value = 1.0 - continents_fragmentation * (1.0 - noise_value);
-        *gradient *= continents_fragmentation;
+        noise_value = 1.0 - self.continents_fragmentation * (1.0 - noise_value);
+        noise_gradient *= self.continents_fragmentation;
 
+        *gradient += &noise_gradient;
         noise_value
     }
 }
+
+impl TerrainLayer for ContinentLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        self.combine_noises(gradient, |_, frequency, noise_gradient| {
+            simplex401(&(coords * frequency as f64), seed, noise_gradient)
+        })
+    }
+
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        let noises = sample_octaves(
+            coords,
+            self.noise_frequencies(),
+            |coords, values, gradients| simplex401_batch(coords, seed, values, gradients),
+        );
+        for (point, (value, gradient)) in values.iter_mut().zip(gradients).enumerate() {
+            *value = self.combine_noises(gradient, |i, _, noise_gradient| {
+                *noise_gradient = noises[i].1[point].clone();
+                noises[i].0[point]
+            });
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "continent"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        // the octaves and the detail noise in [0, 1] keep the continents in [0, 1] before the fragmentation remaps them
+        let lowest = 1.0 - self.continents_fragmentation;
+        (f32::min(lowest, 1.0), f32::max(lowest, 1.0))
+    }
+
+    fn max_frequency(&self) -> f32 {
+        f32::max(
+            self.frequency * f32::powi(LACUNARITY, NB_OCTAVES - 1),
+            DETAIL_FREQUENCY,
+        )
+    }
+
+    fn cost(&self) -> u32 {
+        NB_OCTAVES as u32 + 1
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("frequency", self.frequency),
+            ("continents_fragmentation", self.continents_fragmentation),
+        ]
+    }
+}
+
+pub fn continent_layer(frequency: f32, continents_fragmentation: f32) -> ContinentLayer {
+    ContinentLayer {
+        frequency,
+        continents_fragmentation,
+    }
+}
@@ -0,0 +1,230 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::math::smoothstep;
+use crate::utils::random::pcg3d;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// The largest crater radius relative to the size of a cell, which keeps every crater inside the 3x3x3 neighbourhood of its cell
+const MAX_RADIUS: f32 = 0.25;
+/// The half width of the rim relative to the crater radius
+const RIM_WIDTH: f32 = 0.3;
+/// The radius of the central peak relative to the crater radius
+const PEAK_RADIUS: f32 = 0.25;
+/// The half width of the ejecta blanket relative to the crater radius
+const EJECTA_WIDTH: f32 = 1.0;
+/// An estimate of the number of craters of one octave overlapping at a given point, used for the bounds
+const MAX_OVERLAP: f32 = 8.0;
+
+/// Scatters impact craters on the unit sphere. Each octave halves the size of the craters,
+/// the number of craters per octave follows a power law of their radius.
+/// The depth of the largest craters is 1, the depth of the other craters is proportional to their radius.
+pub struct CraterLayer {
+    /// The number of cells per unit of the first octave
+    pub frequency: f32,
+    pub nb_octaves: u32,
+    /// The probability for a cell of the first octave to hold a crater
+    pub density: f32,
+    /// The exponent of the cumulative size distribution N(>r) ~ r^-size_exponent
+    pub size_exponent: f32,
+    /// The height of the rim relative to the depth of the bowl
+    pub rim_height: f32,
+    /// The height of the central peak of the largest craters relative to the depth of the bowl
+    pub peak_height: f32,
+    /// The height of the ejecta blanket relative to the depth of the bowl
+    pub ejecta_height: f32,
+}
+
+/// A C2 bump of compact support in [-1, 1] with its derivative
+fn bump(t: f32) -> (f32, f32) {
+    if t.abs() >= 1.0 {
+        return (0.0, 0.0);
+    }
+    let s = 1.0 - t * t;
+    (s * s * s, -6.0 * t * s * s)
+}
+
+/// Uniform random values in [0, 1] for the given cell
+fn hash01(cell_x: i32, cell_y: i32, cell_z: i32, seed: u32) -> [f32; 6] {
+    let (seed_x, seed_y, seed_z) = pcg3d(seed, seed, seed);
+    let (a, b, c) = pcg3d(
+        (cell_x as u32) ^ seed_x,
+        (cell_y as u32) ^ seed_y,
+        (cell_z as u32) ^ seed_z,
+    );
+    let (d, e, f) = pcg3d(c, a, b);
+
+    [a, b, c, d, e, f].map(|x| x as f32 / u32::MAX as f32)
+}
+
+impl CraterLayer {
+    /// The elevation profile of a crater relative to the depth of its bowl
+    /// * `r` - The distance to the center of the crater divided by its radius
+    /// * `peak` - The weight of the central peak
+    ///
+    /// Returns the elevation and its derivative with respect to `r`
+    fn profile(&self, r: f32, peak: f32) -> (f32, f32) {
+        let (bowl, bowl_derivative) = bump(r);
+        let (rim, rim_derivative) = bump((r - 1.0) / RIM_WIDTH);
+        let (central_peak, central_peak_derivative) = bump(r / PEAK_RADIUS);
+        let (ejecta, ejecta_derivative) = bump((r - 1.0) / EJECTA_WIDTH);
+
+        let value = -bowl
+            + self.rim_height * rim
+            + peak * self.peak_height * central_peak
+            + self.ejecta_height * ejecta;
+        let derivative = -bowl_derivative
+            + self.rim_height * rim_derivative / RIM_WIDTH
+            + peak * self.peak_height * central_peak_derivative / PEAK_RADIUS
+            + self.ejecta_height * ejecta_derivative / EJECTA_WIDTH;
+
+        (value, derivative)
+    }
+
+    /// The probability for a cell of the given octave to hold a crater
+    fn octave_density(&self, octave: u32) -> f32 {
+        // cells are 4 times as numerous at each octave while the radius halves
+        let growth = f32::powf(2.0, self.size_exponent - 2.0);
+        f32::min(self.density * f32::powi(growth, octave as i32), 1.0)
+    }
+
+    /// The total amplitude of all octaves
+    fn total_amplitude(&self) -> f32 {
+        (0..self.nb_octaves).map(|i| f32::powi(0.5, i as i32)).sum()
+    }
+
+    /// The largest value of a single crater profile
+    fn max_crater_elevation(&self) -> f32 {
+        f32::max(self.rim_height, 0.0)
+            + f32::max(self.peak_height, 0.0)
+            + f32::max(self.ejecta_height, 0.0)
+    }
+}
+
+impl TerrainLayer for CraterLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        if self.density <= 0.0 {
+            return 0.0;
+        }
+
+        let mut value = 0.0;
+        for octave in 0..self.nb_octaves {
+            let cell_frequency = self.frequency * f32::powi(2.0, octave as i32);
+            let cell_size = 1.0 / cell_frequency;
+            let max_radius = MAX_RADIUS * cell_size;
+            let octave_scale = f32::powi(0.5, octave as i32);
+            let octave_density = self.octave_density(octave);
+            let octave_seed = seed.bits() ^ octave.wrapping_mul(0x9e37_79b9);
+
+            let cell = coords * cell_frequency as f64;
+            let (cell_x, cell_y, cell_z) = (
+                cell.x.floor() as i32,
+                cell.y.floor() as i32,
+                cell.z.floor() as i32,
+            );
+
+            for i in -1..=1 {
+                for j in -1..=1 {
+                    for k in -1..=1 {
+                        let (x, y, z) = (cell_x + i, cell_y + j, cell_z + k);
+                        let random = hash01(x, y, z, octave_seed);
+                        if random[3] >= octave_density {
+                            continue;
+                        }
+
+                        // only the cells close to the sphere hold a crater, so that projecting the center moves it by less than half a cell
+                        let jittered = Vector3::new(
+                            x as f32 + random[0],
+                            y as f32 + random[1],
+                            z as f32 + random[2],
+                        ) * cell_size;
+                        let jittered_length = jittered.length();
+                        if (jittered_length - 1.0).abs() >= 0.5 * cell_size {
+                            continue;
+                        }
+                        let center = jittered / jittered_length;
+
+                        // inverse transform sampling of a power law truncated to [max_radius / 2, max_radius]
+                        let tail = 1.0 - f32::powf(0.5, self.size_exponent);
+                        let radius = 0.5
+                            * max_radius
+                            * f32::powf(1.0 - random[4] * tail, -1.0 / self.size_exponent);
+
+                        let offset = (coords - &Vector3d::from(&center)).to_f32();
+                        let distance = offset.length();
+                        let r = distance / radius;
+                        if r >= 1.0 + EJECTA_WIDTH {
+                            continue;
+                        }
+
+                        // the depth of a crater is proportional to its radius
+                        let amplitude = octave_scale * radius / max_radius;
+
+                        // only the largest craters grow a central peak
+                        let mut peak_gradient = Vector3::zero();
+                        let peak = random[5] * smoothstep(0.5, 1.0, amplitude, &mut peak_gradient);
+
+                        let (elevation, derivative) = self.profile(r, peak);
+                        value += amplitude * elevation;
+                        if distance > 0.0 {
+                            *gradient += &offset * (amplitude * derivative / (distance * radius));
+                        }
+                    }
+                }
+            }
+        }
+
+        value
+    }
+
+    fn name(&self) -> &'static str {
+        "crater"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        if self.density <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let total_amplitude = MAX_OVERLAP * self.total_amplitude();
+        (
+            -total_amplitude,
+            total_amplitude * self.max_crater_elevation(),
+        )
+    }
+
+    fn max_frequency(&self) -> f32 {
+        // the narrowest feature is the rim of the smallest craters
+        let smallest_radius =
+            0.5 * MAX_RADIUS / (self.frequency * f32::powi(2.0, self.nb_octaves as i32 - 1));
+        1.0 / (RIM_WIDTH * smallest_radius)
+    }
+
+    fn cost(&self) -> u32 {
+        self.nb_octaves.saturating_mul(27)
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("frequency", self.frequency),
+            ("nb_octaves", self.nb_octaves as f32),
+            ("density", self.density),
+            ("size_exponent", self.size_exponent),
+            ("rim_height", self.rim_height),
+            ("peak_height", self.peak_height),
+            ("ejecta_height", self.ejecta_height),
+        ]
+    }
+}
+
+pub fn crater_layer(frequency: f32, nb_octaves: u32, density: f32) -> CraterLayer {
+    CraterLayer {
+        frequency,
+        nb_octaves,
+        density,
+        size_exponent: 2.0,
+        rim_height: 0.25,
+        peak_height: 0.4,
+        ejecta_height: 0.08,
+    }
+}
@@ -0,0 +1,114 @@
+use crate::landscape::simplex_noise_layer::SimplexLayer;
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// Offsets decorrelating the three components of the warp field
+const WARP_OFFSETS: [[f32; 3]; 3] = [
+    [0.0, 0.0, 0.0],
+    [31.416, -47.853, 12.793],
+    [-23.638, 17.221, 53.519],
+];
+
+/// Samples the input layer at a position offset by a vector noise field to break the isotropy of the noise
+pub struct DomainWarpLayer<L: TerrainLayer, W: TerrainLayer> {
+    pub input: L,
+    /// The scalar noise sampled three times to build the warp field
+    pub warp: W,
+    /// The maximum length of the offset applied to the sample position
+    pub strength: f32,
+    pub frequency: f32,
+}
+
+impl<L: TerrainLayer, W: TerrainLayer> DomainWarpLayer<L, W> {
+    /// Computes the warped sample position and stores the gradients of its components in `jacobian`
+    fn warp_position(
+        &self,
+        coords: &Vector3d,
+        seed: &NoiseSeed,
+        jacobian: &mut [Vector3; 3],
+    ) -> Vector3d {
+        // remap the warp noise to [-1, 1] to avoid drifting the whole layer in one direction
+        let (low, high) = self.warp.bounds();
+        let center = (low + high) / 2.0;
+        let half_range = f32::max((high - low) / 2.0, f32::EPSILON);
+
+        let mut offset = [0.0; 3];
+        for (i, warp_offset) in WARP_OFFSETS.iter().enumerate() {
+            let warp_sample_point = &(coords * self.frequency as f64)
+                + &Vector3d::from(&Vector3::new(
+                    warp_offset[0],
+                    warp_offset[1],
+                    warp_offset[2],
+                ));
+
+            let mut warp_gradient = Vector3::zero();
+            let warp_value = self
+                .warp
+                .sample(&warp_sample_point, seed, &mut warp_gradient);
+
+            offset[i] = self.strength * (warp_value - center) / half_range;
+            jacobian[i] = warp_gradient * (self.strength * self.frequency / half_range);
+        }
+
+        coords + &Vector3d::from(&Vector3::new(offset[0], offset[1], offset[2]))
+    }
+}
+
+impl<L: TerrainLayer, W: TerrainLayer> TerrainLayer for DomainWarpLayer<L, W> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut jacobian = [Vector3::zero(), Vector3::zero(), Vector3::zero()];
+        let warped_coords = self.warp_position(coords, seed, &mut jacobian);
+
+        let mut input_gradient = Vector3::zero();
+        let value = self.input.sample(&warped_coords, seed, &mut input_gradient);
+
+        // chain rule: the gradient is transported by the transposed jacobian of the warp (identity + offset derivatives)
+        *gradient += &input_gradient;
+        *gradient += &jacobian[0] * input_gradient.x;
+        *gradient += &jacobian[1] * input_gradient.y;
+        *gradient += &jacobian[2] * input_gradient.z;
+
+        value
+    }
+
+    fn name(&self) -> &'static str {
+        "domain_warp"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        self.input.bounds()
+    }
+
+    fn max_frequency(&self) -> f32 {
+        // the warp compresses the input by at most the largest slope of the offset
+        f32::max(
+            self.input.max_frequency() * (1.0 + self.strength * self.frequency),
+            self.frequency * self.warp.max_frequency(),
+        )
+    }
+
+    fn cost(&self) -> u32 {
+        self.input
+            .cost()
+            .saturating_add(self.warp.cost().saturating_mul(3))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![("strength", self.strength), ("frequency", self.frequency)]
+    }
+}
+
+pub fn domain_warp_layer<L: TerrainLayer>(
+    input: L,
+    strength: f32,
+    frequency: f32,
+) -> DomainWarpLayer<L, SimplexLayer> {
+    DomainWarpLayer {
+        input,
+        warp: SimplexLayer,
+        strength,
+        frequency,
+    }
+}
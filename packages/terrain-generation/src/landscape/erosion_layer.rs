@@ -0,0 +1,53 @@
+use crate::seed::NoiseSeed;
+use crate::utils::erosion::erode3d;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// Carves gullies oriented along the slope of an elevation in [0, 1]
+pub struct ErosionLayer {
+    /// The amplitude of the gullies relative to the eroded elevation
+    pub strength: f32,
+    pub nb_octaves: u32,
+    pub frequency: f32,
+}
+
+impl ErosionLayer {
+    /// Erodes the given value and updates its gradient accordingly
+    /// * `coords` - The position of the sample on the unit sphere
+    /// * `seed` - The seed of the planet
+    /// * `value` - The elevation to erode
+    /// * `gradient` - The gradient of the elevation WILL STORE THE RESULT
+    pub fn apply(
+        &self,
+        coords: &Vector3d,
+        seed: &NoiseSeed,
+        value: f32,
+        gradient: &mut Vector3,
+    ) -> f32 {
+        if self.strength <= 0.0 || self.nb_octaves == 0 {
+            return value;
+        }
+
+        let mut erosion_gradient = Vector3::zero();
+        let erosion = erode3d(
+            coords,
+            self.frequency,
+            self.nb_octaves,
+            seed.bits(),
+            value,
+            gradient,
+            &mut erosion_gradient,
+        );
+
+        *gradient += &erosion_gradient * self.strength;
+        value + erosion * self.strength
+    }
+}
+
+pub fn erosion_layer(strength: f32, nb_octaves: u32, frequency: f32) -> ErosionLayer {
+    ErosionLayer {
+        strength,
+        nb_octaves,
+        frequency,
+    }
+}
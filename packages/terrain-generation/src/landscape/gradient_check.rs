@@ -0,0 +1,147 @@
+use crate::landscape::make_terrain_function::TerrainFunction;
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use std::fmt;
+
+/// How far an analytic gradient is from central differences over a set of sample points.
+/// The error at each point is `|analytic - numerical| / (1 + |numerical|)`: relative on steep slopes, absolute on flat ones.
+#[derive(Clone, Debug)]
+pub struct GradientReport {
+    pub name: String,
+    pub nb_points: usize,
+    pub max_error: f32,
+    pub mean_error: f32,
+    /// The error not exceeded by 95% of the points
+    pub p95_error: f32,
+    /// The sample point with the largest error
+    pub worst_point: Vector3,
+}
+
+impl fmt::Display for GradientReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: max error {:.2e} at ({:.4}, {:.4}, {:.4}), 95th percentile {:.2e}, mean error {:.2e} over {} points",
+            self.name,
+            self.max_error,
+            self.worst_point.x,
+            self.worst_point.y,
+            self.worst_point.z,
+            self.p95_error,
+            self.mean_error,
+            self.nb_points
+        )
+    }
+}
+
+/// Points spread evenly over the unit sphere
+pub fn fibonacci_sphere(nb_points: usize) -> Vec<Vector3> {
+    let golden_angle = std::f32::consts::PI * (3.0 - f32::sqrt(5.0));
+    (0..nb_points)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / nb_points as f32;
+            let radius = f32::sqrt(1.0 - y * y);
+            let theta = golden_angle * i as f32;
+            Vector3::new(radius * f32::cos(theta), y, radius * f32::sin(theta))
+        })
+        .collect()
+}
+
+/// The gradient of `f` at `point` estimated with central differences along the axes
+pub fn numerical_gradient(f: &dyn Fn(&Vector3) -> f32, point: &Vector3, epsilon: f32) -> Vector3 {
+    let derivative =
+        |offset: Vector3| (f(&(point + &offset)) - f(&(point - &offset))) / (2.0 * epsilon);
+    Vector3::new(
+        derivative(Vector3::new(epsilon, 0.0, 0.0)),
+        derivative(Vector3::new(0.0, epsilon, 0.0)),
+        derivative(Vector3::new(0.0, 0.0, epsilon)),
+    )
+}
+
+/// Compares the gradients computed by `f` against central differences at the given points
+/// * `f` - Returns the value of the field and adds its gradient to the given recipient
+/// * `epsilon` - The step of the central differences, a small fraction of the smallest wavelength of the field
+pub fn check_gradient(
+    name: &str,
+    points: &[Vector3],
+    epsilon: f32,
+    f: &dyn Fn(&Vector3, &mut Vector3) -> f32,
+) -> GradientReport {
+    let mut report = GradientReport {
+        name: name.to_string(),
+        nb_points: points.len(),
+        max_error: 0.0,
+        mean_error: 0.0,
+        p95_error: 0.0,
+        worst_point: Vector3::zero(),
+    };
+
+    let mut errors = Vec::with_capacity(points.len());
+    for point in points {
+        let mut analytic = Vector3::zero();
+        f(point, &mut analytic);
+        let numerical = numerical_gradient(&|p| f(p, &mut Vector3::zero()), point, epsilon);
+
+        let error = (&analytic - &numerical).length() / (1.0 + numerical.length());
+        report.mean_error += error / points.len() as f32;
+        if error > report.max_error {
+            report.max_error = error;
+            report.worst_point = point.clone();
+        }
+        errors.push(error);
+    }
+
+    errors.sort_by(f32::total_cmp);
+    if let Some(&p95_error) = errors.get(points.len() * 95 / 100) {
+        report.p95_error = p95_error;
+    }
+
+    report
+}
+
+/// Compares the gradient of a layer against central differences at the given points
+pub fn check_layer(
+    layer: &dyn TerrainLayer,
+    seed: &NoiseSeed,
+    points: &[Vector3],
+    epsilon: f32,
+) -> GradientReport {
+    check_gradient(layer.name(), points, epsilon, &|point, gradient| {
+        layer.sample(&Vector3d::from(point), seed, gradient)
+    })
+}
+
+/// Compares the slopes of a terrain function at full detail against central differences.
+/// Like the normals of the chunks, the gradient of the elevation is divided by the radius of the planet
+/// and projected onto the tangent plane of the sphere, where the elevation is defined.
+/// * `points` - Sample points on the unit sphere
+pub fn check_terrain_function(
+    name: &str,
+    terrain_function: &TerrainFunction,
+    seed: &NoiseSeed,
+    planet_radius: f32,
+    points: &[Vector3],
+    epsilon: f32,
+) -> GradientReport {
+    let relative_elevation = |point: &Vector3, gradient: &mut Vector3| {
+        let unit_point = point.normalize_to_new();
+        let mut displacement = Vector3::zero();
+        let mut elevation_gradient = Vector3::zero();
+        terrain_function(
+            &Vector3d::from(&unit_point),
+            seed,
+            0.0,
+            &mut displacement,
+            &mut elevation_gradient,
+        );
+
+        elevation_gradient /= planet_radius;
+        *gradient +=
+            &elevation_gradient - &(&unit_point * Vector3::dot(&elevation_gradient, &unit_point));
+        Vector3::dot(&displacement, &unit_point) / planet_radius
+    };
+
+    check_gradient(name, points, epsilon, &relative_elevation)
+}
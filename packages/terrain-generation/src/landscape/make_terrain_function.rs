@@ -1,26 +1,91 @@
-use crate::landscape::constant_layers::zero_layer;
+use crate::landscape::bathymetry_layer::bathymetry_layer;
 use crate::landscape::continent_layer::continent_layer;
+use crate::landscape::crater_layer::crater_layer;
+use crate::landscape::erosion_layer::erosion_layer;
 use crate::landscape::mountain_layer::mountain_layer;
-use crate::landscape::simplex_noise_layer::simplex_noise_layer;
+use crate::landscape::simple_fractal_layer::SimpleFractalLayer;
+use crate::landscape::simplex_noise_layer::{simplex_noise_layer, SimplexLayer};
+use crate::landscape::terrace_layer::terrace_layer;
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::{NoiseSeed, Salt};
 use crate::terrain_settings::TerrainSettings;
-use crate::utils::math::{multiply, smoothstep};
+use crate::utils::math::{multiply, s_ceil, s_max_gradient, smoothstep};
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub type TerrainFunction = dyn Fn(&Vector3, f32, &mut Vector3, &mut Vector3);
+/// The smoothness of the blend between the mountain mask and the collisions of the plates
+const MOUNTAIN_MASK_SMOOTHNESS: f32 = 16.0;
+
+/// Displaces `out_position` and accumulates the gradient of the elevation in `out_gradient`
+/// * `unit_sample_point` - The position of the sample on the unit sphere, in double precision for the noise
+/// * `seed` - The seed of the planet, from which every layer derives its own sub-seed
+/// * `vertex_spacing` - The distance between neighbouring vertices on the unit sphere (0 for full detail)
+pub type TerrainFunction =
+    dyn Fn(&Vector3d, &NoiseSeed, f32, &mut Vector3, &mut Vector3) + Send + Sync;
+
+/// The terrain function applied to a row of samples at once, so that the noise layers evaluate several points per call.
+/// Displaces each of `out_positions` and accumulates the gradients of the elevation in `out_gradients`
+pub type TerrainRowFunction =
+    dyn Fn(&[Vector3d], &NoiseSeed, f32, &mut [Vector3], &mut [Vector3]) + Send + Sync;
+
+/// The noise restricting the mountains to some regions of the planet, at a quarter of their frequency
+pub fn mountain_mask_layer(mountains_frequency: f32) -> SimpleFractalLayer<SimplexLayer> {
+    simplex_noise_layer(mountains_frequency / 4.0, 1, 2.0, 2.0, 1.0)
+}
+
+/// The small scale relief of the continents
+pub fn bumps_layer(bumps_frequency: f32) -> SimpleFractalLayer<SimplexLayer> {
+    simplex_noise_layer(bumps_frequency, 8, 1.7, 2.0, 1.0)
+}
 
 pub fn make_terrain_function(settings: TerrainSettings) -> Box<TerrainFunction> {
+    let row_function = make_terrain_row_function(settings);
+    Box::new(
+        move |unit_sample_point: &Vector3d,
+              seed: &NoiseSeed,
+              vertex_spacing: f32,
+              out_position: &mut Vector3,
+              out_gradient: &mut Vector3| {
+            row_function(
+                std::slice::from_ref(unit_sample_point),
+                seed,
+                vertex_spacing,
+                std::slice::from_mut(out_position),
+                std::slice::from_mut(out_gradient),
+            )
+        },
+    )
+}
+
+pub fn make_terrain_row_function(settings: TerrainSettings) -> Box<TerrainRowFunction> {
     let continents = continent_layer(
         settings.continents_frequency,
         settings.continents_fragmentation,
     );
-    let bumps = zero_layer(); //simplex_noise_layer(settings.bumps_frequency, 8, 1.7, 2.0, 1.0);
+    let bumps = bumps_layer(settings.bumps_frequency);
     let mountains = mountain_layer(settings.mountains_frequency, 7, 2.0, 2.0, 0.4);
-    let mountain_mask = simplex_noise_layer(settings.mountains_frequency / 4.0, 1, 2.0, 2.0, 1.0);
-    //let terraceMask = simplex_noise_layer(settings.mountainsFrequency / 20, 1, 2, 2, 1.0);
+    let mountain_mask = mountain_mask_layer(settings.mountains_frequency);
+    let erosion = erosion_layer(
+        settings.erosion_strength,
+        settings.erosion_octaves,
+        settings.erosion_frequency,
+    );
+    let terraces = terrace_layer(
+        settings.mountains_frequency,
+        settings.terrace_steps,
+        settings.terrace_sharpness,
+        settings.terrace_coverage,
+    );
+    let craters = crater_layer(settings.craters_frequency, 6, settings.craters_density);
+    let bathymetry = bathymetry_layer(
+        settings.sea_level,
+        settings.continental_shelf_depth,
+        settings.abyssal_depth,
+    );
 
     /*return Box::new(
         move |unit_sample_point: &Vector3,
-              seed: f32,
+              seed: &NoiseSeed,
               out_position: &mut Vector3,
               out_gradient: &mut Vector3| {
             let mut gradient = Vector3::zero();
@@ -35,72 +100,201 @@ pub fn make_terrain_function(settings: TerrainSettings) -> Box<TerrainFunction>
     );*/
 
     Box::new(
-        move |unit_sample_point: &Vector3,
-              seed: f32,
-              out_position: &mut Vector3,
-              out_gradient: &mut Vector3| {
-            let mut elevation = 0.0;
+        move |unit_sample_points: &[Vector3d],
+              seed: &NoiseSeed,
+              vertex_spacing: f32,
+              out_positions: &mut [Vector3],
+              out_gradients: &mut [Vector3]| {
+            // the noise layers are sampled over the whole row
+            let sample_row = |layer: &dyn TerrainLayer, salt: Salt| {
+                let mut values = vec![0.0; unit_sample_points.len()];
+                let mut gradients = vec![Vector3::zero(); unit_sample_points.len()];
+                layer.sample_batch(
+                    unit_sample_points,
+                    seed.derive(salt),
+                    &mut values,
+                    &mut gradients,
+                );
+                (values, gradients)
+            };
+            let continent_row = sample_row(&continents, Salt::Continents);
+            let mountain_row = sample_row(&mountains, Salt::Mountains);
+            let mountain_mask_row = sample_row(&mountain_mask, Salt::MountainMask);
+            let bump_row = sample_row(&bumps, Salt::Bumps);
 
-            // Continent Generation
+            let plates = (settings.nb_tectonic_plates > 0).then(|| {
+                seed.tectonic_plates(
+                    settings.nb_tectonic_plates,
+                    settings.tectonic_boundary_width,
+                )
+            });
 
-            let mut continent_mask_gradient = Vector3::zero();
-            let mut continent_mask =
-                continents(unit_sample_point, seed, &mut continent_mask_gradient);
+            for (point, unit_sample_point) in unit_sample_points.iter().enumerate() {
+                let unit_sample_coords = unit_sample_point.to_f32();
+                let out_gradient = &mut out_gradients[point];
+                let mut elevation = 0.0;
 
-            // Mountain Generation
-            continent_mask = smoothstep(0.3, 0.5, continent_mask, &mut continent_mask_gradient);
+                // Continent Generation
 
-            elevation += continent_mask * settings.continent_base_height;
-            *out_gradient += &continent_mask_gradient * settings.continent_base_height;
+                let mut continent_mask_gradient = continent_row.1[point].clone();
+                let mut continent_mask = continent_row.0[point];
 
-            let mut mountain_gradient = Vector3::zero();
-            let mut mountain_elevation = mountains(unit_sample_point, seed, &mut mountain_gradient);
+                // Plate Tectonics
 
-            mountain_elevation = multiply(
-                mountain_elevation,
-                continent_mask,
-                &mut mountain_gradient,
-                &continent_mask_gradient,
-            );
+                let tectonics = plates
+                    .as_ref()
+                    .map(|plates| plates.sample(&unit_sample_coords));
 
-            // Mountain Mask
-            let mut mountain_mask_gradient = Vector3::zero();
-            let mountain_mask = mountain_mask(unit_sample_point, seed, &mut mountain_mask_gradient);
+                if let Some(tectonics) = &tectonics {
+                    // oceanic plates only keep the highest continents as islands
+                    let plate_factor = 0.4 + 0.6 * tectonics.continentalness;
+                    continent_mask = multiply(
+                        continent_mask,
+                        plate_factor,
+                        &mut continent_mask_gradient,
+                        &(&tectonics.continentalness_gradient * 0.6),
+                    );
+                }
 
-            mountain_elevation = multiply(
-                mountain_elevation,
-                mountain_mask,
-                &mut mountain_gradient,
-                &mountain_mask_gradient,
-            );
+                let continent_noise = continent_mask;
+                let continent_noise_gradient = continent_mask_gradient.clone();
 
-            // Terrace Generation
+                // Mountain Generation
+                continent_mask = smoothstep(0.3, 0.5, continent_mask, &mut continent_mask_gradient);
 
-            // terraces are interesting but require lots of polygons to look good
-            /*const terraceGradient = mountain_gradient.clone();
-            let terraceElevation = smoothstep(0.51, 0.52, mountain_elevation, terraceGradient);
+                elevation += continent_mask * settings.continent_base_height;
+                *out_gradient += &continent_mask_gradient * settings.continent_base_height;
 
-            const terraceElevationMaskGradient = LVector3.Zero();
-            let terraceElevationMask = terraceMask(samplePoint, seed, terraceElevationMaskGradient);
-            terraceElevationMask = smoothstep(0.40, 0.41, terraceElevationMask, terraceGradient);
+                // Bathymetry
 
-            terraceElevation = multiply(terraceElevation, terraceElevationMask, terraceGradient, terraceElevationMaskGradient);
+                elevation = bathymetry.apply(
+                    continent_noise,
+                    &continent_noise_gradient,
+                    continent_mask,
+                    &continent_mask_gradient,
+                    elevation,
+                    out_gradient,
+                );
 
-            mountain_elevation += 0.1 * terraceElevation;
-            mountain_gradient.addInPlace(terraceGradient.scaleInPlace(0.1));*/
+                let mut mountain_gradient = mountain_row.1[point].clone();
+                let mut mountain_elevation = mountain_row.0[point];
 
-            elevation += mountain_elevation * settings.max_mountain_height;
-            *out_gradient += mountain_gradient * settings.max_mountain_height;
+                mountain_elevation = multiply(
+                    mountain_elevation,
+                    continent_mask,
+                    &mut mountain_gradient,
+                    &continent_mask_gradient,
+                );
 
-            // Bump Generation
+                // Mountain Mask
+                let mut mountain_mask_gradient = mountain_mask_row.1[point].clone();
+                let mut mountain_mask = mountain_mask_row.0[point];
 
-            let mut bumpy_gradient = Vector3::zero();
-            let bumpy_elevation = bumps(unit_sample_point, seed, &mut bumpy_gradient);
+                // mountain belts rise where plates collide, no higher than the tallest mountains
+                if let Some(tectonics) = &tectonics {
+                    let mut collision_gradient = tectonics.collision_gradient.clone();
+                    mountain_mask = s_max_gradient(
+                        mountain_mask,
+                        tectonics.collision,
+                        MOUNTAIN_MASK_SMOOTHNESS,
+                        &mut mountain_mask_gradient,
+                        &mut collision_gradient,
+                    );
+                    mountain_mask_gradient += collision_gradient;
+                    mountain_mask = s_ceil(
+                        mountain_mask,
+                        1.0,
+                        MOUNTAIN_MASK_SMOOTHNESS,
+                        &mut mountain_mask_gradient,
+                    );
+                }
 
-            elevation += bumpy_elevation * settings.max_bump_height;
-            *out_gradient += bumpy_gradient * settings.max_bump_height;
+                mountain_elevation = multiply(
+                    mountain_elevation,
+                    mountain_mask,
+                    &mut mountain_gradient,
+                    &mountain_mask_gradient,
+                );
 
-            *out_position += unit_sample_point * elevation;
+                // Erosion
+
+                mountain_elevation = erosion.apply(
+                    unit_sample_point,
+                    seed.derive(Salt::Erosion),
+                    mountain_elevation,
+                    &mut mountain_gradient,
+                );
+
+                // Terrace Generation
+
+                mountain_elevation = terraces.apply(
+                    unit_sample_point,
+                    seed.derive(Salt::Terraces),
+                    vertex_spacing,
+                    mountain_elevation,
+                    &mut mountain_gradient,
+                );
+
+                elevation += mountain_elevation * settings.max_mountain_height;
+                *out_gradient += mountain_gradient * settings.max_mountain_height;
+
+                // Bump Generation (masked by the continents to keep the ocean floor smooth)
+
+                let mut bumpy_gradient = bump_row.1[point].clone();
+                let mut bumpy_elevation = bump_row.0[point];
+
+                bumpy_elevation = multiply(
+                    bumpy_elevation,
+                    continent_mask,
+                    &mut bumpy_gradient,
+                    &continent_mask_gradient,
+                );
+
+                elevation += bumpy_elevation * settings.max_bump_height;
+                *out_gradient += bumpy_gradient * settings.max_bump_height;
+
+                // Ocean Ridges (divergent boundaries only lift the ocean floor)
+
+                if let Some(tectonics) = &tectonics {
+                    let mut ridge_gradient = tectonics.rifting_gradient.clone();
+                    let ridge_elevation = multiply(
+                        tectonics.rifting,
+                        1.0 - continent_mask,
+                        &mut ridge_gradient,
+                        &(&continent_mask_gradient * -1.0),
+                    );
+
+                    elevation += ridge_elevation * settings.max_ridge_height;
+                    *out_gradient += ridge_gradient * settings.max_ridge_height;
+
+                    // Ocean Trenches (convergent boundaries sink the ocean floor)
+
+                    let mut trench_gradient = tectonics.collision_gradient.clone();
+                    let trench_elevation = multiply(
+                        tectonics.collision,
+                        1.0 - continent_mask,
+                        &mut trench_gradient,
+                        &(&continent_mask_gradient * -1.0),
+                    );
+
+                    elevation -= trench_elevation * settings.max_trench_depth;
+                    *out_gradient -= trench_gradient * settings.max_trench_depth;
+                }
+
+                // Crater Generation (not masked as craters are meant for bodies without oceans)
+
+                let mut crater_gradient = Vector3::zero();
+                let crater_elevation = craters.sample(
+                    unit_sample_point,
+                    seed.derive(Salt::Craters),
+                    &mut crater_gradient,
+                );
+
+                elevation += crater_elevation * settings.max_crater_depth;
+                *out_gradient += crater_gradient * settings.max_crater_depth;
+
+                out_positions[point] += &unit_sample_coords * elevation;
+            }
         },
     )
 }
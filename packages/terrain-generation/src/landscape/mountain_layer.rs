@@ -1,57 +1,128 @@
-use crate::utils::math::{s_abs, s_max_gradient};
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::dual::Dual;
 use crate::utils::simplex::simplex411;
+use crate::utils::simplex_batch::{sample_octaves, simplex411_batch};
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub fn mountain_layer(
-    frequency: f32,
-    nb_octaves: i32,
-    decay: f32,
-    lacunarity: f32,
-    threshold: f32,
-) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    move |coords: &Vector3, seed: f32, gradient: &mut Vector3| {
-        let mut noise_value = 0.0;
+pub struct MountainLayer {
+    pub frequency: f32,
+    pub nb_octaves: i32,
+    pub decay: f32,
+    pub lacunarity: f32,
+    pub threshold: f32,
+}
+
+impl MountainLayer {
+    fn octave_frequencies(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.nb_octaves).map(|i| self.frequency * f32::powi(self.lacunarity, i))
+    }
+
+    /// Sums the ridged octaves of the noise
+    /// * `octave` - Samples the noise at the given octave and frequency
+    fn combine_octaves(&self, mut octave: impl FnMut(usize, f32) -> Dual) -> Dual {
+        let mut noise = Dual::ZERO;
         let mut total_amplitude = 0.0;
-        let mut local_gradient = Vector3::zero();
-        for i in 0..nb_octaves {
-            let local_frequency = frequency * f32::powi(lacunarity, i);
-            let mut local_elevation =
-                simplex411(&(coords * local_frequency), seed, &mut local_gradient);
-            local_gradient *= local_frequency;
+        for (i, local_frequency) in self.octave_frequencies().enumerate() {
+            let decay = f32::powi(self.decay, i as i32);
 
             // TODO: ne pas hardcoder
             let sharpness = 4.0;
-            local_elevation = 1.0 - s_abs(local_elevation, sharpness, &mut local_gradient);
+            let ridge = 1.0 - octave(i, local_frequency).s_abs(sharpness);
+
+            noise += ridge / decay;
+            total_amplitude += 1.0 / decay;
+        }
 
-            local_gradient *= -1.0;
+        noise /= total_amplitude;
 
-            local_elevation /= f32::powi(decay, i);
-            local_gradient /= f32::powi(decay, i);
+        //noise.powf(power)
 
-            noise_value += local_elevation;
-            *gradient += &local_gradient;
+        let noise = noise.s_max(Dual::constant(self.threshold), 10.0);
 
-            total_amplitude += 1.0 / f32::powi(decay, i);
-        }
+        (noise - self.threshold) / (1.0 - self.threshold)
+    }
+}
 
-        noise_value /= total_amplitude;
-        *gradient /= total_amplitude;
+impl TerrainLayer for MountainLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut octave_gradient = Vector3::zero();
+        let noise = self.combine_octaves(|_, frequency| {
+            let value = simplex411(&(coords * frequency as f64), seed, &mut octave_gradient);
+            Dual::new(value, &(&octave_gradient * frequency))
+        });
 
-        //pow(noise_value, power, gradient)
+        *gradient += &noise.gradient();
+        noise.value
+    }
 
-        noise_value = s_max_gradient(
-            noise_value,
-            threshold,
-            10.0,
-            gradient,
-            &mut Vector3::new(0.0, 0.0, 0.0),
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        let octaves = sample_octaves(
+            coords,
+            self.octave_frequencies(),
+            |coords, values, gradients| simplex411_batch(coords, seed, values, gradients),
         );
+        for (point, (value, gradient)) in values.iter_mut().zip(gradients).enumerate() {
+            let noise = self.combine_octaves(|i, frequency| {
+                Dual::new(octaves[i].0[point], &(&octaves[i].1[point] * frequency))
+            });
+
+            *gradient += &noise.gradient();
+            *value = noise.value;
+        }
+    }
 
-        noise_value -= threshold;
+    fn name(&self) -> &'static str {
+        "mountain"
+    }
 
-        noise_value /= 1.0 - threshold;
-        *gradient /= 1.0 - threshold;
+    fn bounds(&self) -> (f32, f32) {
+        // the ridges are at most 1 and the smooth maximum overshoots by at most ln(2) / k
+        (
+            0.0,
+            (1.0 + f32::ln(2.0) / 10.0 - self.threshold) / (1.0 - self.threshold),
+        )
+    }
 
-        noise_value
+    fn max_frequency(&self) -> f32 {
+        self.frequency * f32::powi(self.lacunarity, self.nb_octaves - 1)
+    }
+
+    fn cost(&self) -> u32 {
+        // negative octave counts sample no octave
+        u32::try_from(self.nb_octaves).unwrap_or(0)
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("frequency", self.frequency),
+            ("nb_octaves", self.nb_octaves as f32),
+            ("decay", self.decay),
+            ("lacunarity", self.lacunarity),
+            ("threshold", self.threshold),
+        ]
+    }
+}
+
+pub fn mountain_layer(
+    frequency: f32,
+    nb_octaves: i32,
+    decay: f32,
+    lacunarity: f32,
+    threshold: f32,
+) -> MountainLayer {
+    MountainLayer {
+        frequency,
+        nb_octaves,
+        decay,
+        lacunarity,
+        threshold,
     }
 }
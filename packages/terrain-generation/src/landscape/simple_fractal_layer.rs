@@ -1,23 +1,42 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
 use crate::utils::math;
+use crate::utils::simplex_batch::sample_octaves;
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub fn simple_fractal_layer(
-    frequency: f32,
-    nb_octaves: i32,
-    decay: f32,
-    lacunarity: f32,
-    power: f32,
-    f: impl Fn(&Vector3, f32, &mut Vector3) -> f32,
-) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    move |coords: &Vector3, seed: f32, gradient: &mut Vector3| {
+pub struct SimpleFractalLayer<L: TerrainLayer> {
+    pub frequency: f32,
+    pub nb_octaves: i32,
+    pub decay: f32,
+    pub lacunarity: f32,
+    pub power: f32,
+    pub basis: L,
+}
+
+impl<L: TerrainLayer> SimpleFractalLayer<L> {
+    fn total_amplitude(&self) -> f32 {
+        (1.0 - f32::powi(1.0 / self.decay, self.nb_octaves + 1)) / (1.0 - 1.0 / self.decay)
+    }
+
+    fn octave_frequencies(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.nb_octaves).map(|i| self.frequency * f32::powi(self.lacunarity, i))
+    }
+
+    /// Sums the octaves of the basis
+    /// * `octave` - Samples the basis at the given octave and frequency, adding its gradient
+    fn combine_octaves(
+        &self,
+        gradient: &mut Vector3,
+        mut octave: impl FnMut(usize, f32, &mut Vector3) -> f32,
+    ) -> f32 {
         let mut noise_value = 0.0;
-        let total_amplitude = (1.0 - f32::powi(1.0 / decay, nb_octaves + 1)) / (1.0 - 1.0 / decay);
-        let mut local_gradient = Vector3::zero();
-        for i in 0..nb_octaves {
-            let local_frequency = frequency * f32::powi(lacunarity, i);
-            let local_elevation =
-                f(&(coords * local_frequency), seed, &mut local_gradient) / f32::powi(decay, i);
-            local_gradient *= local_frequency / f32::powi(decay, i);
+        let total_amplitude = self.total_amplitude();
+        for (i, local_frequency) in self.octave_frequencies().enumerate() {
+            let decay = f32::powi(self.decay, i as i32);
+            let mut local_gradient = Vector3::zero();
+            let local_elevation = octave(i, local_frequency, &mut local_gradient) / decay;
+            local_gradient *= local_frequency / decay;
 
             noise_value += local_elevation;
             *gradient += &local_gradient;
@@ -25,6 +44,93 @@ pub fn simple_fractal_layer(
         noise_value /= total_amplitude;
         *gradient /= total_amplitude;
 
-        math::pow(noise_value, power, gradient)
+        math::pow(noise_value, self.power, gradient)
+    }
+}
+
+impl<L: TerrainLayer> TerrainLayer for SimpleFractalLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        self.combine_octaves(gradient, |_, frequency, octave_gradient| {
+            self.basis
+                .sample(&(coords * frequency as f64), seed, octave_gradient)
+        })
+    }
+
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        let octaves = sample_octaves(
+            coords,
+            self.octave_frequencies(),
+            |coords, values, gradients| self.basis.sample_batch(coords, seed, values, gradients),
+        );
+        for (point, (value, gradient)) in values.iter_mut().zip(gradients).enumerate() {
+            *value = self.combine_octaves(gradient, |i, _, octave_gradient| {
+                *octave_gradient += &octaves[i].1[point];
+                octaves[i].0[point]
+            });
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "simple_fractal"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        // the basis is expected to be positive for the power function to be defined
+        let (low, high) = self.basis.bounds();
+        let octave_amplitudes: f32 = (0..self.nb_octaves)
+            .map(|i| 1.0 / f32::powi(self.decay, i))
+            .sum();
+        let ratio = octave_amplitudes / self.total_amplitude();
+        (
+            f32::powf(low * ratio, self.power),
+            f32::powf(high * ratio, self.power),
+        )
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.frequency
+            * f32::powi(self.lacunarity, self.nb_octaves - 1)
+            * self.basis.max_frequency()
+    }
+
+    fn cost(&self) -> u32 {
+        // negative octave counts sample no octave
+        u32::try_from(self.nb_octaves)
+            .unwrap_or(0)
+            .saturating_mul(self.basis.cost())
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("frequency", self.frequency),
+            ("nb_octaves", self.nb_octaves as f32),
+            ("decay", self.decay),
+            ("lacunarity", self.lacunarity),
+            ("power", self.power),
+        ]
+    }
+}
+
+pub fn simple_fractal_layer<L: TerrainLayer>(
+    frequency: f32,
+    nb_octaves: i32,
+    decay: f32,
+    lacunarity: f32,
+    power: f32,
+    basis: L,
+) -> SimpleFractalLayer<L> {
+    SimpleFractalLayer {
+        frequency,
+        nb_octaves,
+        decay,
+        lacunarity,
+        power,
+        basis,
     }
 }
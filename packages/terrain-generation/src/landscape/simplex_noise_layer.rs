@@ -1,6 +1,56 @@
-use crate::landscape::simple_fractal_layer::simple_fractal_layer;
+use crate::landscape::simple_fractal_layer::{simple_fractal_layer, SimpleFractalLayer};
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
 use crate::utils::simplex::simplex401;
+use crate::utils::simplex_batch::simplex401_batch;
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// A single octave of simplex noise in [0, 1]
+pub struct SimplexLayer;
+
+impl TerrainLayer for SimplexLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let mut local_gradient = Vector3::zero();
+        let noise_value = simplex401(coords, seed, &mut local_gradient);
+        *gradient += &local_gradient;
+        noise_value
+    }
+
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        let mut local_gradients = vec![Vector3::zero(); coords.len()];
+        simplex401_batch(coords, seed, values, &mut local_gradients);
+        for (gradient, local_gradient) in gradients.iter_mut().zip(&local_gradients) {
+            *gradient += local_gradient;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "simplex"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        (0.0, 1.0)
+    }
+
+    fn max_frequency(&self) -> f32 {
+        1.0
+    }
+
+    fn cost(&self) -> u32 {
+        1
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+}
 
 pub fn simplex_noise_layer(
     frequency: f32,
@@ -8,6 +58,13 @@ pub fn simplex_noise_layer(
     decay: f32,
     lacunarity: f32,
     power: f32,
-) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    simple_fractal_layer(frequency, nb_octaves, decay, lacunarity, power, simplex401)
+) -> SimpleFractalLayer<SimplexLayer> {
+    simple_fractal_layer(
+        frequency,
+        nb_octaves,
+        decay,
+        lacunarity,
+        power,
+        SimplexLayer,
+    )
 }
@@ -0,0 +1,246 @@
+use crate::seed::Seed;
+use crate::utils::random::pcg3d;
+use crate::utils::vector3::Vector3;
+use std::f32::consts::PI;
+
+/// The probability for a plate to be oceanic
+const OCEANIC_PROBABILITY: f32 = 0.6;
+
+/// The boundary weight under which two plates are considered too far from each other to interact
+const MIN_BOUNDARY_WEIGHT: f32 = 1e-6;
+
+/// A rigid plate of the crust rotating around the center of the planet
+pub struct TectonicPlate {
+    /// The center of the plate on the unit sphere
+    pub center: Vector3,
+    /// The angular velocity of the plate: the velocity of a point `p` of the plate is `angular_velocity x p`
+    pub angular_velocity: Vector3,
+    pub is_oceanic: bool,
+}
+
+/// The fields derived from the plates at one point of the sphere, with their gradients
+pub struct TectonicSample {
+    /// 1 inside continental plates, 0 inside oceanic plates, blended across boundaries
+    pub continentalness: f32,
+    pub continentalness_gradient: Vector3,
+    /// The speed at which plates collide along convergent boundaries in [0, 2]
+    pub collision: f32,
+    pub collision_gradient: Vector3,
+    /// The speed at which plates separate along divergent boundaries in [0, 2]
+    pub rifting: f32,
+    pub rifting_gradient: Vector3,
+}
+
+/// A partition of the sphere into plates moving relative to each other
+pub struct TectonicPlates {
+    pub plates: Vec<TectonicPlate>,
+    /// The width of the plate boundaries on the unit sphere
+    pub boundary_width: f32,
+}
+
+/// Uniform random values in [0, 1] for the given plate
+fn hash01(index: u32, salt: u32, seed: u32) -> [f32; 3] {
+    let (seed_x, seed_y, seed_z) = pcg3d(seed, seed, seed);
+    let (a, b, c) = pcg3d(index ^ seed_x, salt ^ seed_y, seed_z);
+    [a, b, c].map(|x| x as f32 / u32::MAX as f32)
+}
+
+/// A uniformly distributed point on the unit sphere
+fn random_unit_vector(random: [f32; 3]) -> Vector3 {
+    let z = 2.0 * random[0] - 1.0;
+    let phi = 2.0 * PI * random[1];
+    let radius = f32::sqrt(1.0 - z * z);
+    Vector3::new(radius * f32::cos(phi), radius * f32::sin(phi), z)
+}
+
+impl TectonicPlates {
+    /// Partitions the sphere into plates with random velocities
+    /// * `seed` - The seed of the planet
+    /// * `nb_plates` - The number of plates
+    /// * `boundary_width` - The width of the plate boundaries on the unit sphere
+    pub fn generate(seed: Seed, nb_plates: u32, boundary_width: f32) -> TectonicPlates {
+        let seed = seed.bits();
+        let plates = (0..nb_plates)
+            .map(|i| {
+                let center = random_unit_vector(hash01(i, 0, seed));
+
+                let velocity_random = hash01(i, 1, seed);
+                let angular_velocity =
+                    random_unit_vector(velocity_random) * (0.5 + 0.5 * velocity_random[2]);
+
+                // make sure there is at least one ocean and one continent
+                let is_oceanic = match i {
+                    0 => false,
+                    1 => true,
+                    _ => hash01(i, 2, seed)[0] < OCEANIC_PROBABILITY,
+                };
+
+                TectonicPlate {
+                    center,
+                    angular_velocity,
+                    is_oceanic,
+                }
+            })
+            .collect();
+
+        TectonicPlates {
+            plates,
+            boundary_width,
+        }
+    }
+
+    /// The sharpness of the soft assignment of points to plates, chosen so that the boundary fields
+    /// fall to about 10% of their maximum at `boundary_width` from the boundary between plates of typical size
+    fn sharpness(&self) -> f32 {
+        let typical_spacing = 2.0 * f32::sqrt(PI / self.plates.len() as f32);
+        3.64 / (typical_spacing * f32::max(self.boundary_width, 1e-4))
+    }
+
+    /// The index of the plate containing the given point of the unit sphere and the proximity to its center
+    fn closest_plate(&self, coords: &Vector3) -> (usize, f32) {
+        self.plates
+            .iter()
+            .enumerate()
+            .fold((0, f32::MIN), |(best_index, best), (index, plate)| {
+                let proximity = Vector3::dot(coords, &plate.center);
+                if proximity > best {
+                    (index, proximity)
+                } else {
+                    (best_index, best)
+                }
+            })
+    }
+
+    /// The index of the plate containing the given point of the unit sphere
+    pub fn plate_index(&self, coords: &Vector3) -> usize {
+        self.closest_plate(coords).0
+    }
+
+    /// The distance from the given point of the unit sphere to the closest boundary of its plate,
+    /// with its gradient (2 when there is a single plate)
+    pub fn boundary_distance(&self, coords: &Vector3) -> (f32, Vector3) {
+        // the exact distance to the closest bisector plane between the plate and its neighbours
+        let plate_index = self.plate_index(coords);
+        let center = &self.plates[plate_index].center;
+        let mut boundary_distance = f32::MAX;
+        let mut boundary_distance_gradient = Vector3::zero();
+        for (index, plate) in self.plates.iter().enumerate() {
+            if index == plate_index {
+                continue;
+            }
+            let difference = center - &plate.center;
+            let length = difference.length();
+            if length <= 0.0 {
+                continue;
+            }
+            let distance = Vector3::dot(coords, &difference) / length;
+            if distance < boundary_distance {
+                boundary_distance = distance;
+                boundary_distance_gradient = difference / length;
+            }
+        }
+        if self.plates.len() < 2 {
+            boundary_distance = 2.0;
+        }
+
+        (boundary_distance, boundary_distance_gradient)
+    }
+
+    /// Samples the tectonic fields at the given point of the unit sphere, without allocating
+    pub fn sample(&self, coords: &Vector3) -> TectonicSample {
+        let sharpness = self.sharpness();
+
+        // soft assignment to the closest plate: the weights are a softmax of the proximity to the plate centers
+        let (_, max_proximity) = self.closest_plate(coords);
+        let exponential = |plate: &TectonicPlate| {
+            f32::exp(sharpness * (Vector3::dot(coords, &plate.center) - max_proximity))
+        };
+
+        let mut total = 0.0;
+        let mut mean_center = Vector3::zero();
+        let mut continentalness = 0.0;
+        let mut continental_center = Vector3::zero();
+        for plate in &self.plates {
+            let exponential = exponential(plate);
+            total += exponential;
+            mean_center += &plate.center * exponential;
+            if !plate.is_oceanic {
+                continentalness += exponential;
+                continental_center += &plate.center * exponential;
+            }
+        }
+        mean_center /= total;
+        continentalness /= total;
+        continental_center /= total;
+
+        // the gradient of the weight of plate i is sharpness * w_i * (c_i - mean_center)
+        let weight = |plate: &TectonicPlate| exponential(plate) / total;
+        let weight_gradient = |plate: &TectonicPlate, weight: f32| {
+            (&plate.center - &mean_center) * (sharpness * weight)
+        };
+        let continentalness_gradient =
+            (continental_center - &mean_center * continentalness) * sharpness;
+
+        // the product of the weights of two plates peaks along their common boundary.
+        // Only the plates close enough to the point to weigh on a boundary are paired,
+        // so that the cost grows with the number of plates rather than its square
+        let mut collision = 0.0;
+        let mut collision_gradient = Vector3::zero();
+        let mut rifting = 0.0;
+        let mut rifting_gradient = Vector3::zero();
+        for (i, plate_i) in self.plates.iter().enumerate() {
+            let weight_i = weight(plate_i);
+            if 4.0 * weight_i < MIN_BOUNDARY_WEIGHT {
+                continue;
+            }
+            let weight_gradient_i = weight_gradient(plate_i, weight_i);
+
+            for plate_j in &self.plates[i + 1..] {
+                let weight_j = weight(plate_j);
+                let boundary_weight = 4.0 * weight_i * weight_j;
+                if boundary_weight < MIN_BOUNDARY_WEIGHT {
+                    continue;
+                }
+                let boundary_weight_gradient = (&weight_gradient_i * weight_j
+                    + weight_gradient(plate_j, weight_j) * weight_i)
+                    * 4.0;
+
+                // the speed of plate i toward plate j: ((w_i - w_j) x p) . n = p . (n x (w_i - w_j))
+                let normal = (&plate_j.center - &plate_i.center).normalize_to_new();
+                let relative_angular_velocity =
+                    &plate_i.angular_velocity - &plate_j.angular_velocity;
+                let convergence_gradient = Vector3::cross(&normal, &relative_angular_velocity);
+                let convergence = Vector3::dot(coords, &convergence_gradient);
+
+                let (speed, speed_gradient, total, total_gradient) = if convergence > 0.0 {
+                    (
+                        convergence,
+                        convergence_gradient,
+                        &mut collision,
+                        &mut collision_gradient,
+                    )
+                } else {
+                    (
+                        -convergence,
+                        &convergence_gradient * -1.0,
+                        &mut rifting,
+                        &mut rifting_gradient,
+                    )
+                };
+
+                *total += speed * boundary_weight;
+                *total_gradient +=
+                    &speed_gradient * boundary_weight + &boundary_weight_gradient * speed;
+            }
+        }
+
+        TectonicSample {
+            continentalness,
+            continentalness_gradient,
+            collision,
+            collision_gradient,
+            rifting,
+            rifting_gradient,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::landscape::simple_fractal_layer::SimpleFractalLayer;
+use crate::landscape::simplex_noise_layer::{simplex_noise_layer, SimplexLayer};
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
+use crate::utils::math::{multiply, scale, smoothstep, terrace};
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// Turns an elevation into stepped plateaus in the areas selected by a low frequency coverage mask
+pub struct TerraceLayer {
+    /// The frequency of the terraced features, used to estimate the width of the steps
+    pub frequency: f32,
+    pub nb_steps: f32,
+    pub sharpness: f32,
+    /// The fraction of the surface covered by terraces, in [0, 1]
+    pub coverage: f32,
+    pub mask: SimpleFractalLayer<SimplexLayer>,
+}
+
+impl TerraceLayer {
+    /// How much of the terraces can be resolved with the given distance between vertices on the unit sphere (0 = none, 1 = all)
+    pub fn level_of_detail(&self, vertex_spacing: f32) -> f32 {
+        if vertex_spacing <= 0.0 {
+            return 1.0;
+        }
+
+        // the features rise over roughly a quarter of their wavelength
+        let step_width = 1.0 / (4.0 * self.frequency * self.nb_steps);
+
+        // the terraces fade out when a step spans less than a few vertices to avoid aliasing
+        smoothstep(1.0, 4.0, step_width / vertex_spacing, &mut Vector3::zero())
+    }
+
+    /// Terraces the given value and updates its gradient accordingly
+    /// * `coords` - The position of the sample on the unit sphere
+    /// * `seed` - The seed of the planet
+    /// * `vertex_spacing` - The distance between vertices on the unit sphere (0 for full detail)
+    /// * `value` - The value to terrace
+    /// * `gradient` - The gradient of the value WILL STORE THE RESULT
+    pub fn apply(
+        &self,
+        coords: &Vector3d,
+        seed: &NoiseSeed,
+        vertex_spacing: f32,
+        value: f32,
+        gradient: &mut Vector3,
+    ) -> f32 {
+        if self.coverage <= 0.0 || self.nb_steps <= 0.0 {
+            return value;
+        }
+
+        let level_of_detail = self.level_of_detail(vertex_spacing);
+        if level_of_detail <= 0.0 {
+            return value;
+        }
+
+        let mut mask_gradient = Vector3::zero();
+        let mut mask = self.mask.sample(coords, seed, &mut mask_gradient);
+
+        // full coverage must select the whole mask range and no coverage must select nothing
+        let mask_edge = 1.1 * (1.0 - self.coverage) - 0.1;
+        mask = smoothstep(mask_edge, mask_edge + 0.1, mask, &mut mask_gradient);
+        mask = scale(mask, level_of_detail, &mut mask_gradient);
+
+        let mut terrace_gradient = gradient.clone();
+        let terraced_value = terrace(value, self.nb_steps, self.sharpness, &mut terrace_gradient);
+
+        // blend between the value and its terraced version using the mask
+        let mut difference_gradient = &terrace_gradient - gradient;
+        let difference = multiply(
+            terraced_value - value,
+            mask,
+            &mut difference_gradient,
+            &mask_gradient,
+        );
+
+        *gradient += &difference_gradient;
+        value + difference
+    }
+}
+
+pub fn terrace_layer(frequency: f32, nb_steps: f32, sharpness: f32, coverage: f32) -> TerraceLayer {
+    TerraceLayer {
+        frequency,
+        nb_steps,
+        sharpness,
+        coverage,
+        mask: simplex_noise_layer(frequency / 20.0, 1, 2.0, 2.0, 1.0),
+    }
+}
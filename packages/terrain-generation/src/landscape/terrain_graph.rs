@@ -0,0 +1,357 @@
+use crate::landscape::combinator_layers::{
+    AddLayer, MultiplyLayer, SMaxLayer, ScaleLayer, SmoothstepLayer,
+};
+use crate::landscape::constant_layers::constant_layer;
+use crate::landscape::continent_layer::continent_layer;
+use crate::landscape::crater_layer::CraterLayer;
+use crate::landscape::domain_warp_layer::domain_warp_layer;
+use crate::landscape::make_terrain_function::{
+    bumps_layer, mountain_mask_layer, TerrainFunction, TerrainRowFunction,
+};
+use crate::landscape::mountain_layer::mountain_layer;
+use crate::landscape::simplex_noise_layer::simplex_noise_layer;
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::landscape::uber_noise_layer::uber_noise_layer;
+use crate::seed::{NoiseSeed, Salt};
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// The sub-seeds of the planet a node can be sampled with, named after the layers of `make_terrain_function` using them
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubSeed {
+    Continents,
+    Mountains,
+    MountainMask,
+    Bumps,
+    Craters,
+}
+
+impl SubSeed {
+    fn salt(self) -> Salt {
+        match self {
+            SubSeed::Continents => Salt::Continents,
+            SubSeed::Mountains => Salt::Mountains,
+            SubSeed::MountainMask => Salt::MountainMask,
+            SubSeed::Bumps => Salt::Bumps,
+            SubSeed::Craters => Salt::Craters,
+        }
+    }
+}
+
+/// The input sampled with a sub-seed of the planet instead of the seed it is given
+pub struct SubSeedLayer<L: TerrainLayer> {
+    pub input: L,
+    pub sub_seed: SubSeed,
+}
+
+impl<L: TerrainLayer> TerrainLayer for SubSeedLayer<L> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        self.input
+            .sample(coords, seed.derive(self.sub_seed.salt()), gradient)
+    }
+
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        self.input
+            .sample_batch(coords, seed.derive(self.sub_seed.salt()), values, gradients)
+    }
+
+    fn name(&self) -> &'static str {
+        "sub_seed"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        self.input.bounds()
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.input.max_frequency()
+    }
+
+    fn cost(&self) -> u32 {
+        self.input.cost()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+}
+
+/// A node of a declarative terrain graph.
+///
+/// Leaves are the layers of the `landscape` module, inner nodes combine the output of their inputs
+/// using the gradient-aware helpers of `utils::math`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TerrainNode {
+    Constant {
+        value: f32,
+    },
+    Continent {
+        frequency: f32,
+        fragmentation: f32,
+    },
+    Crater {
+        frequency: f32,
+        nb_octaves: u32,
+        density: f32,
+        size_exponent: f32,
+        rim_height: f32,
+        peak_height: f32,
+        ejecta_height: f32,
+    },
+    Mountain {
+        frequency: f32,
+        nb_octaves: i32,
+        decay: f32,
+        lacunarity: f32,
+        threshold: f32,
+    },
+    SimplexNoise {
+        frequency: f32,
+        nb_octaves: i32,
+        decay: f32,
+        lacunarity: f32,
+        power: f32,
+    },
+    UberNoise {
+        frequency: f32,
+        nb_octaves: i32,
+        decay: f32,
+        lacunarity: f32,
+        threshold: f32,
+    },
+    /// The mask of the mountains of `make_terrain_function`, given the frequency of the mountains
+    MountainMask {
+        frequency: f32,
+    },
+    /// The bumps of `make_terrain_function`
+    Bumps {
+        frequency: f32,
+    },
+    /// Sum of all the inputs
+    Add {
+        inputs: Vec<TerrainNode>,
+    },
+    /// Product of all the inputs
+    Multiply {
+        inputs: Vec<TerrainNode>,
+    },
+    /// The input multiplied by a constant factor
+    Scale {
+        input: Box<TerrainNode>,
+        factor: f32,
+    },
+    Smoothstep {
+        input: Box<TerrainNode>,
+        edge0: f32,
+        edge1: f32,
+    },
+    /// The input sampled at a position offset by a vector noise field
+    DomainWarp {
+        input: Box<TerrainNode>,
+        strength: f32,
+        frequency: f32,
+    },
+    /// Smooth maximum of two inputs with smoothness factor `k`
+    SMax {
+        a: Box<TerrainNode>,
+        b: Box<TerrainNode>,
+        k: f32,
+    },
+    /// The input sampled with a sub-seed of the planet, so that its features are not correlated with the other layers
+    SubSeed {
+        input: Box<TerrainNode>,
+        sub_seed: SubSeed,
+    },
+}
+
+impl TerrainNode {
+    /// Compiles the node and its inputs into a single layer with analytic gradients
+    pub fn compile(&self) -> Box<dyn TerrainLayer> {
+        match self {
+            TerrainNode::Constant { value } => Box::new(constant_layer(*value)),
+            TerrainNode::Continent {
+                frequency,
+                fragmentation,
+            } => Box::new(continent_layer(*frequency, *fragmentation)),
+            TerrainNode::Crater {
+                frequency,
+                nb_octaves,
+                density,
+                size_exponent,
+                rim_height,
+                peak_height,
+                ejecta_height,
+            } => Box::new(CraterLayer {
+                frequency: *frequency,
+                nb_octaves: *nb_octaves,
+                density: *density,
+                size_exponent: *size_exponent,
+                rim_height: *rim_height,
+                peak_height: *peak_height,
+                ejecta_height: *ejecta_height,
+            }),
+            TerrainNode::Mountain {
+                frequency,
+                nb_octaves,
+                decay,
+                lacunarity,
+                threshold,
+            } => Box::new(mountain_layer(
+                *frequency,
+                *nb_octaves,
+                *decay,
+                *lacunarity,
+                *threshold,
+            )),
+            TerrainNode::SimplexNoise {
+                frequency,
+                nb_octaves,
+                decay,
+                lacunarity,
+                power,
+            } => Box::new(simplex_noise_layer(
+                *frequency,
+                *nb_octaves,
+                *decay,
+                *lacunarity,
+                *power,
+            )),
+            TerrainNode::UberNoise {
+                frequency,
+                nb_octaves,
+                decay,
+                lacunarity,
+                threshold,
+            } => Box::new(uber_noise_layer(
+                *frequency,
+                *nb_octaves,
+                *decay,
+                *lacunarity,
+                *threshold,
+            )),
+            TerrainNode::MountainMask { frequency } => Box::new(mountain_mask_layer(*frequency)),
+            TerrainNode::Bumps { frequency } => Box::new(bumps_layer(*frequency)),
+            TerrainNode::Add { inputs } => Box::new(AddLayer {
+                inputs: inputs.iter().map(TerrainNode::compile).collect(),
+            }),
+            TerrainNode::Multiply { inputs } => Box::new(MultiplyLayer {
+                inputs: inputs.iter().map(TerrainNode::compile).collect(),
+            }),
+            TerrainNode::Scale { input, factor } => Box::new(ScaleLayer {
+                input: input.compile(),
+                factor: *factor,
+            }),
+            TerrainNode::Smoothstep {
+                input,
+                edge0,
+                edge1,
+            } => Box::new(SmoothstepLayer {
+                input: input.compile(),
+                edge0: *edge0,
+                edge1: *edge1,
+            }),
+            TerrainNode::DomainWarp {
+                input,
+                strength,
+                frequency,
+            } => Box::new(domain_warp_layer(input.compile(), *strength, *frequency)),
+            TerrainNode::SMax { a, b, k } => Box::new(SMaxLayer {
+                a: a.compile(),
+                b: b.compile(),
+                k: *k,
+            }),
+            TerrainNode::SubSeed { input, sub_seed } => Box::new(SubSeedLayer {
+                input: input.compile(),
+                sub_seed: *sub_seed,
+            }),
+        }
+    }
+}
+
+/// A data-driven description of a terrain: the elevation node is displaced along the normal of the unit sphere
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[wasm_bindgen]
+pub struct TerrainGraph {
+    #[wasm_bindgen(skip)]
+    pub elevation: TerrainNode,
+}
+
+impl TerrainGraph {
+    pub fn from_json(json: &str) -> serde_json::Result<TerrainGraph> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Compiles the graph into a row function with the same contract as `make_terrain_row_function`
+    pub fn compile_rows(&self) -> Box<TerrainRowFunction> {
+        let elevation_function = self.elevation.compile();
+
+        Box::new(
+            move |unit_sample_points: &[Vector3d],
+                  seed: &NoiseSeed,
+                  _vertex_spacing: f32,
+                  out_positions: &mut [Vector3],
+                  out_gradients: &mut [Vector3]| {
+                let mut elevations = vec![0.0; unit_sample_points.len()];
+                elevation_function.sample_batch(
+                    unit_sample_points,
+                    seed,
+                    &mut elevations,
+                    out_gradients,
+                );
+
+                for ((out_position, unit_sample_point), elevation) in out_positions
+                    .iter_mut()
+                    .zip(unit_sample_points)
+                    .zip(elevations)
+                {
+                    *out_position += &unit_sample_point.to_f32() * elevation;
+                }
+            },
+        )
+    }
+
+    /// Compiles the graph into a terrain function with the same contract as `make_terrain_function`
+    pub fn compile(&self) -> Box<TerrainFunction> {
+        let elevation_function = self.elevation.compile();
+
+        Box::new(
+            move |unit_sample_point: &Vector3d,
+                  seed: &NoiseSeed,
+                  _vertex_spacing: f32,
+                  out_position: &mut Vector3,
+                  out_gradient: &mut Vector3| {
+                let mut gradient = Vector3::zero();
+                let elevation = elevation_function.sample(unit_sample_point, seed, &mut gradient);
+
+                *out_position += &unit_sample_point.to_f32() * elevation;
+                *out_gradient += gradient;
+            },
+        )
+    }
+}
+
+/// Reads a terrain graph authored as JSON, to build chunks with `build_chunk_vertex_data_with_graph`
+#[wasm_bindgen]
+pub fn terrain_graph_from_json(json: &str) -> Result<TerrainGraph, JsError> {
+    Ok(TerrainGraph::from_json(json)?)
+}
+
+/// Serializes a terrain graph as JSON
+#[wasm_bindgen]
+pub fn terrain_graph_to_json(graph: &TerrainGraph) -> Result<String, JsError> {
+    Ok(graph.to_json()?)
+}
@@ -0,0 +1,89 @@
+use crate::seed::NoiseSeed;
+use crate::utils::dual::Dual;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// A scalar field sampled on the unit sphere with analytic gradients.
+/// Layers are shared by the threads building the chunks, hence `Send + Sync`.
+pub trait TerrainLayer: Send + Sync {
+    /// Samples the layer at the given coordinates and adds its gradient to `gradient`
+    /// * `coords` - The position to sample the layer at, in double precision to keep the noise smooth on deep chunks
+    /// * `seed` - The seed of the planet
+    /// * `gradient` - The recipient for the gradient of the layer
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32;
+
+    /// Samples the layer at a row of coordinates, which layers built on simplex noise evaluate several points at a time.
+    /// The results are the same as calling `sample` on every point.
+    /// * `values` - The recipient for the values of the layer (will be overridden)
+    /// * `gradients` - The recipients for the gradients of the layer
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        for ((coords, value), gradient) in coords.iter().zip(values).zip(gradients) {
+            *value = self.sample(coords, seed, gradient);
+        }
+    }
+
+    /// Samples the layer as a dual number, to combine it with other fields in plain arithmetic
+    fn sample_dual(&self, coords: &Vector3d, seed: &NoiseSeed) -> Dual {
+        let mut gradient = Vector3::zero();
+        let value = self.sample(coords, seed, &mut gradient);
+        Dual::new(value, &gradient)
+    }
+
+    /// A human-readable identifier for editor tooling
+    fn name(&self) -> &'static str;
+
+    /// Estimated lower and upper bounds of the values returned by `sample`.
+    /// They follow from the expected range of the noise, which is not exact, so the samples may slightly exceed them
+    fn bounds(&self) -> (f32, f32);
+
+    /// The highest frequency at which the underlying noise is sampled
+    fn max_frequency(&self) -> f32;
+
+    /// The number of noise evaluations needed for one sample, saturating at `u32::MAX`
+    fn cost(&self) -> u32;
+
+    /// The parameters of the layer as name/value pairs
+    fn parameters(&self) -> Vec<(&'static str, f32)>;
+}
+
+impl<T: TerrainLayer + ?Sized> TerrainLayer for Box<T> {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        (**self).sample(coords, seed, gradient)
+    }
+
+    fn sample_batch(
+        &self,
+        coords: &[Vector3d],
+        seed: &NoiseSeed,
+        values: &mut [f32],
+        gradients: &mut [Vector3],
+    ) {
+        (**self).sample_batch(coords, seed, values, gradients)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        (**self).bounds()
+    }
+
+    fn max_frequency(&self) -> f32 {
+        (**self).max_frequency()
+    }
+
+    fn cost(&self) -> u32 {
+        (**self).cost()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        (**self).parameters()
+    }
+}
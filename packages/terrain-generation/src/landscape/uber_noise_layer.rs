@@ -1,47 +1,83 @@
+use crate::landscape::terrain_layer::TerrainLayer;
+use crate::seed::NoiseSeed;
 use crate::utils::math::{s_abs, s_max_gradient};
-use crate::utils::simplex;
-use crate::utils::simplex::simplex411;
+use crate::utils::simplex::simplex411_hessian;
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
-pub fn uber_noise_layer(
-    frequency: f32,
-    nb_octaves: i32,
-    decay: f32,
-    lacunarity: f32,
-    threshold: f32,
-) -> impl Fn(&Vector3, f32, &mut Vector3) -> f32 {
-    move |coords: &Vector3, seed: f32, gradient: &mut Vector3| {
+pub struct UberNoiseLayer {
+    pub frequency: f32,
+    pub nb_octaves: i32,
+    pub decay: f32,
+    pub lacunarity: f32,
+    pub threshold: f32,
+}
+
+/// The product of a symmetric matrix given by its rows with a vector
+fn transform(matrix: &[Vector3; 3], vector: &Vector3) -> Vector3 {
+    Vector3::new(
+        Vector3::dot(&matrix[0], vector),
+        Vector3::dot(&matrix[1], vector),
+        Vector3::dot(&matrix[2], vector),
+    )
+}
+
+impl TerrainLayer for UberNoiseLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
         let mut noise_value = 0.0;
+        let mut noise_gradient = Vector3::zero();
         let mut amplitude = 1.0;
         let mut total_amplitude = 0.0;
+        let mut total_amplitude_gradient = Vector3::zero();
         let mut local_gradient = Vector3::zero();
-        for i in 0..nb_octaves {
-            let local_frequency = frequency * f32::powi(lacunarity, i);
-            let mut local_noise_value = if i != 0 {
-                let local_noise_value =
-                    simplex::simplex401(&(coords * local_frequency), seed, &mut local_gradient);
-                local_gradient *= local_frequency;
-                local_noise_value
+        let mut local_hessian = [Vector3::zero(), Vector3::zero(), Vector3::zero()];
+        for i in 0..self.nb_octaves {
+            let local_frequency = self.frequency * f32::powi(self.lacunarity, i);
+            let mut local_noise_value = simplex411_hessian(
+                &(coords * local_frequency as f64),
+                seed,
+                &mut local_gradient,
+                &mut local_hessian,
+            );
+            local_gradient *= local_frequency;
+            for row in &mut local_hessian {
+                *row *= local_frequency * local_frequency;
+            }
+
+            if i != 0 {
+                // [0,1] is half the length of [-1,1]
+                local_noise_value = (local_noise_value + 1.0) / 2.0;
+                local_gradient /= 2.0;
+                for row in &mut local_hessian {
+                    *row /= 2.0;
+                }
             } else {
-                let mut local_noise_value =
-                    simplex411(&(coords * local_frequency), seed, &mut local_gradient);
-                local_gradient *= local_frequency;
+                // the derivative of s_abs is tanh(k x), whose own derivative is k (1 - tanh(k x)^2)
+                let slope = f32::tanh(4.0 * local_noise_value);
+                let curvature = 4.0 * (1.0 - slope * slope);
+                let components = [local_gradient.x, local_gradient.y, local_gradient.z];
+                for (row, component) in local_hessian.iter_mut().zip(components) {
+                    *row = (&local_gradient * (curvature * component) + &*row * slope) * -1.0;
+                }
+
                 local_noise_value = 1.0 - s_abs(local_noise_value, 4.0, &mut local_gradient);
                 local_gradient *= -1.0;
-                local_noise_value
-            };
+            }
 
+            // the steeper the octave, the fainter it is
             let local_amplitude =
                 amplitude / (1.0 + local_gradient.length() * local_gradient.length());
-            total_amplitude += local_amplitude;
+            let local_amplitude_gradient = transform(&local_hessian, &local_gradient)
+                * (-2.0 * local_amplitude * local_amplitude / amplitude);
 
-            local_noise_value *= local_amplitude;
-            local_gradient *= local_amplitude;
+            noise_value += local_noise_value * local_amplitude;
+            noise_gradient += &local_gradient * local_amplitude;
+            noise_gradient += &local_amplitude_gradient * local_noise_value;
 
-            noise_value += local_noise_value;
-            *gradient += &local_gradient;
+            total_amplitude += local_amplitude;
+            total_amplitude_gradient += local_amplitude_gradient;
 
-            amplitude /= decay;
+            amplitude /= self.decay;
 
             // rotate sample point
             /*let theta = 1.3;
@@ -51,22 +87,73 @@ pub fn uber_noise_layer(
                 sample_point.z,
             );*/
         }
+        // quotient rule on the weighted average of the octaves
         noise_value /= total_amplitude;
-        *gradient /= total_amplitude;
+        noise_gradient -= total_amplitude_gradient * noise_value;
+        noise_gradient /= total_amplitude;
 
         noise_value = s_max_gradient(
             noise_value,
-            threshold,
+            self.threshold,
             10.0,
-            gradient,
+            &mut noise_gradient,
             &mut Vector3::new(0.0, 0.0, 0.0),
         );
 
-        noise_value -= threshold;
+        noise_value -= self.threshold;
+
+        noise_value /= 1.0 - self.threshold;
+        noise_gradient /= 1.0 - self.threshold;
 
-        noise_value /= 1.0 - threshold;
-        *gradient /= 1.0 - threshold;
+        *gradient += &noise_gradient;
 
         noise_value
     }
+
+    fn name(&self) -> &'static str {
+        "uber_noise"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        // the octaves are averaged in [0, 1] and the smooth maximum overshoots by at most ln(2) / k
+        (
+            0.0,
+            (1.0 + f32::ln(2.0) / 10.0 - self.threshold) / (1.0 - self.threshold),
+        )
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.frequency * f32::powi(self.lacunarity, self.nb_octaves - 1)
+    }
+
+    fn cost(&self) -> u32 {
+        // negative octave counts sample no octave
+        u32::try_from(self.nb_octaves).unwrap_or(0)
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("frequency", self.frequency),
+            ("nb_octaves", self.nb_octaves as f32),
+            ("decay", self.decay),
+            ("lacunarity", self.lacunarity),
+            ("threshold", self.threshold),
+        ]
+    }
+}
+
+pub fn uber_noise_layer(
+    frequency: f32,
+    nb_octaves: i32,
+    decay: f32,
+    lacunarity: f32,
+    threshold: f32,
+) -> UberNoiseLayer {
+    UberNoiseLayer {
+        frequency,
+        nb_octaves,
+        decay,
+        lacunarity,
+        threshold,
+    }
 }
@@ -1,19 +1,41 @@
+pub mod base_shape;
+pub mod biomes;
 pub mod build_data;
+pub mod chunk_layout;
+pub mod chunk_mesh;
 mod chunk_skirt;
+pub mod erosion_bake;
 pub mod landscape;
+mod parallel;
 pub mod return_data;
+pub mod seed;
+pub mod splat;
+mod surface;
 pub mod terrain_settings;
 pub mod utils;
+pub mod validation;
+pub mod volumetric_chunk;
 
+use crate::biomes::{BiomeClassifier, BIOME_STRIDE};
 use crate::build_data::BuildData;
-use crate::chunk_skirt::append_chunk_skirt;
-use crate::landscape::make_terrain_function::TerrainFunction;
+use crate::chunk_layout::{ChunkLayout, SCATTER_STRIDE};
+use crate::chunk_skirt::{append_chunk_skirt, copy_border_attributes};
+use crate::erosion_bake::erosion_bake;
+use crate::landscape::make_terrain_function::TerrainRowFunction;
+use crate::landscape::terrain_graph::TerrainGraph;
+use crate::parallel::map_rows;
 use crate::return_data::ReturnData;
+use crate::seed::{mix64, NoiseSeed, Seed};
+use crate::splat::{splat_weights, GRASS_WEIGHT, SPLAT_STRIDE};
+use crate::surface::Surface;
+use crate::terrain_settings::TerrainSettings;
 use crate::utils::direction::Direction;
 use crate::utils::triangle::scatter_in_triangle;
 use crate::utils::vector3::Vector3;
-use landscape::make_terrain_function::make_terrain_function;
-use std::cell::RefCell;
+use crate::utils::vector3d::Vector3d;
+use crate::validation::BuildError;
+use landscape::make_terrain_function::make_terrain_row_function;
+use std::sync::{Arc, Mutex};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -23,237 +45,468 @@ extern "C" {
 }
 
 struct TerrainCache {
-    seed: f32,
-    function: Box<TerrainFunction>,
+    seed: Seed,
+    settings: TerrainSettings,
+    graph: Option<TerrainGraph>,
+    function: Arc<TerrainRowFunction>,
+    noise_seed: Arc<NoiseSeed>,
 }
 
-thread_local! {
-    static TERRAIN_CACHE: RefCell<Option<TerrainCache>> = const { RefCell::new(None) };
+/// Shared by all the threads so that the terrain function is only compiled once per planet
+static TERRAIN_CACHE: Mutex<Option<TerrainCache>> = Mutex::new(None);
+
+/// Runs the given closure with the terrain function of the planet and its resolved seed,
+/// building them only when the seed, the settings or the graph change
+/// * `graph` - The graph replacing the terrain of the settings, if any
+pub(crate) fn with_terrain_function<R>(
+    seed: Seed,
+    settings: TerrainSettings,
+    graph: Option<&TerrainGraph>,
+    f: impl FnOnce(&TerrainRowFunction, &NoiseSeed) -> R,
+) -> R {
+    let (terrain_function, noise_seed) = {
+        let mut cache = TERRAIN_CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match cache.as_ref() {
+            Some(state)
+                if state.seed == seed
+                    && state.settings == settings
+                    && state.graph.as_ref() == graph =>
+            {
+                (state.function.clone(), state.noise_seed.clone())
+            }
+            _ => {
+                let function: Arc<TerrainRowFunction> = match graph {
+                    Some(graph) => Arc::from(graph.compile_rows()),
+                    None => Arc::from(make_terrain_row_function(settings)),
+                };
+                let noise_seed = Arc::new(NoiseSeed::new(seed));
+                *cache = Some(TerrainCache {
+                    seed,
+                    settings,
+                    graph: graph.cloned(),
+                    function: function.clone(),
+                    noise_seed: noise_seed.clone(),
+                });
+                (function, noise_seed)
+            }
+        }
+    };
+
+    // the lock is released so that other threads can build their chunks meanwhile
+    f(terrain_function.as_ref(), noise_seed.as_ref())
+}
+
+/// The position of a vertex of the chunk grid on a flat plane centered on the origin, in units of vertex spacing
+/// * `direction` - The direction of the quadtree the chunk belongs to
+/// * `x` - The first grid coordinate (may lie outside of the chunk)
+/// * `y` - The second grid coordinate (may lie outside of the chunk)
+/// * `nb_subdivisions` - The number of subdivisions of the chunk along one side
+pub(crate) fn chunk_plane_position(
+    direction: Direction,
+    x: f32,
+    y: f32,
+    nb_subdivisions: usize,
+) -> Vector3 {
+    let x = x - nb_subdivisions as f32 / 2.0;
+    let y = y - nb_subdivisions as f32 / 2.0;
+    match direction {
+        Direction::Forward => Vector3::new(x, y, 0.0),
+        Direction::Backward => Vector3::new(y, x, 0.0),
+        Direction::Left => Vector3::new(0.0, x, y),
+        Direction::Right => Vector3::new(0.0, y, x),
+        Direction::Up => Vector3::new(x, 0.0, y),
+        Direction::Down => Vector3::new(y, 0.0, x),
+    }
+}
+
+/// The share of the scatter density kept on a triangle: the mean grass weight of its vertices,
+/// or all of it when the splat weights are not computed
+/// * `splat_weights` - The splat buffer of the chunk (empty when skipped)
+/// * `triangle` - The indices of the vertices of the triangle
+fn scatter_coverage(splat_weights: &[f32], triangle: &[usize; 3]) -> f32 {
+    if splat_weights.is_empty() {
+        return 1.0;
+    }
+    triangle
+        .iter()
+        .map(|&index| splat_weights[SPLAT_STRIDE * index + GRASS_WEIGHT])
+        .sum::<f32>()
+        / 3.0
+}
+
+/// The vertex attributes of a row of the chunk, laid out like the buffers they are copied to
+struct VertexRow {
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    biomes: Vec<f32>,
+    splat_weights: Vec<f32>,
+}
+
+impl VertexRow {
+    fn with_capacity(nb_vertices: usize) -> VertexRow {
+        VertexRow {
+            positions: Vec::with_capacity(3 * nb_vertices),
+            normals: Vec::with_capacity(3 * nb_vertices),
+            biomes: Vec::new(),
+            splat_weights: Vec::new(),
+        }
+    }
 }
 
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 /// Fills the given buffers with the vertex data from the chunk
 /// * `data` - The data needed to guide the build process
 /// * `positions` - A mutable reference to the buffer that will be filled with vertex positions
 /// * `indices` - A mutable reference to the buffer that will be filled with the face indices
 /// * `normals` - A mutable reference to the buffer that will be filled with the vertex normals
+/// * `biomes` - A mutable reference to the buffer that will be filled with the two main biomes of each vertex and their weights (leave empty to skip the classification)
+/// * `splat_weights_buffer` - A mutable reference to the buffer that will be filled with the rock, sand, grass and snow weights of each vertex (leave empty to skip them)
 /// * `scattered_points_buffer` - A mutable reference to the buffer that will be filled with scattered point positions and normals
+/// (only on the grass when the splat weights are computed)
+///
+/// Fails when the data is invalid (see `BuildData::validate`), when the buffers have the wrong sizes or when the scatter buffer is too small
 pub fn build_chunk_vertex_data(
     data: &BuildData,
     positions: &mut [f32],
     indices: &mut [u16],
     normals: &mut [f32],
+    biomes: &mut [f32],
+    splat_weights_buffer: &mut [f32],
     scattered_points_buffer: &mut [f32],
     scatter_per_square_meter: f32,
-) -> ReturnData {
+) -> Result<ReturnData, JsError> {
+    let nb_instances_created = fill_chunk_buffers(
+        data,
+        None,
+        positions,
+        indices,
+        normals,
+        biomes,
+        splat_weights_buffer,
+        scattered_points_buffer,
+        scatter_per_square_meter,
+    )?;
+    Ok(ReturnData {
+        nb_instances_created,
+    })
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+/// Fills the buffers like `build_chunk_vertex_data` with the terrain of a graph instead of the terrain of the settings.
+/// The other settings (base shape, biomes, splat weights...) still apply
+/// * `graph` - The terrain graph of the planet, see `terrain_graph_from_json`
+pub fn build_chunk_vertex_data_with_graph(
+    data: &BuildData,
+    graph: &TerrainGraph,
+    positions: &mut [f32],
+    indices: &mut [u16],
+    normals: &mut [f32],
+    biomes: &mut [f32],
+    splat_weights_buffer: &mut [f32],
+    scattered_points_buffer: &mut [f32],
+    scatter_per_square_meter: f32,
+) -> Result<ReturnData, JsError> {
+    let nb_instances_created = fill_chunk_buffers(
+        data,
+        Some(graph),
+        positions,
+        indices,
+        normals,
+        biomes,
+        splat_weights_buffer,
+        scattered_points_buffer,
+        scatter_per_square_meter,
+    )?;
+    Ok(ReturnData {
+        nb_instances_created,
+    })
+}
+
+/// Fills the buffers like `build_chunk_vertex_data` and returns the number of scattered instances
+/// * `graph` - The graph replacing the terrain of the settings, if any
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fill_chunk_buffers(
+    data: &BuildData,
+    graph: Option<&TerrainGraph>,
+    positions: &mut [f32],
+    indices: &mut [u16],
+    normals: &mut [f32],
+    biomes: &mut [f32],
+    splat_weights_buffer: &mut [f32],
+    scattered_points_buffer: &mut [f32],
+    scatter_per_square_meter: f32,
+) -> Result<usize, BuildError> {
+    data.validate().map_err(BuildError::InvalidData)?;
+
     let planet_diameter = data.planet_diameter;
-    let depth = data.chunk_depth;
     let direction = data.chunk_tree_direction;
-    let chunk_cube_position = Vector3::new(
-        data.chunk_cube_position_x,
-        data.chunk_cube_position_y,
-        data.chunk_cube_position_z,
+    let chunk_cube_position = Vector3d::new(
+        data.chunk_cube_position_x as f64,
+        data.chunk_cube_position_y as f64,
+        data.chunk_cube_position_z as f64,
     );
 
     let seed = data.planet_seed;
 
-    let chunk_size = planet_diameter / i32::pow(2, depth) as f32;
+    let chunk_size = data.chunk_size();
     let planet_radius = planet_diameter / 2.0;
 
     let nb_vertices_per_row = data.resolution as usize;
     let nb_subdivisions = nb_vertices_per_row - 1;
-    let base_vertex_count = nb_vertices_per_row * nb_vertices_per_row;
-    let base_index_count = nb_subdivisions * nb_subdivisions * 2 * 3;
-    let skirt_vertex_count = 4 * nb_vertices_per_row;
-    let skirt_index_count = 4 * nb_subdivisions * 2 * 3;
 
     let rescale_factor = chunk_size / nb_subdivisions as f32;
     let skirt_depth = rescale_factor * 2.0;
-    let has_base_buffers = positions.len() == base_vertex_count * 3
-        && normals.len() == base_vertex_count * 3
-        && indices.len() == base_index_count;
-    let has_skirt_buffers = positions.len() == (base_vertex_count + skirt_vertex_count) * 3
-        && normals.len() == (base_vertex_count + skirt_vertex_count) * 3
-        && indices.len() == base_index_count + skirt_index_count;
-
-    let should_generate_skirt = if has_skirt_buffers {
-        true
-    } else if has_base_buffers {
-        false
+    let vertex_spacing = rescale_factor / planet_radius;
+
+    // the skirt is generated when the buffers are large enough to hold it
+    let layout = [true, false]
+        .map(|has_skirt| ChunkLayout::for_chunk(data, has_skirt, scatter_per_square_meter))
+        .into_iter()
+        .find(|layout| {
+            positions.len() == layout.positions_length
+                && normals.len() == layout.normals_length
+                && indices.len() == layout.indices_length
+        })
+        .ok_or(BuildError::InvalidBufferSizes {
+            positions: positions.len(),
+            normals: normals.len(),
+            indices: indices.len(),
+        })?;
+    let should_generate_skirt = layout.has_skirt();
+
+    let biome_classifier = if biomes.is_empty() {
+        None
+    } else if biomes.len() == layout.biomes_length {
+        Some(BiomeClassifier::new(&data.terrain_settings))
     } else {
-        panic!(
-            "Invalid chunk buffer sizes: positions={}, normals={}, indices={}",
-            positions.len(),
-            normals.len(),
-            indices.len()
-        );
+        return Err(BuildError::InvalidBiomeBufferSize {
+            length: biomes.len(),
+            expected: layout.biomes_length,
+        });
     };
 
-    let mut instance_index: usize = 0;
-    let mut excess_instance_number: f32 = 0.0;
+    let has_splat_weights = if splat_weights_buffer.is_empty() {
+        false
+    } else if splat_weights_buffer.len() == layout.splat_weights_length {
+        true
+    } else {
+        return Err(BuildError::InvalidSplatWeightBufferSize {
+            length: splat_weights_buffer.len(),
+            expected: layout.splat_weights_length,
+        });
+    };
 
     // the offset used to bring back the vertices close to the origin (the position of the chunk on the sphere)
-    let mut chunk_sphere_position = chunk_cube_position.clone();
-    chunk_sphere_position.set_magnitude_in_place(planet_radius);
-
-    TERRAIN_CACHE.with(|cache| {
-        let mut cache_mut = cache.borrow_mut();
-        if cache_mut
-            .as_ref()
-            .map(|state| state.seed == seed)
-            .unwrap_or(false)
-        {
-            // cache is already initialised with the current seed
-        } else {
-            *cache_mut = Some(TerrainCache {
-                seed,
-                function: make_terrain_function(data.terrain_settings),
-            });
-        }
-
-        let terrain_function = &mut cache_mut
-            .as_mut()
-            .expect("terrain function cache should be initialized")
-            .function;
-
-        for x in 0..nb_vertices_per_row {
-            for y in 0..nb_vertices_per_row {
-                // create flat plane with the right orientation
-                let mut vertex_position = match direction {
-                    Direction::Forward => Vector3::new(
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                        0.0,
-                    ),
-                    Direction::Backward => Vector3::new(
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                        0.0,
-                    ),
-                    Direction::Left => Vector3::new(
-                        0.0,
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                    ),
-                    Direction::Right => Vector3::new(
-                        0.0,
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                    ),
-                    Direction::Up => Vector3::new(
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                        0.0,
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                    ),
-                    Direction::Down => Vector3::new(
-                        y as f32 - nb_subdivisions as f32 / 2.0,
-                        0.0,
-                        x as f32 - nb_subdivisions as f32 / 2.0,
-                    ),
-                };
-
-                // resize the plane to the size of the chunk
-                vertex_position *= rescale_factor;
+    let chunk_sphere_position = &chunk_cube_position.normalize_to_new() * planet_radius as f64;
 
-                // move it to the surface of the cube sphere
-                vertex_position += &chunk_cube_position;
-
-                // normalize and then scale to planet radius to morph the cube into a sphere
-                vertex_position.set_magnitude_in_place(planet_radius);
+    // a bake erodes the terrain of the settings, which a graph replaces
+    let erosion = match graph {
+        None => erosion_bake(seed, &data.terrain_settings, planet_radius),
+        Some(_) => None,
+    };
 
-                // apply terrain function to the current vertex (use normalized coordinates for scale invariance)
-                let unit_sphere_coords = vertex_position.normalize_to_new();
-                let mut vertex_gradient = Vector3::zero();
-                terrain_function(
-                    &unit_sphere_coords,
-                    seed,
-                    &mut vertex_position,
-                    &mut vertex_gradient,
-                );
-                vertex_gradient /= planet_radius;
-
-                // Resource: https://math.stackexchange.com/questions/1071662/surface-normal-to-point-on-displaced-sphere
-                // project the gradient onto the tangent plane to the sphere at the current vertex
-                let h = &vertex_gradient
-                    - &(&unit_sphere_coords * Vector3::dot(&vertex_gradient, &unit_sphere_coords));
-
-                // the normal of the terrain is the default terrain minus the projection of the gradient
-                let mut vertex_normal = &unit_sphere_coords - &h;
-                vertex_normal.normalize_in_place();
-
-                // Move back the vertex data to the origin of the chunk to avoid floating point precision issues
-                vertex_position -= &chunk_sphere_position;
-
-                // fill position and normal buffers with the computed data
-                let vertex_index = x * nb_vertices_per_row + y;
-                positions[3 * vertex_index] = vertex_position.x;
-                positions[3 * vertex_index + 1] = vertex_position.y;
-                positions[3 * vertex_index + 2] = vertex_position.z;
-
-                normals[3 * vertex_index] = vertex_normal.x;
-                normals[3 * vertex_index + 1] = vertex_normal.y;
-                normals[3 * vertex_index + 2] = vertex_normal.z;
-
-                // Triangle winding only starts after the first row and column
-                if x == 0 || y == 0 {
-                    continue;
+    // the rows of vertices do not depend on each other so they can be built in parallel
+    let rows = with_terrain_function(
+        seed,
+        data.terrain_settings,
+        graph,
+        |terrain_function, noise_seed| {
+            let surface = Surface {
+                terrain_function,
+                base_shape: data.terrain_settings.base_shape,
+                erosion: erosion.as_deref(),
+                seed: noise_seed,
+                planet_radius,
+            };
+
+            map_rows(nb_vertices_per_row, |x| {
+                let row_directions: Vec<Vector3d> = (0..nb_vertices_per_row)
+                    .map(|y| {
+                        // create flat plane with the right orientation
+                        let plane_position =
+                            chunk_plane_position(direction, x as f32, y as f32, nb_subdivisions);
+
+                        // resize the plane to the size of the chunk and move it to the surface of the cube sphere
+                        let cube_position = &(&Vector3d::from(&plane_position)
+                            * rescale_factor as f64)
+                            + &chunk_cube_position;
+
+                        // normalize and then scale to the radius of the displaced base shape to morph the cube into the planet
+                        cube_position.normalize_to_new()
+                    })
+                    .collect();
+
+                // the terrain of the whole row is evaluated at once to batch the noise evaluations
+                let mut row_gradients = vec![Vector3::zero(); nb_vertices_per_row];
+                let row_elevations =
+                    surface.elevations(&row_directions, vertex_spacing, &mut row_gradients);
+
+                let mut row = VertexRow::with_capacity(nb_vertices_per_row);
+                for y in 0..nb_vertices_per_row {
+                    let unit_sphere_direction = &row_directions[y];
+                    let unit_sphere_coords = unit_sphere_direction.to_f32();
+                    let mut vertex_gradient = row_gradients[y].clone();
+                    let (elevation, base_radius) = row_elevations[y];
+                    let surface_radius = base_radius + elevation as f64;
+
+                    // Move back the vertex data to the origin of the chunk to avoid floating point precision issues
+                    let vertex_position = (&(unit_sphere_direction * surface_radius)
+                        - &chunk_sphere_position)
+                        .to_f32();
+
+                    // the surface is a radial function of the direction
+                    vertex_gradient /= base_radius as f32;
+
+                    if let Some(biome_classifier) = &biome_classifier {
+                        let sample =
+                            biome_classifier.classify(&unit_sphere_coords, elevation, noise_seed);
+                        let [(dominant, dominant_weight), (secondary, secondary_weight)] =
+                            sample.biomes;
+                        row.biomes.extend([
+                            dominant as f32,
+                            dominant_weight,
+                            secondary as f32,
+                            secondary_weight,
+                        ]);
+                    }
+
+                    // Resource: https://math.stackexchange.com/questions/1071662/surface-normal-to-point-on-displaced-sphere
+                    // project the gradient onto the tangent plane to the sphere at the current vertex
+                    let h = &vertex_gradient
+                        - &(&unit_sphere_coords
+                            * Vector3::dot(&vertex_gradient, &unit_sphere_coords));
+
+                    // the normal of the terrain is the default terrain minus the projection of the gradient
+                    let mut vertex_normal = &unit_sphere_coords - &h;
+                    vertex_normal.normalize_in_place();
+
+                    if has_splat_weights {
+                        // measured from the base shape, as for the biomes
+                        let height = elevation - data.terrain_settings.sea_level;
+                        row.splat_weights.extend(splat_weights(
+                            &data.terrain_settings.splat,
+                            &unit_sphere_coords,
+                            &vertex_normal,
+                            height,
+                        ));
+                    }
+
+                    row.positions
+                        .extend([vertex_position.x, vertex_position.y, vertex_position.z]);
+                    row.normals
+                        .extend([vertex_normal.x, vertex_normal.y, vertex_normal.z]);
                 }
+                row
+            })
+        },
+    );
 
-                let indices_index = 6 * ((x - 1) * nb_subdivisions + (y - 1));
-                indices[indices_index] = (vertex_index - 1) as u16;
-                indices[indices_index + 1] = vertex_index as u16;
-                indices[indices_index + 2] = (vertex_index - nb_vertices_per_row - 1) as u16;
-
-                indices[indices_index + 3] = vertex_index as u16;
-                indices[indices_index + 4] = (vertex_index - nb_vertices_per_row) as u16;
-                indices[indices_index + 5] = (vertex_index - nb_vertices_per_row - 1) as u16;
-
-                let index = vertex_index;
-
-                scatter_in_triangle(
-                    scatter_per_square_meter,
-                    &mut excess_instance_number,
-                    &mut instance_index,
-                    scattered_points_buffer,
-                    positions,
-                    index - 1,
-                    index,
-                    index - nb_vertices_per_row - 1,
-                );
-
-                scatter_in_triangle(
-                    scatter_per_square_meter,
-                    &mut excess_instance_number,
-                    &mut instance_index,
-                    scattered_points_buffer,
-                    positions,
-                    index,
-                    index - nb_vertices_per_row,
-                    index - nb_vertices_per_row - 1,
-                );
-                if instance_index > scattered_points_buffer.len() / 6 {
-                    panic!(
-                        "Too many instances: {} > {}",
-                        instance_index,
-                        scattered_points_buffer.len() / 6
-                    );
-                }
-            }
+    // fill position and normal buffers with the computed data
+    for (x, row) in rows.iter().enumerate() {
+        let first_vertex = x * nb_vertices_per_row;
+        let last_vertex = first_vertex + nb_vertices_per_row;
+        positions[3 * first_vertex..3 * last_vertex].copy_from_slice(&row.positions);
+        normals[3 * first_vertex..3 * last_vertex].copy_from_slice(&row.normals);
+        if biome_classifier.is_some() {
+            biomes[BIOME_STRIDE * first_vertex..BIOME_STRIDE * last_vertex]
+                .copy_from_slice(&row.biomes);
+        }
+        if has_splat_weights {
+            splat_weights_buffer[SPLAT_STRIDE * first_vertex..SPLAT_STRIDE * last_vertex]
+                .copy_from_slice(&row.splat_weights);
         }
+    }
+
+    // the triangles are scattered in order with a generator seeded by the chunk,
+    // so that the instances depend neither on the threads nor on the chunks built before
+    let mut instance_index: usize = 0;
+    let mut excess_instance_number: f32 = 0.0;
+    let mut random_state = [
+        data.chunk_cube_position_x,
+        data.chunk_cube_position_y,
+        data.chunk_cube_position_z,
+    ]
+    .iter()
+    .fold(seed.bits() as u64, |state, coordinate| {
+        mix64(state ^ coordinate.to_bits() as u64)
     });
 
+    // Triangle winding only starts after the first row and column
+    for x in 1..nb_vertices_per_row {
+        for y in 1..nb_vertices_per_row {
+            let vertex_index = x * nb_vertices_per_row + y;
+
+            let indices_index = 6 * ((x - 1) * nb_subdivisions + (y - 1));
+            indices[indices_index] = (vertex_index - 1) as u16;
+            indices[indices_index + 1] = vertex_index as u16;
+            indices[indices_index + 2] = (vertex_index - nb_vertices_per_row - 1) as u16;
+
+            indices[indices_index + 3] = vertex_index as u16;
+            indices[indices_index + 4] = (vertex_index - nb_vertices_per_row) as u16;
+            indices[indices_index + 5] = (vertex_index - nb_vertices_per_row - 1) as u16;
+
+            let index = vertex_index;
+
+            let triangle = [index - 1, index, index - nb_vertices_per_row - 1];
+            scatter_in_triangle(
+                scatter_per_square_meter * scatter_coverage(splat_weights_buffer, &triangle),
+                &mut excess_instance_number,
+                &mut instance_index,
+                &mut random_state,
+                scattered_points_buffer,
+                positions,
+                triangle[0],
+                triangle[1],
+                triangle[2],
+            );
+
+            let triangle = [
+                index,
+                index - nb_vertices_per_row,
+                index - nb_vertices_per_row - 1,
+            ];
+            scatter_in_triangle(
+                scatter_per_square_meter * scatter_coverage(splat_weights_buffer, &triangle),
+                &mut excess_instance_number,
+                &mut instance_index,
+                &mut random_state,
+                scattered_points_buffer,
+                positions,
+                triangle[0],
+                triangle[1],
+                triangle[2],
+            );
+        }
+    }
+
+    // the whole grid is scattered first so that the error tells how many instances the chunk needs
+    if instance_index > scattered_points_buffer.len() / SCATTER_STRIDE {
+        return Err(BuildError::TooManyInstances {
+            nb_instances: instance_index,
+            capacity: scattered_points_buffer.len() / SCATTER_STRIDE,
+        });
+    }
+
     if should_generate_skirt {
         append_chunk_skirt(
             positions,
             normals,
             indices,
             nb_vertices_per_row,
-            &chunk_sphere_position,
+            &chunk_sphere_position.to_f32(),
             skirt_depth,
         );
+        if biome_classifier.is_some() {
+            copy_border_attributes(biomes, BIOME_STRIDE, nb_vertices_per_row);
+        }
+        if has_splat_weights {
+            copy_border_attributes(splat_weights_buffer, SPLAT_STRIDE, nb_vertices_per_row);
+        }
     }
 
-    ReturnData {
-        nb_instances_created: instance_index,
-    }
+    Ok(instance_index)
 }
@@ -0,0 +1,74 @@
+/// Maps the rows of a chunk in order, across the threads of the rayon pool when the `parallel` feature is enabled.
+/// The rows are computed independently and collected in order, so the output does not depend on the number of threads.
+/// * `nb_rows` - The number of rows to map
+/// * `f` - Computes a row from its index
+pub(crate) fn map_rows<T: Send>(nb_rows: usize, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        (0..nb_rows).into_par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..nb_rows).map(f).collect()
+    }
+}
+
+/// The rayon pool of the multithreaded WebAssembly build, whose threads run on web workers sharing the memory of the module.
+/// The workers are started from JavaScript before the pool is built, as a worker only starts once its creator yields.
+#[cfg(all(target_arch = "wasm32", feature = "parallel"))]
+mod web_threads {
+    use rayon::ThreadBuilder;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::{Mutex, OnceLock};
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen(module = "/src/thread_pool.js")]
+    extern "C" {
+        #[wasm_bindgen(js_name = startWorkers)]
+        fn start_workers(module: JsValue, memory: JsValue, nb_threads: usize) -> JsValue;
+    }
+
+    /// The threads of the pool waiting for a worker to run them
+    static PENDING_THREADS: OnceLock<Mutex<Receiver<ThreadBuilder>>> = OnceLock::new();
+
+    /// Starts `nb_threads` web workers and builds the global rayon pool on them.
+    /// Returns a promise resolved once the pool is ready. Only the first call has an effect.
+    /// * `nb_threads` - The number of threads of the pool
+    #[wasm_bindgen(js_name = initThreadPool, unchecked_return_type = "Promise<void>")]
+    pub fn init_thread_pool(nb_threads: usize) -> JsValue {
+        start_workers(wasm_bindgen::module(), wasm_bindgen::memory(), nb_threads)
+    }
+
+    /// Builds the global rayon pool once its workers are waiting for their threads (called by `startWorkers`)
+    #[doc(hidden)]
+    #[wasm_bindgen]
+    pub fn build_thread_pool(nb_threads: usize) -> Result<(), JsError> {
+        let (sender, receiver) = channel();
+        if PENDING_THREADS.set(Mutex::new(receiver)).is_err() {
+            return Ok(());
+        }
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(nb_threads)
+            .spawn_handler(move |thread| {
+                sender
+                    .send(thread)
+                    .map_err(|_| std::io::Error::other("the thread pool workers are gone"))
+            })
+            .build_global()?;
+        Ok(())
+    }
+
+    /// Runs the next thread of the pool on the calling worker, never returns (called by the workers of `startWorkers`)
+    #[doc(hidden)]
+    #[wasm_bindgen]
+    pub fn run_pool_thread() {
+        let thread = PENDING_THREADS
+            .get()
+            .and_then(|threads| threads.lock().ok()?.recv().ok());
+        if let Some(thread) = thread {
+            thread.run();
+        }
+    }
+}
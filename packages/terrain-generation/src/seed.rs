@@ -0,0 +1,201 @@
+use crate::landscape::tectonic_plates::TectonicPlates;
+use crate::utils::simplex::{permutation_table, PermutationTable};
+use std::sync::{Arc, OnceLock};
+use wasm_bindgen::prelude::*;
+
+/// How the seed alters the noise
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum SeedKind {
+    /// An offset along the 4th dimension of the noise (the legacy behaviour)
+    Offset(f32),
+    /// The seed of a shuffled permutation table
+    Integer(u64),
+}
+
+/// The seed of a planet.
+/// Integer seeds shuffle the permutation table of the noise so that nearby seeds give uncorrelated planets.
+/// Offset seeds move along the 4th dimension of the noise and are kept for the planets generated before integer seeds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[wasm_bindgen]
+pub struct Seed {
+    kind: SeedKind,
+}
+
+/// The sub-systems of the planet deriving their own sub-seed, so that their features are not correlated.
+/// Their values salt the integer seeds: changing them changes the planets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Salt {
+    Continents = 1,
+    TectonicPlates,
+    Mountains,
+    MountainMask,
+    Erosion,
+    Terraces,
+    Bumps,
+    Craters,
+    Moisture,
+    Lumps,
+    Caves,
+}
+
+impl Salt {
+    /// The number of salts
+    const COUNT: usize = 11;
+
+    /// The position of the salt among the salts
+    fn index(self) -> usize {
+        self as usize - 1
+    }
+}
+
+/// SplitMix64 finalizer (Steele, Lea & Flood, Fast Splittable Pseudorandom Number Generators, 2014)
+pub(crate) fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl Seed {
+    /// A seed offsetting the noise along its 4th dimension (the legacy behaviour)
+    pub const fn from_offset(offset: f32) -> Seed {
+        Seed {
+            kind: SeedKind::Offset(offset),
+        }
+    }
+
+    /// A seed shuffling the permutation table of the noise
+    pub const fn from_integer(seed: u64) -> Seed {
+        Seed {
+            kind: SeedKind::Integer(seed),
+        }
+    }
+
+    pub(crate) fn kind(&self) -> SeedKind {
+        self.kind
+    }
+
+    /// The offset along the 4th dimension of the noise (0 for integer seeds)
+    pub fn offset(&self) -> f32 {
+        match self.kind {
+            SeedKind::Offset(offset) => offset,
+            SeedKind::Integer(_) => 0.0,
+        }
+    }
+
+    /// 32 bits identifying the seed, used to salt the integer hashes
+    pub fn bits(&self) -> u32 {
+        match self.kind {
+            // kept as is so that offset seeds generate the same planets as before
+            SeedKind::Offset(offset) => offset.to_bits(),
+            SeedKind::Integer(seed) => (mix64(seed) >> 32) as u32,
+        }
+    }
+
+    /// The seed of a sub-system of the planet (a layer, the plates...) so that their features are not correlated.
+    /// Offset seeds are returned unchanged to keep the planets generated before sub-seeds identical,
+    /// so their continents, mountains and masks stay correlated: use `to_integer` to decorrelate them.
+    /// * `salt` - The sub-system
+    pub fn derive(&self, salt: Salt) -> Seed {
+        match self.kind {
+            SeedKind::Offset(_) => *self,
+            SeedKind::Integer(seed) => Seed {
+                kind: SeedKind::Integer(mix64(seed ^ mix64(salt as u64))),
+            },
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Seed {
+    pub fn new_offset(offset: f32) -> Seed {
+        Seed::from_offset(offset)
+    }
+
+    pub fn new_integer(seed: u64) -> Seed {
+        Seed::from_integer(seed)
+    }
+
+    /// The integer seed replacing an offset seed, to migrate a planet to uncorrelated sub-seeds.
+    /// The planet looks different afterwards, integer seeds are returned unchanged.
+    pub fn to_integer(&self) -> Seed {
+        match self.kind {
+            SeedKind::Offset(offset) => Seed::from_integer(mix64(offset.to_bits() as u64)),
+            SeedKind::Integer(_) => *self,
+        }
+    }
+}
+
+/// A seed resolved to what the noise reads: its permutation table and its offset along the 4th dimension.
+/// Shuffling a table is much slower than sampling the noise, so a seed is resolved once per planet
+/// and its sub-seeds once per salt, when they are first derived. The tectonic plates are generated once as well.
+pub struct NoiseSeed {
+    seed: Seed,
+    permutation: Box<PermutationTable>,
+    sub_seeds: [OnceLock<Box<NoiseSeed>>; Salt::COUNT],
+    tectonic_plates: OnceLock<Arc<TectonicPlates>>,
+}
+
+impl NoiseSeed {
+    pub fn new(seed: Seed) -> NoiseSeed {
+        NoiseSeed {
+            seed,
+            permutation: permutation_table(seed),
+            sub_seeds: std::array::from_fn(|_| OnceLock::new()),
+            tectonic_plates: OnceLock::new(),
+        }
+    }
+
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    pub fn offset(&self) -> f32 {
+        self.seed.offset()
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.seed.bits()
+    }
+
+    pub(crate) fn permutation(&self) -> &PermutationTable {
+        &self.permutation
+    }
+
+    /// The resolved seed of a sub-system of the planet, see `Seed::derive`
+    /// * `salt` - The sub-system
+    pub fn derive(&self, salt: Salt) -> &NoiseSeed {
+        self.sub_seeds[salt.index()]
+            .get_or_init(|| Box::new(NoiseSeed::new(self.seed.derive(salt))))
+    }
+
+    /// The tectonic plates of the planet, generated when first requested.
+    /// A seed shared by planets with other plate settings regenerates the plates that differ from the first ones.
+    /// * `nb_plates` - The number of plates
+    /// * `boundary_width` - The width of the plate boundaries on the unit sphere
+    pub(crate) fn tectonic_plates(
+        &self,
+        nb_plates: u32,
+        boundary_width: f32,
+    ) -> Arc<TectonicPlates> {
+        let generate = || {
+            Arc::new(TectonicPlates::generate(
+                self.seed.derive(Salt::TectonicPlates),
+                nb_plates,
+                boundary_width,
+            ))
+        };
+        let plates = self.tectonic_plates.get_or_init(generate);
+        if plates.plates.len() == nb_plates as usize && plates.boundary_width == boundary_width {
+            plates.clone()
+        } else {
+            generate()
+        }
+    }
+}
+
+impl From<Seed> for NoiseSeed {
+    fn from(seed: Seed) -> NoiseSeed {
+        NoiseSeed::new(seed)
+    }
+}
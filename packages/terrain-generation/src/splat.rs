@@ -0,0 +1,100 @@
+use crate::utils::math::smoothstep;
+use crate::utils::vector3::Vector3;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// The number of floats written per vertex in the splat buffer: rock, sand, grass, snow
+pub const SPLAT_STRIDE: usize = 4;
+
+/// The index of the grass weight of a vertex in the splat buffer, where the scattered instances grow
+pub const GRASS_WEIGHT: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[wasm_bindgen]
+pub struct SplatSettings {
+    /// The height above sea level where beaches turn into grass
+    pub beach_height: f32,
+    /// The flatness (cosine of the slope) below which the ground is only rock
+    pub min_flatness: f32,
+    /// The flatness above which the ground is not rock anymore
+    pub max_flatness: f32,
+    /// The height above sea level where snow starts on the equator, it goes down to sea level at the poles
+    pub equator_snow_line: f32,
+    /// The height over which the ground turns from grass to snow
+    pub snow_transition: f32,
+}
+
+impl SplatSettings {
+    /// Matches the material of the telluric planets
+    pub const DEFAULT: SplatSettings = SplatSettings {
+        beach_height: 50.0,
+        min_flatness: 0.85,
+        max_flatness: 1.0,
+        equator_snow_line: 4e3,
+        snow_transition: 500.0,
+    };
+}
+
+#[wasm_bindgen]
+impl SplatSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SplatSettings {
+        SplatSettings::default()
+    }
+}
+
+impl Default for SplatSettings {
+    fn default() -> Self {
+        SplatSettings::DEFAULT
+    }
+}
+
+/// Computes the material weights of a vertex, which sum to 1
+/// * `settings` - The thresholds of the materials
+/// * `unit_sample_point` - The position of the vertex on the unit sphere (the poles are along the y axis)
+/// * `normal` - The normal of the terrain at the vertex
+/// * `height` - The elevation above sea level
+pub fn splat_weights(
+    settings: &SplatSettings,
+    unit_sample_point: &Vector3,
+    normal: &Vector3,
+    height: f32,
+) -> [f32; SPLAT_STRIDE] {
+    // steep slopes are rock, the remaining weight is shared between the other materials
+    let flatness = Vector3::dot(normal, unit_sample_point);
+    let flat = smoothstep(
+        settings.min_flatness,
+        settings.max_flatness,
+        flatness,
+        &mut Vector3::zero(),
+    );
+
+    let cos_latitude = f32::sqrt(f32::max(
+        1.0 - unit_sample_point.y * unit_sample_point.y,
+        0.0,
+    ));
+    let snow_line = settings.equator_snow_line * cos_latitude;
+    let snow = smoothstep(
+        snow_line,
+        snow_line + settings.snow_transition,
+        height,
+        &mut Vector3::zero(),
+    );
+
+    // sea beds and beaches are sand
+    let sand = 1.0
+        - smoothstep(
+            0.0,
+            f32::max(settings.beach_height, 1e-3),
+            height,
+            &mut Vector3::zero(),
+        );
+
+    [
+        1.0 - flat,
+        flat * (1.0 - snow) * sand,
+        flat * (1.0 - snow) * (1.0 - sand),
+        flat * snow,
+    ]
+}
@@ -0,0 +1,85 @@
+use crate::base_shape::BaseShape;
+use crate::erosion_bake::ErosionBake;
+use crate::landscape::make_terrain_function::TerrainRowFunction;
+use crate::seed::NoiseSeed;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+
+/// The surface of a planet: its base shape displaced by the terrain and the offline erosion
+pub(crate) struct Surface<'a> {
+    pub terrain_function: &'a TerrainRowFunction,
+    pub base_shape: BaseShape,
+    pub erosion: Option<&'a ErosionBake>,
+    pub seed: &'a NoiseSeed,
+    pub planet_radius: f32,
+}
+
+impl Surface<'_> {
+    /// The elevation of the surface above the base shape in the given direction.
+    /// Returns the elevation and the radius of the base shape, kept apart so that their sum can be computed in double precision
+    /// * `unit_sphere_direction` - The direction on the unit sphere
+    /// * `vertex_spacing` - The distance between neighbouring vertices on the unit sphere (0 for full detail)
+    /// * `gradient` - The gradient of the surface radius w.r.t. the unit sphere coordinates WILL BE ADDED TO
+    pub fn elevation(
+        &self,
+        unit_sphere_direction: &Vector3d,
+        vertex_spacing: f32,
+        gradient: &mut Vector3,
+    ) -> (f32, f64) {
+        self.elevations(
+            std::slice::from_ref(unit_sphere_direction),
+            vertex_spacing,
+            std::slice::from_mut(gradient),
+        )[0]
+    }
+
+    /// The elevations of the surface along a row of directions, see `elevation`
+    /// * `gradients` - The gradients of the surface radius w.r.t. the unit sphere coordinates WILL BE ADDED TO
+    pub fn elevations(
+        &self,
+        unit_sphere_directions: &[Vector3d],
+        vertex_spacing: f32,
+        gradients: &mut [Vector3],
+    ) -> Vec<(f32, f64)> {
+        // the elevation is small enough to be computed in single precision, but not the directions:
+        // the noise sees them in double precision so that it stays smooth between the vertices of deep chunks
+        let unit_sphere_coords: Vec<Vector3> = unit_sphere_directions
+            .iter()
+            .map(Vector3d::to_f32)
+            .collect();
+
+        // apply terrain function to the whole row (use normalized coordinates for scale invariance)
+        let mut displacements = vec![Vector3::zero(); unit_sphere_coords.len()];
+        let mut terrain_gradients = vec![Vector3::zero(); unit_sphere_coords.len()];
+        (self.terrain_function)(
+            unit_sphere_directions,
+            self.seed,
+            vertex_spacing,
+            &mut displacements,
+            &mut terrain_gradients,
+        );
+
+        let mut elevations = Vec::with_capacity(unit_sphere_directions.len());
+        for (index, unit_sphere_direction) in unit_sphere_directions.iter().enumerate() {
+            let mut base_gradient = Vector3::zero();
+            let base_radius = self.planet_radius as f64
+                * self.base_shape.precise_radius(
+                    unit_sphere_direction,
+                    self.seed,
+                    &mut base_gradient,
+                );
+
+            let terrain_gradient = &mut terrain_gradients[index];
+            let mut elevation = Vector3::dot(&displacements[index], &unit_sphere_coords[index]);
+
+            // apply the offline erosion on top of the procedural elevation
+            if let Some(erosion) = self.erosion {
+                elevation += erosion.sample(&unit_sphere_coords[index], terrain_gradient);
+            }
+
+            gradients[index] += terrain_gradient.clone() + base_gradient * self.planet_radius;
+            elevations.push((elevation, base_radius));
+        }
+        elevations
+    }
+}
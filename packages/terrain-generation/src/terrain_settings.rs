@@ -1,6 +1,21 @@
-use wasm_bindgen::prelude::wasm_bindgen;
+use crate::base_shape::BaseShape;
+use crate::biomes::BiomeSettings;
+use crate::splat::SplatSettings;
+use crate::validation::{ValidationError, ValidationErrors};
+use crate::volumetric_chunk::CaveSettings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wasm_bindgen::prelude::*;
 
-#[derive(Copy, Clone)]
+/// The version of the serialized settings, bumped whenever the presets saved before need a migration.
+/// Version 1 is the flat object of the seven original fields, written without a version.
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// The names of the presets returned by `TerrainSettings::preset`
+pub const PRESET_NAMES: [&str; 4] = ["earthlike", "desert", "ocean_world", "barren_moon"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 #[wasm_bindgen]
 pub struct TerrainSettings {
     pub continents_frequency: f32,
@@ -10,6 +25,275 @@ pub struct TerrainSettings {
     pub continent_base_height: f32,
     pub max_mountain_height: f32,
     pub max_bump_height: f32,
+    pub terrace_steps: f32,
+    pub terrace_sharpness: f32,
+    pub terrace_coverage: f32,
+    pub erosion_strength: f32,
+    pub erosion_octaves: u32,
+    pub erosion_frequency: f32,
+    pub craters_frequency: f32,
+    pub craters_density: f32,
+    pub max_crater_depth: f32,
+    pub nb_tectonic_plates: u32,
+    pub tectonic_boundary_width: f32,
+    /// Height of the ocean ridges raised along divergent plate boundaries.
+    /// Has no effect when `nb_tectonic_plates` is 0.
+    pub max_ridge_height: f32,
+    pub sea_level: f32,
+    pub continental_shelf_depth: f32,
+    pub abyssal_depth: f32,
+    /// Depth of the ocean trenches dug along convergent plate boundaries.
+    /// Has no effect when `nb_tectonic_plates` is 0.
+    pub max_trench_depth: f32,
+    pub base_shape: BaseShape,
+    pub biomes: BiomeSettings,
+    pub splat: SplatSettings,
+    pub caves: CaveSettings,
+}
+
+impl TerrainSettings {
+    pub const DEFAULT: TerrainSettings = TerrainSettings {
+        continents_frequency: 1.0,
+        bumps_frequency: 1.0,
+        mountains_frequency: 1.0,
+        continents_fragmentation: 1.0,
+        continent_base_height: 1.0,
+        max_mountain_height: 1.0,
+        max_bump_height: 1.0,
+        terrace_steps: 8.0,
+        terrace_sharpness: 4.0,
+        terrace_coverage: 0.0,
+        erosion_strength: 0.0,
+        erosion_octaves: 5,
+        erosion_frequency: 100.0,
+        craters_frequency: 4.0,
+        craters_density: 0.0,
+        max_crater_depth: 1.0,
+        nb_tectonic_plates: 0,
+        tectonic_boundary_width: 0.05,
+        max_ridge_height: 1.0,
+        sea_level: 0.0,
+        continental_shelf_depth: 0.0,
+        abyssal_depth: 0.0,
+        max_trench_depth: 1.0,
+        base_shape: BaseShape::SPHERE,
+        biomes: BiomeSettings::WHITTAKER,
+        splat: SplatSettings::DEFAULT,
+        caves: CaveSettings::NONE,
+    };
+
+    /// Continents drifting on tectonic plates between oceans with shelves and abyssal plains
+    pub const EARTHLIKE: TerrainSettings = TerrainSettings {
+        continents_frequency: 1.0,
+        bumps_frequency: 30.0,
+        mountains_frequency: 60.0,
+        continents_fragmentation: 0.65,
+        continent_base_height: 10e3,
+        max_mountain_height: 10e3,
+        max_bump_height: 1.5e3,
+        erosion_strength: 0.3,
+        nb_tectonic_plates: 12,
+        max_ridge_height: 2.5e3,
+        sea_level: 10e3,
+        continental_shelf_depth: 200.0,
+        abyssal_depth: 4e3,
+        max_trench_depth: 5e3,
+        ..TerrainSettings::DEFAULT
+    };
+
+    /// A single dry landmass with eroded mesas and no sea
+    pub const DESERT: TerrainSettings = TerrainSettings {
+        continents_frequency: 1.0,
+        bumps_frequency: 60.0,
+        mountains_frequency: 30.0,
+        continents_fragmentation: 0.1,
+        continent_base_height: 2e3,
+        max_mountain_height: 4e3,
+        max_bump_height: 800.0,
+        terrace_coverage: 0.6,
+        erosion_strength: 0.5,
+        splat: SplatSettings {
+            beach_height: 0.0,
+            equator_snow_line: 8e3,
+            ..SplatSettings::DEFAULT
+        },
+        ..TerrainSettings::DEFAULT
+    };
+
+    /// Scattered volcanic islands above deep oceans
+    pub const OCEAN_WORLD: TerrainSettings = TerrainSettings {
+        continents_frequency: 2.0,
+        bumps_frequency: 30.0,
+        mountains_frequency: 40.0,
+        continents_fragmentation: 0.95,
+        continent_base_height: 4e3,
+        max_mountain_height: 3e3,
+        max_bump_height: 1e3,
+        nb_tectonic_plates: 8,
+        max_ridge_height: 2.5e3,
+        sea_level: 5e3,
+        continental_shelf_depth: 300.0,
+        abyssal_depth: 5e3,
+        max_trench_depth: 6e3,
+        ..TerrainSettings::DEFAULT
+    };
+
+    /// An airless body covered with craters
+    pub const BARREN_MOON: TerrainSettings = TerrainSettings {
+        continents_frequency: 1.0,
+        bumps_frequency: 10.0,
+        mountains_frequency: 1.0,
+        continents_fragmentation: 0.5,
+        continent_base_height: 2e3,
+        max_mountain_height: 0.0,
+        max_bump_height: 1e3,
+        craters_frequency: 4.0,
+        craters_density: 0.6,
+        max_crater_depth: 4e3,
+        ..TerrainSettings::DEFAULT
+    };
+
+    /// Checks that the settings describe a terrain, reporting every violated constraint
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = Vec::new();
+
+        for (field, value) in [
+            ("continents_frequency", self.continents_frequency),
+            ("bumps_frequency", self.bumps_frequency),
+            ("mountains_frequency", self.mountains_frequency),
+            ("erosion_frequency", self.erosion_frequency),
+            ("craters_frequency", self.craters_frequency),
+            ("biomes.moisture_frequency", self.biomes.moisture_frequency),
+            ("base_shape.lump_frequency", self.base_shape.lump_frequency),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                errors.push(ValidationError::InvalidFrequency { field, value });
+            }
+        }
+
+        for (field, value) in [
+            ("continent_base_height", self.continent_base_height),
+            ("max_mountain_height", self.max_mountain_height),
+            ("max_bump_height", self.max_bump_height),
+            ("terrace_sharpness", self.terrace_sharpness),
+            ("terrace_coverage", self.terrace_coverage),
+            ("erosion_strength", self.erosion_strength),
+            ("craters_density", self.craters_density),
+            ("max_crater_depth", self.max_crater_depth),
+            ("max_ridge_height", self.max_ridge_height),
+            ("sea_level", self.sea_level),
+            ("continental_shelf_depth", self.continental_shelf_depth),
+            ("abyssal_depth", self.abyssal_depth),
+            ("max_trench_depth", self.max_trench_depth),
+            (
+                "biomes.equator_temperature",
+                self.biomes.equator_temperature,
+            ),
+            ("biomes.pole_temperature", self.biomes.pole_temperature),
+            (
+                "biomes.temperature_lapse_rate",
+                self.biomes.temperature_lapse_rate,
+            ),
+            (
+                "biomes.ocean_moisture_weight",
+                self.biomes.ocean_moisture_weight,
+            ),
+            ("biomes.min_temperature", self.biomes.min_temperature),
+            ("biomes.max_temperature", self.biomes.max_temperature),
+            ("splat.beach_height", self.splat.beach_height),
+            ("splat.min_flatness", self.splat.min_flatness),
+            ("splat.max_flatness", self.splat.max_flatness),
+            ("splat.equator_snow_line", self.splat.equator_snow_line),
+            ("caves.threshold", self.caves.threshold),
+            ("caves.depth", self.caves.depth),
+        ] {
+            if !value.is_finite() {
+                errors.push(ValidationError::InvalidValue { field, value });
+            }
+        }
+
+        // the plates are blended across their boundaries by a softmax dividing by the width
+        for (field, value) in [
+            ("tectonic_boundary_width", self.tectonic_boundary_width),
+            ("splat.snow_transition", self.splat.snow_transition),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                errors.push(ValidationError::InvalidWidth { field, value });
+            }
+        }
+
+        if !(self.terrace_steps.is_finite() && self.terrace_steps > 0.0) {
+            errors.push(ValidationError::InvalidStepCount(self.terrace_steps));
+        }
+
+        // without fragmentation, the whole planet is a single continent
+        if !(self.continents_fragmentation > 0.0 && self.continents_fragmentation <= 1.0) {
+            errors.push(ValidationError::InvalidFragmentation(
+                self.continents_fragmentation,
+            ));
+        }
+
+        for (field, value) in [
+            ("scale_x", self.base_shape.scale_x),
+            ("scale_y", self.base_shape.scale_y),
+            ("scale_z", self.base_shape.scale_z),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                errors.push(ValidationError::InvalidBaseShapeScale { field, value });
+            }
+        }
+
+        let lump_amplitude = self.base_shape.lump_amplitude;
+        if !(0.0..1.0).contains(&lump_amplitude) {
+            errors.push(ValidationError::InvalidLumpAmplitude(lump_amplitude));
+        }
+
+        // the cave noise is sampled at the position divided by the size
+        let cave_size = self.caves.size;
+        if !(cave_size.is_finite() && cave_size > 0.0) {
+            errors.push(ValidationError::InvalidCaveSize(cave_size));
+        }
+
+        ValidationErrors::check(errors)
+    }
+
+    /// Reads settings serialized as JSON, migrating the presets saved by older versions
+    pub fn from_json(json: &str) -> Result<TerrainSettings, SettingsError> {
+        let VersionProbe { version } = serde_json::from_str(json).map_err(SettingsError::Json)?;
+        let settings = if version < 2 {
+            serde_json::from_str(json)
+        } else {
+            serde_json::from_str::<SettingsDocument<TerrainSettings>>(json)
+                .map(|document| document.settings)
+        }
+        .map_err(SettingsError::Json)?;
+        migrate(settings, version)
+    }
+
+    /// Serializes the settings as JSON along with the current version
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&SettingsDocument::new(self))
+    }
+
+    /// Reads settings serialized as RON, migrating the presets saved by older versions
+    #[cfg(feature = "ron")]
+    pub fn from_ron(text: &str) -> Result<TerrainSettings, SettingsError> {
+        let VersionProbe { version } = ron::from_str(text).map_err(SettingsError::Ron)?;
+        let settings = if version < 2 {
+            ron::from_str(text)
+        } else {
+            ron::from_str::<SettingsDocument<TerrainSettings>>(text)
+                .map(|document| document.settings)
+        }
+        .map_err(SettingsError::Ron)?;
+        migrate(settings, version)
+    }
+
+    /// Serializes the settings as human-readable RON along with the current version
+    #[cfg(feature = "ron")]
+    pub fn to_ron(&self) -> ron::Result<String> {
+        ron::ser::to_string_pretty(&SettingsDocument::new(self), Default::default())
+    }
 }
 
 #[wasm_bindgen]
@@ -18,18 +302,99 @@ impl TerrainSettings {
     pub fn new() -> TerrainSettings {
         TerrainSettings::default()
     }
+
+    /// One of the named presets of `PRESET_NAMES`
+    pub fn preset(name: &str) -> Option<TerrainSettings> {
+        match name {
+            "earthlike" => Some(TerrainSettings::EARTHLIKE),
+            "desert" => Some(TerrainSettings::DESERT),
+            "ocean_world" => Some(TerrainSettings::OCEAN_WORLD),
+            "barren_moon" => Some(TerrainSettings::BARREN_MOON),
+            _ => None,
+        }
+    }
 }
 
 impl Default for TerrainSettings {
     fn default() -> Self {
-        Self {
-            continents_frequency: 1.0,
-            bumps_frequency: 1.0,
-            mountains_frequency: 1.0,
-            continents_fragmentation: 1.0,
-            continent_base_height: 1.0,
-            max_mountain_height: 1.0,
-            max_bump_height: 1.0,
+        TerrainSettings::DEFAULT
+    }
+}
+
+/// Reads terrain settings serialized as JSON by `TerrainSettings::to_json` or saved by older versions
+#[wasm_bindgen]
+pub fn terrain_settings_from_json(json: &str) -> Result<TerrainSettings, JsError> {
+    Ok(TerrainSettings::from_json(json)?)
+}
+
+/// Serializes terrain settings as JSON along with the current version
+#[wasm_bindgen]
+pub fn terrain_settings_to_json(settings: &TerrainSettings) -> Result<String, JsError> {
+    Ok(settings.to_json()?)
+}
+
+/// Why serialized settings could not be read
+#[derive(Debug)]
+pub enum SettingsError {
+    Json(serde_json::Error),
+    #[cfg(feature = "ron")]
+    Ron(ron::error::SpannedError),
+    /// The settings were saved by a newer version of the crate
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Json(error) => write!(f, "invalid terrain settings: {error}"),
+            #[cfg(feature = "ron")]
+            SettingsError::Ron(error) => write!(f, "invalid terrain settings: {error}"),
+            SettingsError::UnsupportedVersion(version) => write!(
+                f,
+                "terrain settings version {version} is not supported (expected at most {SETTINGS_VERSION})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// The envelope of the serialized settings since version 2
+#[derive(Serialize, Deserialize)]
+struct SettingsDocument<S> {
+    version: u32,
+    settings: S,
+}
+
+impl SettingsDocument<&TerrainSettings> {
+    fn new(settings: &TerrainSettings) -> SettingsDocument<&TerrainSettings> {
+        SettingsDocument {
+            version: SETTINGS_VERSION,
+            settings,
         }
     }
 }
+
+/// Reads the version of a document before its settings, as the layout depends on it
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default = "legacy_version")]
+    version: u32,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// Brings settings saved by an older version up to date, so that the planets look the same as when they were saved.
+/// The fields added since the settings were saved take their default values, which leave the new features disabled.
+fn migrate(mut settings: TerrainSettings, version: u32) -> Result<TerrainSettings, SettingsError> {
+    if version == 0 || version > SETTINGS_VERSION {
+        return Err(SettingsError::UnsupportedVersion(version));
+    }
+    if version < 2 {
+        // the bumps were not generated before version 2, whatever their height
+        settings.max_bump_height = 0.0;
+    }
+    Ok(settings)
+}
@@ -0,0 +1,40 @@
+// The web workers of the rayon pool of the multithreaded build (see `initThreadPool` in src/parallel.rs).
+// This file runs both in the thread calling `initThreadPool`, where it starts the workers,
+// and in each of the workers, where it instantiates the module on the shared memory and runs a thread of the pool.
+
+const WORKER_INIT = "terrain_generation_thread_init";
+const WORKER_READY = "terrain_generation_thread_ready";
+
+function waitForMessage(target, type) {
+    return new Promise((resolve) => {
+        const onMessage = (event) => {
+            if (event.data?.type !== type) return;
+            target.removeEventListener("message", onMessage);
+            resolve(event.data);
+        };
+        target.addEventListener("message", onMessage);
+    });
+}
+
+// in a worker of the pool: wait for the module and its memory, then run a thread of the pool forever
+if (typeof WorkerGlobalScope !== "undefined" && self instanceof WorkerGlobalScope) {
+    waitForMessage(self, WORKER_INIT).then(async ({ module, memory }) => {
+        const pkg = await import("../../../terrain_generation.js");
+        await pkg.default({ module_or_path: module, memory });
+        self.postMessage({ type: WORKER_READY });
+        pkg.run_pool_thread();
+    });
+}
+
+export async function startWorkers(module, memory, nbThreads) {
+    const pkg = await import("../../../terrain_generation.js");
+    await Promise.all(
+        Array.from({ length: nbThreads }, async () => {
+            const worker = new Worker(new URL("./thread_pool.js", import.meta.url), { type: "module" });
+            const ready = waitForMessage(worker, WORKER_READY);
+            worker.postMessage({ type: WORKER_INIT, module, memory });
+            await ready;
+        }),
+    );
+    pkg.build_thread_pool(nbThreads);
+}
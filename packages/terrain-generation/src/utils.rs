@@ -1,10 +1,14 @@
 pub mod direction;
+pub mod dual;
 pub mod erosion;
 pub mod math;
 pub mod random;
 pub mod simplex;
+pub mod simplex_batch;
 pub mod triangle;
 pub mod vector3;
+pub mod vector3d;
+pub mod worley;
 
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -0,0 +1,366 @@
+use crate::utils::vector3::Vector3;
+use std::ops;
+
+/// A value carrying its gradient, propagated through arithmetic by forward-mode automatic differentiation.
+/// Fields written with duals get exact gradients without threading them by hand through `utils::math`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub value: f32,
+    /// The partial derivatives along x, y and z
+    pub derivatives: [f32; 3],
+}
+
+impl Dual {
+    pub const ZERO: Dual = Dual::constant(0.0);
+    pub const ONE: Dual = Dual::constant(1.0);
+
+    /// A value that does not vary in space
+    pub const fn constant(value: f32) -> Dual {
+        Dual {
+            value,
+            derivatives: [0.0; 3],
+        }
+    }
+
+    pub fn new(value: f32, gradient: &Vector3) -> Dual {
+        Dual {
+            value,
+            derivatives: [gradient.x, gradient.y, gradient.z],
+        }
+    }
+
+    /// The coordinates of a point as the variables to differentiate with respect to
+    pub fn variables(coords: &Vector3) -> [Dual; 3] {
+        [
+            Dual {
+                value: coords.x,
+                derivatives: [1.0, 0.0, 0.0],
+            },
+            Dual {
+                value: coords.y,
+                derivatives: [0.0, 1.0, 0.0],
+            },
+            Dual {
+                value: coords.z,
+                derivatives: [0.0, 0.0, 1.0],
+            },
+        ]
+    }
+
+    pub fn gradient(&self) -> Vector3 {
+        Vector3::new(
+            self.derivatives[0],
+            self.derivatives[1],
+            self.derivatives[2],
+        )
+    }
+
+    /// Applies a function of the value given its result and its derivative at the value (chain rule)
+    fn chain(self, value: f32, derivative: f32) -> Dual {
+        Dual {
+            value,
+            derivatives: self.derivatives.map(|d| d * derivative),
+        }
+    }
+
+    pub fn recip(self) -> Dual {
+        let recip = 1.0 / self.value;
+        self.chain(recip, -recip * recip)
+    }
+
+    pub fn sqrt(self) -> Dual {
+        let sqrt = f32::sqrt(self.value);
+        self.chain(sqrt, 0.5 / sqrt)
+    }
+
+    pub fn exp(self) -> Dual {
+        let exp = f32::exp(self.value);
+        self.chain(exp, exp)
+    }
+
+    pub fn ln(self) -> Dual {
+        self.chain(f32::ln(self.value), 1.0 / self.value)
+    }
+
+    pub fn powf(self, exponent: f32) -> Dual {
+        self.chain(
+            f32::powf(self.value, exponent),
+            exponent * f32::powf(self.value, exponent - 1.0),
+        )
+    }
+
+    pub fn powi(self, exponent: i32) -> Dual {
+        self.chain(
+            f32::powi(self.value, exponent),
+            exponent as f32 * f32::powi(self.value, exponent - 1),
+        )
+    }
+
+    pub fn sin(self) -> Dual {
+        self.chain(f32::sin(self.value), f32::cos(self.value))
+    }
+
+    pub fn cos(self) -> Dual {
+        self.chain(f32::cos(self.value), -f32::sin(self.value))
+    }
+
+    pub fn tanh(self) -> Dual {
+        let tanh = f32::tanh(self.value);
+        self.chain(tanh, 1.0 - tanh * tanh)
+    }
+
+    /// The absolute value, whose gradient flips sign at 0 (see `s_abs` for a smooth version)
+    pub fn abs(self) -> Dual {
+        self.chain(f32::abs(self.value), f32::signum(self.value))
+    }
+
+    /// The floor of the value, flat between integers
+    pub fn floor(self) -> Dual {
+        Dual::constant(f32::floor(self.value))
+    }
+
+    /// The smaller of the two values with its gradient (see `s_min` for a smooth version)
+    pub fn min(self, other: Dual) -> Dual {
+        if self.value <= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    /// The larger of the two values with its gradient (see `s_max` for a smooth version)
+    pub fn max(self, other: Dual) -> Dual {
+        if self.value >= other.value {
+            self
+        } else {
+            other
+        }
+    }
+
+    pub fn clamp(self, min: f32, max: f32) -> Dual {
+        self.max(Dual::constant(min)).min(Dual::constant(max))
+    }
+
+    /// Smooth minimum with smoothness factor `k`, like `utils::math::s_min`
+    pub fn s_min(self, other: Dual, k: f32) -> Dual {
+        -((self * -k).exp() + (other * -k).exp()).ln() / k
+    }
+
+    /// Smooth maximum with smoothness factor `k`, like `utils::math::s_max`
+    pub fn s_max(self, other: Dual, k: f32) -> Dual {
+        // shifted by the maximum so that the exponentials cannot overflow
+        let max = f32::max(self.value, other.value);
+        (((self - max) * k).exp() + ((other - max) * k).exp()).ln() / k + max
+    }
+
+    /// Smooth absolute value with smoothness factor `k`, like `utils::math::s_abs`
+    pub fn s_abs(self, k: f32) -> Dual {
+        ((self * k).exp() + (self * -k).exp()).ln() / k
+    }
+
+    /// Hermite interpolation between 0 below `edge0` and 1 above `edge1`, like `utils::math::smoothstep`
+    pub fn smoothstep(self, edge0: f32, edge1: f32) -> Dual {
+        if self.value <= edge0 {
+            return Dual::ZERO;
+        } else if self.value >= edge1 {
+            return Dual::ONE;
+        }
+        let t = (self - edge0) / (edge1 - edge0);
+        t * t * (3.0 - t * 2.0)
+    }
+
+    /// Tanh-based interpolation in [0, 1] with sharpness `s`, like `utils::math::tanh_sharpen`
+    pub fn tanh_sharpen(self, s: f32) -> Dual {
+        ((self - 0.5) * s).tanh() / f32::tanh(0.5 * s) * 0.5 + 0.5
+    }
+}
+
+impl From<f32> for Dual {
+    fn from(value: f32) -> Self {
+        Dual::constant(value)
+    }
+}
+
+impl ops::Neg for Dual {
+    type Output = Dual;
+
+    fn neg(self) -> Self::Output {
+        self.chain(-self.value, -1.0)
+    }
+}
+
+impl ops::Add<Dual> for Dual {
+    type Output = Dual;
+
+    fn add(self, rhs: Dual) -> Self::Output {
+        Dual {
+            value: self.value + rhs.value,
+            derivatives: [0, 1, 2].map(|i| self.derivatives[i] + rhs.derivatives[i]),
+        }
+    }
+}
+
+impl ops::Sub<Dual> for Dual {
+    type Output = Dual;
+
+    fn sub(self, rhs: Dual) -> Self::Output {
+        Dual {
+            value: self.value - rhs.value,
+            derivatives: [0, 1, 2].map(|i| self.derivatives[i] - rhs.derivatives[i]),
+        }
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl ops::Mul<Dual> for Dual {
+    type Output = Dual;
+
+    fn mul(self, rhs: Dual) -> Self::Output {
+        // product rule
+        Dual {
+            value: self.value * rhs.value,
+            derivatives: [0, 1, 2]
+                .map(|i| self.derivatives[i] * rhs.value + self.value * rhs.derivatives[i]),
+        }
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl ops::Div<Dual> for Dual {
+    type Output = Dual;
+
+    fn div(self, rhs: Dual) -> Self::Output {
+        // quotient rule
+        let value = self.value / rhs.value;
+        Dual {
+            value,
+            derivatives: [0, 1, 2]
+                .map(|i| (self.derivatives[i] - value * rhs.derivatives[i]) / rhs.value),
+        }
+    }
+}
+
+impl ops::Add<f32> for Dual {
+    type Output = Dual;
+
+    fn add(self, rhs: f32) -> Self::Output {
+        Dual {
+            value: self.value + rhs,
+            derivatives: self.derivatives,
+        }
+    }
+}
+
+impl ops::Sub<f32> for Dual {
+    type Output = Dual;
+
+    fn sub(self, rhs: f32) -> Self::Output {
+        Dual {
+            value: self.value - rhs,
+            derivatives: self.derivatives,
+        }
+    }
+}
+
+impl ops::Mul<f32> for Dual {
+    type Output = Dual;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        self.chain(self.value * rhs, rhs)
+    }
+}
+
+impl ops::Div<f32> for Dual {
+    type Output = Dual;
+
+    fn div(self, rhs: f32) -> Self::Output {
+        self.chain(self.value / rhs, 1.0 / rhs)
+    }
+}
+
+impl ops::Add<Dual> for f32 {
+    type Output = Dual;
+
+    fn add(self, rhs: Dual) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl ops::Sub<Dual> for f32 {
+    type Output = Dual;
+
+    fn sub(self, rhs: Dual) -> Self::Output {
+        -rhs + self
+    }
+}
+
+impl ops::Mul<Dual> for f32 {
+    type Output = Dual;
+
+    fn mul(self, rhs: Dual) -> Self::Output {
+        rhs * self
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl ops::Div<Dual> for f32 {
+    type Output = Dual;
+
+    fn div(self, rhs: Dual) -> Self::Output {
+        rhs.recip() * self
+    }
+}
+
+impl ops::AddAssign<Dual> for Dual {
+    fn add_assign(&mut self, rhs: Dual) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::SubAssign<Dual> for Dual {
+    fn sub_assign(&mut self, rhs: Dual) {
+        *self = *self - rhs;
+    }
+}
+
+impl ops::MulAssign<Dual> for Dual {
+    fn mul_assign(&mut self, rhs: Dual) {
+        *self = *self * rhs;
+    }
+}
+
+impl ops::DivAssign<Dual> for Dual {
+    fn div_assign(&mut self, rhs: Dual) {
+        *self = *self / rhs;
+    }
+}
+
+impl ops::AddAssign<f32> for Dual {
+    fn add_assign(&mut self, rhs: f32) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::SubAssign<f32> for Dual {
+    fn sub_assign(&mut self, rhs: f32) {
+        *self = *self - rhs;
+    }
+}
+
+impl ops::MulAssign<f32> for Dual {
+    fn mul_assign(&mut self, rhs: f32) {
+        *self = *self * rhs;
+    }
+}
+
+impl ops::DivAssign<f32> for Dual {
+    fn div_assign(&mut self, rhs: f32) {
+        *self = *self / rhs;
+    }
+}
+
+impl std::iter::Sum for Dual {
+    fn sum<I: Iterator<Item = Dual>>(iter: I) -> Self {
+        iter.fold(Dual::ZERO, |sum, x| sum + x)
+    }
+}
@@ -1,24 +1,26 @@
+use crate::utils::math::{multiply, smoothstep};
+use crate::utils::random::pcg3d;
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 use std::f32::consts::PI;
 
 // from https://www.shadertoy.com/view/4dffRH
 
-fn hash(p: &Vector3) -> Vector3 // this hash is not production ready, please
-{
-    // replace this by something better
-    let mut new_p = Vector3::new(
-        Vector3::dot(p, &Vector3::new(127.1, 311.7, 74.7)),
-        Vector3::dot(p, &Vector3::new(269.5, 183.3, 246.1)),
-        Vector3::dot(p, &Vector3::new(113.5, 271.9, 124.6)),
+/// Random offset in [-1, 1]^3 for an integer cell, stable for any cell coordinate representable by an i32
+fn hash(cell: &Vector3d, seed: u32) -> Vector3 {
+    let (seed_x, seed_y, seed_z) = pcg3d(seed, seed, seed);
+    let (x, y, z) = pcg3d(
+        (cell.x as i32 as u32) ^ seed_x,
+        (cell.y as i32 as u32) ^ seed_y,
+        (cell.z as i32 as u32) ^ seed_z,
     );
 
-    new_p.map_in_place(f32::sin);
-    new_p *= 43_758.547;
-    new_p.map_in_place(f32::fract);
-    new_p *= 2.0;
-    new_p -= 1.0;
-
-    new_p
+    Vector3::new(
+        x as f32 / u32::MAX as f32,
+        y as f32 / u32::MAX as f32,
+        z as f32 / u32::MAX as f32,
+    ) * 2.0
+        - Vector3::one()
 }
 
 fn curl3d(vector: &Vector3) -> Vector3 {
@@ -29,9 +31,17 @@ fn curl3d(vector: &Vector3) -> Vector3 {
     )
 }
 
-fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
-    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
-    t * t * (3.0 - 2.0 * t)
+/// The normalized projection of the curl of the given vector onto the plane tangent to `normal`
+fn tangent_direction(vector: &Vector3, normal: &Vector3) -> Vector3 {
+    let mut direction = curl3d(vector);
+    direction -= normal * Vector3::dot(&direction, normal);
+
+    let length = direction.length();
+    if length > 0.0 {
+        direction /= length;
+    }
+
+    direction
 }
 
 // code adapted from https://www.shadertoy.com/view/llsGWl
@@ -40,40 +50,70 @@ fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
 // license: Creative Commons Attribution-NonCommercial-ShareAlike 3.0 Unported License
 //Code has been modified to return analytic derivatives and to favour
 //direction quite a bit.
-pub fn erosion(p: &Vector3, dir: &Vector3) -> (f32, Vector3) {
-    let ip = p.map(f32::floor);
-    let fp = p.map(f32::fract);
+/// Returns a value in [-1, 1] made of gullies aligned with `dir` and its gradient with respect to `p` (`dir` being treated as constant)
+pub fn erosion(p: &Vector3d, dir: &Vector3, seed: u32) -> (f32, Vector3) {
+    let ip = Vector3d::new(p.x.floor(), p.y.floor(), p.z.floor());
+    // the position in the cell is narrowed once it is small
+    let fp = (p - &ip).to_f32();
     let f = 2.0 * PI;
-    let mut va = 0.0;
-    let mut gradient = Vector3::zero();
-    let mut wt = 0.0;
+    let mut weighted_sum = 0.0;
+    let mut weighted_sum_gradient = Vector3::zero();
+    let mut total_weight = 0.0;
+    let mut total_weight_gradient = Vector3::zero();
     for i in -2..=1 {
         for j in -2..=1 {
             for k in -2..=1 {
                 let o = Vector3::new(i as f32, j as f32, k as f32);
-                let offset = &ip - &o;
-                let h = hash(&offset) * 0.5;
+                let offset = &ip - &Vector3d::from(&o);
+                let h = hash(&offset, seed) * 0.5;
                 let pp = &fp + &o - h;
                 let d = Vector3::dot(&pp, &pp);
                 let w = f32::exp(-d * 2.0);
-                wt += w;
+                let weight_gradient = &pp * (-4.0 * w);
+
                 let mag = Vector3::dot(&pp, dir);
-                let grad = (&pp + dir) * (-f32::sin(mag * f)) * w;
-                va += f32::cos(mag * f) * w;
-                gradient += grad;
+                let wave = f32::cos(mag * f);
+                let wave_gradient = dir * (-f32::sin(mag * f) * f);
+
+                weighted_sum += wave * w;
+                weighted_sum_gradient += &wave_gradient * w;
+                weighted_sum_gradient += &weight_gradient * wave;
+
+                total_weight += w;
+                total_weight_gradient += weight_gradient;
             }
         }
     }
-    (va / wt, gradient / wt)
+
+    // quotient rule on the normalized sum
+    let value = weighted_sum / total_weight;
+    let gradient = (weighted_sum_gradient - total_weight_gradient * value) / total_weight;
+
+    (value, gradient)
 }
 
-//This is where the magic happens
-pub fn erode3d(p: &Vector3, noise_value: f32, out_gradient: &mut Vector3) -> f32 {
-    let normal = p.normalize_to_new();
+/// Carves directional gullies following the slope of an elevation in [0, 1]
+/// * `p` - The position of the sample on the unit sphere
+/// * `frequency` - The frequency of the first erosion octave
+/// * `nb_octaves` - The number of erosion octaves
+/// * `seed` - The seed of the erosion pattern
+/// * `elevation` - The elevation to erode, low valleys are left untouched
+/// * `elevation_gradient` - The gradient of the elevation which orients the gullies
+/// * `out_gradient` - The recipient for the gradient of the erosion (will be overridden)
+///
+/// Returns the offset to add to the elevation
+pub fn erode3d(
+    p: &Vector3d,
+    frequency: f32,
+    nb_octaves: u32,
+    seed: u32,
+    elevation: f32,
+    elevation_gradient: &Vector3,
+    out_gradient: &mut Vector3,
+) -> f32 {
+    let normal = p.normalize_to_new().to_f32();
     //take the curl of the normal to get the gradient facing down the slope
-    //Vector2(noiseGradient.y, noiseGradient.x).multiplyByFloats(1.0, -1.0);
-    let mut dir = curl3d(out_gradient);
-    dir -= &normal * Vector3::dot(&dir, &normal);
+    let dir = tangent_direction(elevation_gradient, &normal);
 
     //Now we compute another fbm type noise
     // erosion is a type of noise with a strong directionality
@@ -83,15 +123,15 @@ pub fn erode3d(p: &Vector3, noise_value: f32, out_gradient: &mut Vector3) -> f32
     //past layers
     let mut h = 0.0;
     let mut erosion_gradient = Vector3::zero();
-    let mut a = 0.7 * (smoothstep(0.1, 0.5, noise_value * 0.5 + 0.5)); //smooth the valleys
-    let mut f = 1.0;
-    for _ in 0..5 {
-        let sample_point = p * f;
-        let mut local_dir = curl3d(&erosion_gradient);
-        local_dir -= &normal * Vector3::dot(&local_dir, &normal);
+    let mut a = 0.7;
+    let mut f = frequency;
+    for _ in 0..nb_octaves {
+        let sample_point = p * f as f64;
+        let local_dir = tangent_direction(&erosion_gradient, &normal);
 
         let sample_direction = &dir + &local_dir;
-        let (mut local_elevation, mut local_gradient) = erosion(&sample_point, &sample_direction);
+        let (mut local_elevation, mut local_gradient) =
+            erosion(&sample_point, &sample_direction, seed);
 
         local_elevation *= a;
         local_gradient *= a * f;
@@ -103,14 +143,10 @@ pub fn erode3d(p: &Vector3, noise_value: f32, out_gradient: &mut Vector3) -> f32
         f *= 2.0;
     }
 
-    //remap height to [0,1] and add erosion
-    //looks best when erosion amount is small
-    //not sure about adding the normals together, but it looks okay
-    //new Vector3(out_gradient.x + h.y, out_gradient.y + h.w, out_gradient.z + h.z);
-
-    *out_gradient += &erosion_gradient;
-    *out_gradient *= 1.0 / 30.0;
+    //smooth the valleys
+    let mut valley_gradient = elevation_gradient.clone();
+    let valley_mask = smoothstep(0.1, 0.5, elevation, &mut valley_gradient);
 
-    let erosion_strength = 0.04;
-    smoothstep(-1.0, 1.0, noise_value) + h * erosion_strength
+    *out_gradient = erosion_gradient;
+    multiply(h, valley_mask, out_gradient, &valley_gradient)
 }
@@ -24,8 +24,10 @@ pub fn s_min(a: f32, b: f32, k: f32) -> f32 {
  */
 #[wasm_bindgen]
 pub fn s_max(a: f32, b: f32, k: f32) -> f32 {
-    let res = f32::exp(k * a) + f32::exp(k * b);
-    f32::ln(res) / k
+    // shifted by the maximum so that the exponentials cannot overflow
+    let max = f32::max(a, b);
+    let res = f32::exp(k * (a - max)) + f32::exp(k * (b - max));
+    max + f32::ln(res) / k
 }
 
 // based on research folder
@@ -60,14 +62,16 @@ pub fn s_min_gradient(u: f32, v: f32, k: f32, grad_u: &mut Vector3, grad_v: &Vec
  * @returns the smooth maximum between u and v and overrides grad_u with the new gradient
  */
 pub fn s_max_gradient(u: f32, v: f32, k: f32, grad_u: &mut Vector3, grad_v: &mut Vector3) -> f32 {
-    let eku = f32::exp(k * u);
-    let ekv = f32::exp(k * v);
+    // shifted by the maximum so that the exponentials cannot overflow
+    let max = f32::max(u, v);
+    let eku = f32::exp(k * (u - max));
+    let ekv = f32::exp(k * (v - max));
     let ekuv = eku + ekv;
 
     *grad_u *= eku / ekuv;
     *grad_v *= ekv / ekuv;
 
-    f32::ln(ekuv) / k
+    max + f32::ln(ekuv) / k
 }
 
 /**
@@ -82,7 +86,7 @@ pub fn s_ceil(x: f32, ceil: f32, k: f32, grad: &mut Vector3) -> f32 {
     let emkx = f32::exp(-k * x);
     let emkceil = f32::exp(-k * ceil);
 
-    *grad *= -emkx / (emkx + emkceil);
+    *grad *= emkx / (emkx + emkceil);
 
     -f32::ln(emkx + emkceil) / k
 }
@@ -148,6 +152,26 @@ pub fn pow(y: f32, exponent: f32, grad: &mut Vector3) -> f32 {
     f32::powf(y, exponent)
 }
 
+/**
+ * Applies a staircase profile to a value and scales the optional gradient accordingly
+ * @param x the value to terrace
+ * @param nb_steps the number of steps per unit of x
+ * @param sharpness the sharpness of the steps (values <= 0 leave x unchanged)
+ * @param grad the optional gradient to be modified
+ */
+pub fn terrace(x: f32, nb_steps: f32, sharpness: f32, grad: &mut Vector3) -> f32 {
+    if nb_steps <= 0.0 || sharpness <= 0.0 {
+        return x;
+    }
+    let scaled_x = x * nb_steps;
+    let step = f32::floor(scaled_x);
+
+    // tanh_sharpen has the same slope at 0 and 1 so the profile stays smooth from one step to the next
+    let step_profile = tanh_sharpen(scaled_x - step, sharpness, grad);
+
+    (step + step_profile) / nb_steps
+}
+
 pub fn minimum_value(y: f32, min_value: f32, grad: &mut Vector3) -> f32 {
     let new_y = s_floor(y - min_value, 0.0, 100.0, grad) / (1.0 - min_value);
     *grad /= 1.0 - min_value;
@@ -198,7 +222,7 @@ pub fn smoothstep(edge0: f32, edge1: f32, x: f32, grad: &mut Vector3) -> f32 {
     let t2 = t * t;
     let t3 = t2 * t;
 
-    *grad *= 6.0 * t2 * (1.0 - t) / (edge1 - edge0);
+    *grad *= 6.0 * t * (1.0 - t) / (edge1 - edge0);
 
     3.0 * t2 - 2.0 * t3
 }
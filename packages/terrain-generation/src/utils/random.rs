@@ -1,13 +1,32 @@
-pub fn random() -> u64 {
-    unsafe {
-        static mut STATE: u64 = 0x123456789abcdef0;
-        STATE = STATE
-            .wrapping_mul(2862933555777941757)
-            .wrapping_add(3037000493);
-        STATE
-    }
+/// Advances the given state of a linear congruential generator and returns it
+pub fn random(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(2862933555777941757)
+        .wrapping_add(3037000493);
+    *state
 }
 
-pub fn random01() -> f32 {
-    random() as f32 / 18446744073709551616.0
+pub fn random01(state: &mut u64) -> f32 {
+    random(state) as f32 / 18446744073709551616.0
+}
+
+/// PCG3D integer hash (Jarzynski & Olano, Hash Functions for GPU Rendering, 2020)
+pub fn pcg3d(mut x: u32, mut y: u32, mut z: u32) -> (u32, u32, u32) {
+    x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    y = y.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    z = z.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+    x = x.wrapping_add(y.wrapping_mul(z));
+    y = y.wrapping_add(z.wrapping_mul(x));
+    z = z.wrapping_add(x.wrapping_mul(y));
+
+    x ^= x >> 16;
+    y ^= y >> 16;
+    z ^= z >> 16;
+
+    x = x.wrapping_add(y.wrapping_mul(z));
+    y = y.wrapping_add(z.wrapping_mul(x));
+    z = z.wrapping_add(x.wrapping_mul(y));
+
+    (x, y, z)
 }
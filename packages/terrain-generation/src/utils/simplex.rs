@@ -32,7 +32,9 @@
  * 2022: Ported to Rust by Barthélemy Paléologue
  */
 
+use crate::seed::{mix64, NoiseSeed, Seed, SeedKind};
 use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
 
 fn fast_floor(x: f32) -> f32 {
     if f32::floor(x) <= x {
@@ -48,7 +50,9 @@ fn fast_floor(x: f32) -> f32 {
  * Permutation table. This is just a random jumble of all numbers 0-255,
  * repeated twice to avoid wrapping the index at 255 for each lookup.
  */
-const PERM: [usize; 512] = [
+pub(crate) type PermutationTable = [usize; 512];
+
+const PERM: PermutationTable = [
     151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
     142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
     203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
@@ -76,6 +80,34 @@ const PERM: [usize; 512] = [
     222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
 ];
 
+/// Shuffles the numbers 0-255 with the given seed (Fisher-Yates), repeated twice like `PERM`
+fn shuffled_permutation(seed: u64) -> PermutationTable {
+    let mut table = [0; 512];
+    for (i, value) in table.iter_mut().take(256).enumerate() {
+        *value = i;
+    }
+
+    let mut state = seed;
+    for i in (1..256).rev() {
+        state = mix64(state);
+        let j = (state % (i as u64 + 1)) as usize;
+        table.swap(i, j);
+    }
+
+    for i in 0..256 {
+        table[i + 256] = table[i];
+    }
+    table
+}
+
+/// The permutation table of the seed: `PERM` for offset seeds, shuffled for integer seeds
+pub(crate) fn permutation_table(seed: Seed) -> Box<PermutationTable> {
+    match seed.kind() {
+        SeedKind::Offset(_) => Box::new(PERM),
+        SeedKind::Integer(seed) => Box::new(shuffled_permutation(seed)),
+    }
+}
+
 /*
  * Gradient tables. These could be programmed the Ken Perlin way with
  * some clever bit-twiddling, but this is more clear, and not really slower.
@@ -219,7 +251,7 @@ fn grad3(hash: usize) -> (f32, f32, f32) {
     (gx, gy, gz)
 }
 
-fn grad4(hash: usize) -> (f32, f32, f32, f32) {
+pub(crate) fn grad4(hash: usize) -> (f32, f32, f32, f32) {
     let h = hash & 31;
     let gx = GRAD4LUT[h][0];
     let gy = GRAD4LUT[h][1];
@@ -429,9 +461,34 @@ fn sdnoise3(x: f32, y: f32, z: f32, gradient: &mut Vector3) -> f32 {
 }
 
 // The skewing and unskewing factors are hairy again for the 4D case
-const F4: f32 = 0.309_016_97;
+pub(crate) const F4: f32 = 0.309_016_97;
 // F4 = (Math.sqrt(5.0)-1.0)/4.0
-const G4: f32 = 0.138_196_6; // G4 = (5.0-Math.sqrt(5.0))/20.0
+pub(crate) const G4: f32 = 0.138_196_6; // G4 = (5.0-Math.sqrt(5.0))/20.0
+
+// Scale the sum of the corner contributions to cover the range [-1,1], the gradient is scaled by the same factor
+pub(crate) const NOISE_SCALE: f32 = 27.0;
+
+/// The cell of the skewed lattice containing the point and the offset of the point from the origin of the cell.
+/// The coordinates of the point grow with the frequency of the noise while the offset stays below 1,
+/// so the offset is computed in double precision before it is narrowed: in single precision the noise
+/// would be a staircase at the scale of the vertices of deep chunks.
+pub(crate) fn skewed_cell4(x: f64, y: f64, z: f64, w: f64) -> ([f64; 4], [f32; 4]) {
+    // Skew the (x,y,z,w) space to determine which cell of 24 simplices we're in
+    let s = (x + y + z + w) * F4 as f64; // Factor for 4D skewing
+    let cell = [x + s, y + s, z + s, w + s].map(f64::floor);
+
+    let t = cell.iter().sum::<f64>() * G4 as f64; // Factor for 4D unskewing
+    let point = [x, y, z, w];
+    // The x,y,z,w distances from the cell origin, unskewed back to (x,y,z,w) space
+    let offset = std::array::from_fn(|axis| (point[axis] - (cell[axis] - t)) as f32);
+
+    (cell, offset)
+}
+
+/// Wraps an integer index of the lattice at 256, to avoid indexing perm[] out of bounds
+pub(crate) fn wrap_cell(cell: f64) -> usize {
+    (cell as i64 & 0xff) as usize
+}
 
 #[derive(Clone, Copy)]
 struct CornerContribution {
@@ -461,7 +518,8 @@ impl CornerContribution {
 }
 
 #[allow(clippy::too_many_arguments)]
-fn gradient_index(
+pub(crate) fn gradient_index(
+    perm: &PermutationTable,
     ii: usize,
     i_offset: usize,
     jj: usize,
@@ -476,7 +534,7 @@ fn gradient_index(
     let k_idx = kk + k_offset;
     let l_idx = ll + l_offset;
 
-    PERM[i_idx + PERM[j_idx + PERM[k_idx + PERM[l_idx]]]]
+    perm[i_idx + perm[j_idx + perm[k_idx + perm[l_idx]]]]
 }
 
 fn corner_contribution(
@@ -508,33 +566,16 @@ fn corner_contribution(
     }
 }
 
-/** 4D SIMPLEX noise with derivatives.
-* If the last four arguments are not null, the analytic derivative
-* (the 4D gradient of the scalar noise field) is also calculated.
- */
-fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
-    // Skew the (x,y,z,w) space to determine which cell of 24 simplices we're in
-    let s = (x + y + z + w) * F4; // Factor for 4D skewing
-    let xs = x + s;
-    let ys = y + s;
-    let zs = z + s;
-    let ws = w + s;
-
-    let i = fast_floor(xs);
-    let j = fast_floor(ys);
-    let k = fast_floor(zs);
-    let l = fast_floor(ws);
-
-    let t = (i + j + k + l) * G4; // Factor for 4D unskewing
-    let x_origin = i - t; // Unskew the cell origin back to (x,y,z,w) space
-    let y_origin = j - t;
-    let z_origin = k - t;
-    let w_origin = l - t;
-
-    let x0 = x - x_origin; // The x,y,z,w distances from the cell origin
-    let y0 = y - y_origin;
-    let z0 = z - z_origin;
-    let w0 = w - w_origin;
+/// The contributions of the five corners of the 4D simplex containing the point, with the x, y, z offsets
+/// of the point from each corner
+fn corners4(
+    x: f64,
+    y: f64,
+    z: f64,
+    w: f64,
+    perm: &PermutationTable,
+) -> [(CornerContribution, f32, f32, f32); 5] {
+    let ([i, j, k, l], [x0, y0, z0, w0]) = skewed_cell4(x, y, z, w);
 
     // For the 4D case, the SIMPLEX is a 4D shape I won't even try to describe.
     // To find out which of the 24 possible simplices we're in, we need to
@@ -590,11 +631,10 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     let z4 = z0 - 1.0 + 4.0 * G4;
     let w4 = w0 - 1.0 + 4.0 * G4;
 
-    // Wrap the integer indices at 256, to avoid indexing PERM[] out of bounds
-    let ii = (i as i32 & 0xff) as usize;
-    let jj = (j as i32 & 0xff) as usize;
-    let kk = (k as i32 & 0xff) as usize;
-    let ll = (l as i32 & 0xff) as usize;
+    let ii = wrap_cell(i);
+    let jj = wrap_cell(j);
+    let kk = wrap_cell(k);
+    let ll = wrap_cell(l);
 
     let i1_usize = i1 as usize;
     let j1_usize = j1 as usize;
@@ -611,7 +651,7 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
 
     let corner0 = corner_contribution(
         0.6 - x0 * x0 - y0 * y0 - z0 * z0 - w0 * w0,
-        gradient_index(ii, 0, jj, 0, kk, 0, ll, 0),
+        gradient_index(perm, ii, 0, jj, 0, kk, 0, ll, 0),
         x0,
         y0,
         z0,
@@ -619,7 +659,7 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     );
     let corner1 = corner_contribution(
         0.6 - x1 * x1 - y1 * y1 - z1 * z1 - w1 * w1,
-        gradient_index(ii, i1_usize, jj, j1_usize, kk, k1_usize, ll, l1_usize),
+        gradient_index(perm, ii, i1_usize, jj, j1_usize, kk, k1_usize, ll, l1_usize),
         x1,
         y1,
         z1,
@@ -627,7 +667,7 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     );
     let corner2 = corner_contribution(
         0.6 - x2 * x2 - y2 * y2 - z2 * z2 - w2 * w2,
-        gradient_index(ii, i2_usize, jj, j2_usize, kk, k2_usize, ll, l2_usize),
+        gradient_index(perm, ii, i2_usize, jj, j2_usize, kk, k2_usize, ll, l2_usize),
         x2,
         y2,
         z2,
@@ -635,7 +675,7 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     );
     let corner3 = corner_contribution(
         0.6 - x3 * x3 - y3 * y3 - z3 * z3 - w3 * w3,
-        gradient_index(ii, i3_usize, jj, j3_usize, kk, k3_usize, ll, l3_usize),
+        gradient_index(perm, ii, i3_usize, jj, j3_usize, kk, k3_usize, ll, l3_usize),
         x3,
         y3,
         z3,
@@ -643,27 +683,47 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     );
     let corner4 = corner_contribution(
         0.6 - x4 * x4 - y4 * y4 - z4 * z4 - w4 * w4,
-        gradient_index(ii, 1, jj, 1, kk, 1, ll, 1),
+        gradient_index(perm, ii, 1, jj, 1, kk, 1, ll, 1),
         x4,
         y4,
         z4,
         w4,
     );
 
+    [
+        (corner0, x0, y0, z0),
+        (corner1, x1, y1, z1),
+        (corner2, x2, y2, z2),
+        (corner3, x3, y3, z3),
+        (corner4, x4, y4, z4),
+    ]
+}
+
+/** 4D SIMPLEX noise with derivatives.
+* If the last four arguments are not null, the analytic derivative
+* (the 4D gradient of the scalar noise field) is also calculated.
+ */
+fn sdnoise4(
+    x: f64,
+    y: f64,
+    z: f64,
+    w: f64,
+    perm: &PermutationTable,
+    gradient: &mut Vector3,
+) -> f32 {
+    sum_corners4(&corners4(x, y, z, w, perm), gradient)
+}
+
+/// Sums the contributions of the corners into the noise and its gradient
+fn sum_corners4(corners: &[(CornerContribution, f32, f32, f32); 5], gradient: &mut Vector3) -> f32 {
     // Sum up and scale the result to cover the range [-1,1]
-    let noise = 27.0 * (corner0.n + corner1.n + corner2.n + corner3.n + corner4.n);
+    let noise = NOISE_SCALE * corners.iter().map(|(corner, ..)| corner.n).sum::<f32>();
 
     let mut dnoise_dx = 0.0;
     let mut dnoise_dy = 0.0;
     let mut dnoise_dz = 0.0;
 
-    for (corner, x_comp, y_comp, z_comp) in [
-        (corner0, x0, y0, z0),
-        (corner1, x1, y1, z1),
-        (corner2, x2, y2, z2),
-        (corner3, x3, y3, z3),
-        (corner4, x4, y4, z4),
-    ] {
+    for &(corner, x_comp, y_comp, z_comp) in corners {
         let temp = corner.t2 * corner.t * corner.grad_dot;
         dnoise_dx += temp * x_comp;
         dnoise_dy += temp * y_comp;
@@ -674,16 +734,15 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     dnoise_dy *= -8.0;
     dnoise_dz *= -8.0;
 
-    for corner in [corner0, corner1, corner2, corner3, corner4] {
+    for (corner, ..) in corners {
         dnoise_dx += corner.t4 * corner.gx;
         dnoise_dy += corner.t4 * corner.gy;
         dnoise_dz += corner.t4 * corner.gz;
     }
 
-    let magic_factor = 40.0;
-    dnoise_dx *= magic_factor;
-    dnoise_dy *= magic_factor;
-    dnoise_dz *= magic_factor;
+    dnoise_dx *= NOISE_SCALE;
+    dnoise_dy *= NOISE_SCALE;
+    dnoise_dz *= NOISE_SCALE;
 
     gradient.x = dnoise_dx;
     gradient.y = dnoise_dy;
@@ -692,14 +751,55 @@ fn sdnoise4(x: f32, y: f32, z: f32, w: f32, gradient: &mut Vector3) -> f32 {
     noise
 }
 
+/// 4D SIMPLEX noise with its gradient and the second derivatives along x, y and z
+fn sdnoise4_hessian(
+    x: f64,
+    y: f64,
+    z: f64,
+    w: f64,
+    perm: &PermutationTable,
+    gradient: &mut Vector3,
+    hessian: &mut [Vector3; 3],
+) -> f32 {
+    let corners = corners4(x, y, z, w, perm);
+    let noise = sum_corners4(&corners, gradient);
+
+    // the derivative of t^4 * (g . x) with t = 0.6 - |x|^2 along j then k is
+    // 48 t^2 x_j x_k (g . x) - 8 t^3 (delta_jk (g . x) + x_j g_k + x_k g_j)
+    let mut second_derivatives = [[0.0; 3]; 3];
+    for (corner, x_comp, y_comp, z_comp) in corners {
+        let offset = [x_comp, y_comp, z_comp];
+        let corner_gradient = [corner.gx, corner.gy, corner.gz];
+        let t3 = corner.t2 * corner.t;
+        for j in 0..3 {
+            for k in 0..3 {
+                let diagonal = if j == k { corner.grad_dot } else { 0.0 };
+                second_derivatives[j][k] +=
+                    48.0 * corner.t2 * offset[j] * offset[k] * corner.grad_dot
+                        - 8.0
+                            * t3
+                            * (diagonal
+                                + offset[j] * corner_gradient[k]
+                                + offset[k] * corner_gradient[j]);
+            }
+        }
+    }
+
+    for (row, derivatives) in hessian.iter_mut().zip(second_derivatives) {
+        *row = Vector3::new(derivatives[0], derivatives[1], derivatives[2]) * NOISE_SCALE;
+    }
+
+    noise
+}
+
 /**
  * Returns a random value in [0,1] based on SIMPLEX noise and computes the gradient if specified
  * @param vector the position to sample the noise at
  * @param gradient the recipient for the gradient (will be overridden)
- * @param seed an offset along the 4th dimension
+ * @param seed the resolved seed providing the permutation table and the offset along the 4th dimension
  */
-pub fn simplex401(vector: &Vector3, seed: f32, gradient: &mut Vector3) -> f32 {
-    let noise_value = sdnoise4(vector.x, vector.y, vector.z, seed, gradient);
+pub fn simplex401(vector: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+    let noise_value = simplex411(vector, seed, gradient);
     // [0,1] is half the length of [-1,1]
     *gradient /= 2.0;
 
@@ -710,8 +810,39 @@ pub fn simplex401(vector: &Vector3, seed: f32, gradient: &mut Vector3) -> f32 {
  * Returns a random value in [-1,1] based on SIMPLEX noise and computes the gradient if specified
  * @param vector the position to sample the noise at
  * @param gradient the recipient for the gradient (will be overridden)
- * @param seed an offset along the 4th dimension
+ * @param seed the resolved seed providing the permutation table and the offset along the 4th dimension
+ */
+pub fn simplex411(vector: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+    sdnoise4(
+        vector.x,
+        vector.y,
+        vector.z,
+        seed.offset() as f64,
+        seed.permutation(),
+        gradient,
+    )
+}
+
+/**
+ * Returns a random value in [-1,1] based on SIMPLEX noise and computes its first and second derivatives
+ * @param vector the position to sample the noise at
+ * @param gradient the recipient for the gradient (will be overridden)
+ * @param hessian the recipient for the rows of the matrix of the second derivatives (will be overridden)
+ * @param seed the resolved seed providing the permutation table and the offset along the 4th dimension
  */
-pub fn simplex411(vector: &Vector3, seed: f32, gradient: &mut Vector3) -> f32 {
-    sdnoise4(vector.x, vector.y, vector.z, seed, gradient)
+pub fn simplex411_hessian(
+    vector: &Vector3d,
+    seed: &NoiseSeed,
+    gradient: &mut Vector3,
+    hessian: &mut [Vector3; 3],
+) -> f32 {
+    sdnoise4_hessian(
+        vector.x,
+        vector.y,
+        vector.z,
+        seed.offset() as f64,
+        seed.permutation(),
+        gradient,
+        hessian,
+    )
 }
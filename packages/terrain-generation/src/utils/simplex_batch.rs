@@ -0,0 +1,444 @@
+//! Batched evaluation of the 4D simplex noise, several points per call.
+//! The arithmetic runs on SIMD lanes (wasm simd128 when it is enabled, plain arrays otherwise)
+//! while the lookups in the permutation table are gathered lane by lane.
+//! The operations are the same as in `simplex.rs` and in the same order, so that both paths agree.
+
+use crate::seed::NoiseSeed;
+use crate::utils::simplex::{
+    grad4, gradient_index, skewed_cell4, wrap_cell, PermutationTable, G4, NOISE_SCALE,
+};
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use lanes::F32x4;
+
+/// The number of points evaluated per call
+pub const LANES: usize = 4;
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod lanes {
+    use core::arch::wasm32::*;
+    use std::ops;
+
+    #[derive(Clone, Copy)]
+    pub struct F32x4(v128);
+
+    impl F32x4 {
+        pub fn splat(value: f32) -> F32x4 {
+            F32x4(f32x4_splat(value))
+        }
+
+        pub fn from_array(values: [f32; 4]) -> F32x4 {
+            F32x4(f32x4(values[0], values[1], values[2], values[3]))
+        }
+
+        pub fn to_array(self) -> [f32; 4] {
+            [
+                f32x4_extract_lane::<0>(self.0),
+                f32x4_extract_lane::<1>(self.0),
+                f32x4_extract_lane::<2>(self.0),
+                f32x4_extract_lane::<3>(self.0),
+            ]
+        }
+
+        pub fn max(self, rhs: F32x4) -> F32x4 {
+            F32x4(f32x4_max(self.0, rhs.0))
+        }
+
+        /// 1 in the lanes where `self > rhs`, 0 elsewhere
+        pub fn greater_than(self, rhs: F32x4) -> F32x4 {
+            F32x4(v128_and(f32x4_gt(self.0, rhs.0), f32x4_splat(1.0)))
+        }
+    }
+
+    impl ops::Add for F32x4 {
+        type Output = F32x4;
+
+        fn add(self, rhs: F32x4) -> F32x4 {
+            F32x4(f32x4_add(self.0, rhs.0))
+        }
+    }
+
+    impl ops::Sub for F32x4 {
+        type Output = F32x4;
+
+        fn sub(self, rhs: F32x4) -> F32x4 {
+            F32x4(f32x4_sub(self.0, rhs.0))
+        }
+    }
+
+    impl ops::Mul for F32x4 {
+        type Output = F32x4;
+
+        fn mul(self, rhs: F32x4) -> F32x4 {
+            F32x4(f32x4_mul(self.0, rhs.0))
+        }
+    }
+}
+
+/// The scalar fallback used for native builds, which compilers are free to auto-vectorize
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+mod lanes {
+    use std::ops;
+
+    #[derive(Clone, Copy)]
+    pub struct F32x4([f32; 4]);
+
+    impl F32x4 {
+        pub fn splat(value: f32) -> F32x4 {
+            F32x4([value; 4])
+        }
+
+        pub fn from_array(values: [f32; 4]) -> F32x4 {
+            F32x4(values)
+        }
+
+        pub fn to_array(self) -> [f32; 4] {
+            self.0
+        }
+
+        fn map2(self, rhs: F32x4, f: impl Fn(f32, f32) -> f32) -> F32x4 {
+            F32x4(std::array::from_fn(|lane| f(self.0[lane], rhs.0[lane])))
+        }
+
+        pub fn max(self, rhs: F32x4) -> F32x4 {
+            self.map2(rhs, f32::max)
+        }
+
+        /// 1 in the lanes where `self > rhs`, 0 elsewhere
+        pub fn greater_than(self, rhs: F32x4) -> F32x4 {
+            self.map2(rhs, |a, b| if a > b { 1.0 } else { 0.0 })
+        }
+    }
+
+    impl ops::Add for F32x4 {
+        type Output = F32x4;
+
+        fn add(self, rhs: F32x4) -> F32x4 {
+            self.map2(rhs, |a, b| a + b)
+        }
+    }
+
+    impl ops::Sub for F32x4 {
+        type Output = F32x4;
+
+        fn sub(self, rhs: F32x4) -> F32x4 {
+            self.map2(rhs, |a, b| a - b)
+        }
+    }
+
+    impl ops::Mul for F32x4 {
+        type Output = F32x4;
+
+        fn mul(self, rhs: F32x4) -> F32x4 {
+            self.map2(rhs, |a, b| a * b)
+        }
+    }
+}
+
+/// The noise values and gradients of a batch of points, stored as structure of arrays
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseBatch {
+    pub values: [f32; LANES],
+    pub gradients_x: [f32; LANES],
+    pub gradients_y: [f32; LANES],
+    pub gradients_z: [f32; LANES],
+}
+
+impl NoiseBatch {
+    pub fn zero() -> NoiseBatch {
+        NoiseBatch {
+            values: [0.0; LANES],
+            gradients_x: [0.0; LANES],
+            gradients_y: [0.0; LANES],
+            gradients_z: [0.0; LANES],
+        }
+    }
+}
+
+/// The contribution of one corner of the simplices of the batch
+struct CornerLanes {
+    n: F32x4,
+    t: F32x4,
+    t2: F32x4,
+    t4: F32x4,
+    gx: F32x4,
+    gy: F32x4,
+    gz: F32x4,
+    grad_dot: F32x4,
+}
+
+/// The gradients of the corners of the lanes, looked up in the permutation table one lane at a time
+fn gather_gradients(
+    perm: &PermutationTable,
+    cells: [[usize; LANES]; 4],
+    offsets: [[f32; 4]; 4],
+) -> [F32x4; 4] {
+    let mut gradients = [[0.0; LANES]; 4];
+    for lane in 0..LANES {
+        let index = gradient_index(
+            perm,
+            cells[0][lane],
+            offsets[0][lane] as usize,
+            cells[1][lane],
+            offsets[1][lane] as usize,
+            cells[2][lane],
+            offsets[2][lane] as usize,
+            cells[3][lane],
+            offsets[3][lane] as usize,
+        );
+        let (gx, gy, gz, gw) = grad4(index);
+        gradients[0][lane] = gx;
+        gradients[1][lane] = gy;
+        gradients[2][lane] = gz;
+        gradients[3][lane] = gw;
+    }
+    gradients.map(F32x4::from_array)
+}
+
+fn corner_lanes(offset: [F32x4; 4], gradient: [F32x4; 4]) -> CornerLanes {
+    let [x, y, z, w] = offset;
+    let [gx, gy, gz, gw] = gradient;
+
+    // the corners out of reach have t < 0: clamping t zeroes their contribution like the scalar branch does
+    let t = (F32x4::splat(0.6) - x * x - y * y - z * z - w * w).max(F32x4::splat(0.0));
+    let t2 = t * t;
+    let t4 = t2 * t2;
+    let grad_dot = gx * x + gy * y + gz * z + gw * w;
+    CornerLanes {
+        n: t4 * grad_dot,
+        t,
+        t2,
+        t4,
+        gx,
+        gy,
+        gz,
+        grad_dot,
+    }
+}
+
+/// `sdnoise4` on 4 points at once, returning the noise and its gradient w.r.t. (x, y, z)
+fn sdnoise4_lanes(
+    x: &[f64; LANES],
+    y: &[f64; LANES],
+    z: &[f64; LANES],
+    w: f64,
+    perm: &PermutationTable,
+) -> (F32x4, [F32x4; 3]) {
+    // the cells are found in double precision one lane at a time, see `skewed_cell4`
+    let mut cells = [[0; LANES]; 4];
+    let mut origin = [[0.0; LANES]; 4];
+    for lane in 0..LANES {
+        let (cell, offset) = skewed_cell4(x[lane], y[lane], z[lane], w);
+        for axis in 0..4 {
+            cells[axis][lane] = wrap_cell(cell[axis]);
+            origin[axis][lane] = offset[axis];
+        }
+    }
+    let [x0, y0, z0, w0] = origin.map(F32x4::from_array);
+
+    // The rank of each coordinate in the magnitude ordering replaces the lookup in SIMPLEX[]:
+    // ties are broken the same way, in favor of the later coordinate
+    let one = F32x4::splat(1.0);
+    let x_gt_y = x0.greater_than(y0);
+    let x_gt_z = x0.greater_than(z0);
+    let y_gt_z = y0.greater_than(z0);
+    let x_gt_w = x0.greater_than(w0);
+    let y_gt_w = y0.greater_than(w0);
+    let z_gt_w = z0.greater_than(w0);
+    let rank_x = x_gt_y + x_gt_z + x_gt_w;
+    let rank_y = (one - x_gt_y) + y_gt_z + y_gt_w;
+    let rank_z = (one - x_gt_z) + (one - y_gt_z) + z_gt_w;
+    let rank_w = (one - x_gt_w) + (one - y_gt_w) + (one - z_gt_w);
+    let ranks = [rank_x, rank_y, rank_z, rank_w];
+
+    let offsets_above =
+        |threshold: f32| ranks.map(|rank| rank.greater_than(F32x4::splat(threshold)));
+    let offsets1 = offsets_above(2.5);
+    let offsets2 = offsets_above(1.5);
+    let offsets3 = offsets_above(0.5);
+
+    let origin = [x0, y0, z0, w0];
+    let corner_offset = |offsets: [F32x4; 4], unskew: f32| {
+        std::array::from_fn(|axis| origin[axis] - offsets[axis] + F32x4::splat(unskew))
+    };
+    let offset1 = corner_offset(offsets1, G4);
+    let offset2 = corner_offset(offsets2, 2.0 * G4);
+    let offset3 = corner_offset(offsets3, 3.0 * G4);
+    let offset4 = corner_offset([one; 4], 4.0 * G4);
+
+    let zero = [[0.0; LANES]; 4];
+    let corners = [
+        corner_lanes(origin, gather_gradients(perm, cells, zero)),
+        corner_lanes(
+            offset1,
+            gather_gradients(perm, cells, offsets1.map(F32x4::to_array)),
+        ),
+        corner_lanes(
+            offset2,
+            gather_gradients(perm, cells, offsets2.map(F32x4::to_array)),
+        ),
+        corner_lanes(
+            offset3,
+            gather_gradients(perm, cells, offsets3.map(F32x4::to_array)),
+        ),
+        corner_lanes(offset4, gather_gradients(perm, cells, [[1.0; LANES]; 4])),
+    ];
+    let offsets = [origin, offset1, offset2, offset3, offset4];
+
+    let noise = F32x4::splat(NOISE_SCALE)
+        * (corners[0].n + corners[1].n + corners[2].n + corners[3].n + corners[4].n);
+
+    let mut dnoise = [F32x4::splat(0.0); 3];
+    for (corner, offset) in corners.iter().zip(&offsets) {
+        let temp = corner.t2 * corner.t * corner.grad_dot;
+        for axis in 0..3 {
+            dnoise[axis] = dnoise[axis] + temp * offset[axis];
+        }
+    }
+
+    for axis in &mut dnoise {
+        *axis = *axis * F32x4::splat(-8.0);
+    }
+
+    for corner in &corners {
+        dnoise[0] = dnoise[0] + corner.t4 * corner.gx;
+        dnoise[1] = dnoise[1] + corner.t4 * corner.gy;
+        dnoise[2] = dnoise[2] + corner.t4 * corner.gz;
+    }
+
+    (noise, dnoise.map(|axis| axis * F32x4::splat(NOISE_SCALE)))
+}
+
+fn simplex_lanes(
+    x: &[f64; LANES],
+    y: &[f64; LANES],
+    z: &[f64; LANES],
+    perm: &PermutationTable,
+    w: f32,
+    out: &mut NoiseBatch,
+) {
+    let (noise, gradient) = sdnoise4_lanes(x, y, z, w as f64, perm);
+    out.values = noise.to_array();
+    out.gradients_x = gradient[0].to_array();
+    out.gradients_y = gradient[1].to_array();
+    out.gradients_z = gradient[2].to_array();
+}
+
+/// Maps a batch of noise in [-1,1] to [0,1] like `simplex401`
+fn to_unit_range(out: &mut NoiseBatch) {
+    for lane in 0..LANES {
+        out.values[lane] = (out.values[lane] + 1.0) / 2.0;
+        out.gradients_x[lane] /= 2.0;
+        out.gradients_y[lane] /= 2.0;
+        out.gradients_z[lane] /= 2.0;
+    }
+}
+
+/// `simplex411` on a batch of points given by their coordinates
+/// * `out` - The recipient for the values and the gradients (will be overridden)
+pub fn simplex411_lanes(
+    x: &[f64; LANES],
+    y: &[f64; LANES],
+    z: &[f64; LANES],
+    seed: &NoiseSeed,
+    out: &mut NoiseBatch,
+) {
+    simplex_lanes(x, y, z, seed.permutation(), seed.offset(), out);
+}
+
+/// `simplex401` on a batch of points given by their coordinates
+/// * `out` - The recipient for the values and the gradients (will be overridden)
+pub fn simplex401_lanes(
+    x: &[f64; LANES],
+    y: &[f64; LANES],
+    z: &[f64; LANES],
+    seed: &NoiseSeed,
+    out: &mut NoiseBatch,
+) {
+    simplex411_lanes(x, y, z, seed, out);
+    to_unit_range(out);
+}
+
+/// Evaluates the noise over a slice of points, `LANES` at a time
+fn simplex_batch(
+    coords: &[Vector3d],
+    seed: &NoiseSeed,
+    values: &mut [f32],
+    gradients: &mut [Vector3],
+    unit_range: bool,
+) {
+    assert_eq!(coords.len(), values.len());
+    assert_eq!(coords.len(), gradients.len());
+
+    let mut batch = NoiseBatch::zero();
+    for (start, points) in coords.chunks(LANES).enumerate() {
+        // the last batch is padded by repeating its last point
+        let lane_point = |lane: usize| &points[usize::min(lane, points.len() - 1)];
+        let x = std::array::from_fn(|lane| lane_point(lane).x);
+        let y = std::array::from_fn(|lane| lane_point(lane).y);
+        let z = std::array::from_fn(|lane| lane_point(lane).z);
+
+        simplex_lanes(&x, &y, &z, seed.permutation(), seed.offset(), &mut batch);
+        if unit_range {
+            to_unit_range(&mut batch);
+        }
+
+        for lane in 0..points.len() {
+            let index = start * LANES + lane;
+            values[index] = batch.values[lane];
+            gradients[index] = Vector3::new(
+                batch.gradients_x[lane],
+                batch.gradients_y[lane],
+                batch.gradients_z[lane],
+            );
+        }
+    }
+}
+
+/// `simplex411` on a slice of points
+/// * `values` - The recipient for the values (will be overridden)
+/// * `gradients` - The recipient for the gradients (will be overridden)
+pub fn simplex411_batch(
+    coords: &[Vector3d],
+    seed: &NoiseSeed,
+    values: &mut [f32],
+    gradients: &mut [Vector3],
+) {
+    simplex_batch(coords, seed, values, gradients, false);
+}
+
+/// `simplex401` on a slice of points
+/// * `values` - The recipient for the values (will be overridden)
+/// * `gradients` - The recipient for the gradients (will be overridden)
+pub fn simplex401_batch(
+    coords: &[Vector3d],
+    seed: &NoiseSeed,
+    values: &mut [f32],
+    gradients: &mut [Vector3],
+) {
+    simplex_batch(coords, seed, values, gradients, true);
+}
+
+/// The values and gradients of octaves of noise, indexed by octave then by point
+pub(crate) type OctaveBatches = Vec<(Vec<f32>, Vec<Vector3>)>;
+
+/// Samples a batched noise at the given frequencies over a row of points
+/// * `noise` - Writes the values and the gradients of the noise at a row of scaled points, the gradients are given zeroed
+pub(crate) fn sample_octaves(
+    coords: &[Vector3d],
+    frequencies: impl Iterator<Item = f32>,
+    noise: impl Fn(&[Vector3d], &mut [f32], &mut [Vector3]),
+) -> OctaveBatches {
+    frequencies
+        .map(|frequency| {
+            let scaled_coords: Vec<Vector3d> = coords
+                .iter()
+                .map(|coords| coords * frequency as f64)
+                .collect();
+            let mut values = vec![0.0; coords.len()];
+            let mut gradients = vec![Vector3::zero(); coords.len()];
+            noise(&scaled_coords, &mut values, &mut gradients);
+            (values, gradients)
+        })
+        .collect()
+}
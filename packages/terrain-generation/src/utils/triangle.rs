@@ -1,3 +1,4 @@
+use crate::chunk_layout::SCATTER_STRIDE;
 use crate::utils::random::random01;
 
 pub struct TriangleSurface {
@@ -48,9 +49,10 @@ pub fn random_point_in_triangle(
     x3: f32,
     y3: f32,
     z3: f32,
+    random_state: &mut u64,
 ) -> [f32; 3] {
-    let r1 = random01();
-    let r2 = random01();
+    let r1 = random01(random_state);
+    let r2 = random01(random_state);
 
     let r1_sqrt = r1.sqrt();
     let f1 = 1.0 - r1_sqrt;
@@ -69,6 +71,7 @@ pub fn scatter_in_triangle(
     scatter_per_square_meter: f32,
     excess_instance_number: &mut f32,
     instance_index: &mut usize,
+    random_state: &mut u64,
     scattered_points_buffer: &mut [f32],
     positions: &[f32],
     index1: usize,
@@ -96,14 +99,12 @@ pub fn scatter_in_triangle(
     let [nx, ny, nz] = normal;
 
     for _ in 0..nb_instances {
-        let [x, y, z] = random_point_in_triangle(x1, y1, z1, x2, y2, z2, x3, y3, z3);
-        let offset = 6 * *instance_index;
-        scattered_points_buffer[offset] = x;
-        scattered_points_buffer[offset + 1] = y;
-        scattered_points_buffer[offset + 2] = z;
-        scattered_points_buffer[offset + 3] = -nx;
-        scattered_points_buffer[offset + 4] = -ny;
-        scattered_points_buffer[offset + 5] = -nz;
+        let [x, y, z] = random_point_in_triangle(x1, y1, z1, x2, y2, z2, x3, y3, z3, random_state);
+        let offset = SCATTER_STRIDE * *instance_index;
+        // the instances that do not fit are still counted so that the caller can report the overflow
+        if let Some(instance) = scattered_points_buffer.get_mut(offset..offset + SCATTER_STRIDE) {
+            instance.copy_from_slice(&[x, y, z, -nx, -ny, -nz]);
+        }
 
         *instance_index += 1;
     }
@@ -0,0 +1,113 @@
+use crate::utils::vector3::Vector3;
+use std::ops;
+
+/// A double precision vector for the absolute positions on the planet.
+/// At planetary scales, f32 positions are only precise to a few centimeters, so the positions are assembled in f64
+/// and only narrowed to f32 once they are made relative to their chunk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vector3d {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vector3d {
+    pub fn new(x: f64, y: f64, z: f64) -> Vector3d {
+        Vector3d { x, y, z }
+    }
+
+    pub fn zero() -> Vector3d {
+        Vector3d::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn get_squared_magnitude(&self) -> f64 {
+        Vector3d::dot(self, self)
+    }
+
+    pub fn length(&self) -> f64 {
+        f64::sqrt(self.get_squared_magnitude())
+    }
+
+    pub fn normalize_to_new(&self) -> Vector3d {
+        self / self.length()
+    }
+
+    pub fn dot(v1: &Vector3d, v2: &Vector3d) -> f64 {
+        v1.x * v2.x + v1.y * v2.y + v1.z * v2.z
+    }
+
+    /// Narrows the vector to single precision
+    pub fn to_f32(&self) -> Vector3 {
+        Vector3::new(self.x as f32, self.y as f32, self.z as f32)
+    }
+}
+
+impl From<&Vector3> for Vector3d {
+    fn from(vector: &Vector3) -> Self {
+        Vector3d::new(vector.x as f64, vector.y as f64, vector.z as f64)
+    }
+}
+
+impl ops::Add<&Vector3d> for &Vector3d {
+    type Output = Vector3d;
+
+    fn add(self, rhs: &Vector3d) -> Self::Output {
+        Vector3d {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl ops::AddAssign<&Vector3d> for Vector3d {
+    fn add_assign(&mut self, rhs: &Vector3d) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z;
+    }
+}
+
+impl ops::Sub<&Vector3d> for &Vector3d {
+    type Output = Vector3d;
+
+    fn sub(self, rhs: &Vector3d) -> Self::Output {
+        Vector3d {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl ops::Mul<f64> for &Vector3d {
+    type Output = Vector3d;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Vector3d {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+impl ops::MulAssign<f64> for Vector3d {
+    fn mul_assign(&mut self, rhs: f64) {
+        self.x *= rhs;
+        self.y *= rhs;
+        self.z *= rhs;
+    }
+}
+
+impl ops::Div<f64> for &Vector3d {
+    type Output = Vector3d;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        Vector3d {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+        }
+    }
+}
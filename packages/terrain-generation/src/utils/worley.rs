@@ -0,0 +1,200 @@
+/*
+ * Cellular noise (Steven Worley, A Cellular Texture Basis Function, 1996) with analytic derivatives in 3D and 4D.
+ *
+ * Each integer cell holds one feature point jittered inside of it. The noise returns the distances to the closest (F1)
+ * and second closest (F2) feature points, searched in the neighbouring cells only.
+ * Like the simplex functions, the 4D version uses its 4th coordinate as a seed, or salts its hash with integer seeds.
+ */
+
+use crate::seed::{Seed, SeedKind};
+use crate::utils::random::pcg3d;
+use crate::utils::vector3::Vector3;
+
+/// The feature of the cellular noise to return
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorleyFeature {
+    /// The distance to the closest feature point
+    F1,
+    /// The distance to the second closest feature point
+    F2,
+    /// The difference between F2 and F1, which is 0 on the boundaries of the cells
+    F2MinusF1,
+}
+
+/// Random values in [0, 1] for the 4D integer cell, the last one being used as the identifier of the cell
+fn hash(i: i32, j: i32, k: i32, l: i32, salt: u32) -> ([f32; 4], u32) {
+    let l = l as u32 ^ salt;
+    let (seed_x, seed_y, seed_z) = pcg3d(l, l, l);
+    let (a, b, c) = pcg3d(
+        (i as u32) ^ seed_x,
+        (j as u32) ^ seed_y,
+        (k as u32) ^ seed_z,
+    );
+    let (d, _, _) = pcg3d(c, a, b);
+
+    ([a, b, c, d].map(|x| x as f32 / u32::MAX as f32), d)
+}
+
+/// Keeps track of the two closest feature points and of the offsets toward them
+struct ClosestPoints {
+    f1: f32,
+    f2: f32,
+    offset1: Vector3,
+    offset2: Vector3,
+    cell_id: u32,
+}
+
+impl ClosestPoints {
+    fn new() -> Self {
+        Self {
+            f1: f32::MAX,
+            f2: f32::MAX,
+            offset1: Vector3::zero(),
+            offset2: Vector3::zero(),
+            cell_id: 0,
+        }
+    }
+
+    /// Inserts a feature point given the offset from it to the sample point and the squared distance along the 4th dimension
+    fn insert(&mut self, offset: Vector3, squared_w: f32, cell_id: u32) {
+        let distance = f32::sqrt(offset.get_squared_magnitude() + squared_w);
+        if distance < self.f1 {
+            self.f2 = self.f1;
+            self.offset2 = std::mem::replace(&mut self.offset1, offset);
+            self.f1 = distance;
+            self.cell_id = cell_id;
+        } else if distance < self.f2 {
+            self.f2 = distance;
+            self.offset2 = offset;
+        }
+    }
+
+    /// The gradient of a distance is the unit vector pointing away from the feature point
+    fn distance_gradient(offset: &Vector3, distance: f32) -> Vector3 {
+        if distance > 0.0 {
+            offset / distance
+        } else {
+            Vector3::zero()
+        }
+    }
+
+    fn feature(&self, feature: WorleyFeature, gradient: &mut Vector3) -> f32 {
+        let f1_gradient = Self::distance_gradient(&self.offset1, self.f1);
+        let f2_gradient = Self::distance_gradient(&self.offset2, self.f2);
+        match feature {
+            WorleyFeature::F1 => {
+                *gradient = f1_gradient;
+                self.f1
+            }
+            WorleyFeature::F2 => {
+                *gradient = f2_gradient;
+                self.f2
+            }
+            WorleyFeature::F2MinusF1 => {
+                *gradient = f2_gradient - f1_gradient;
+                self.f2 - self.f1
+            }
+        }
+    }
+}
+
+/// Finds the two closest feature points of the 3D cellular noise
+fn sdworley3(x: f32, y: f32, z: f32) -> ClosestPoints {
+    let (cell_x, cell_y, cell_z) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+
+    let mut closest = ClosestPoints::new();
+    for i in cell_x - 1..=cell_x + 1 {
+        for j in cell_y - 1..=cell_y + 1 {
+            for k in cell_z - 1..=cell_z + 1 {
+                let (random, cell_id) = hash(i, j, k, 0, 0);
+                let offset = Vector3::new(
+                    x - (i as f32 + random[0]),
+                    y - (j as f32 + random[1]),
+                    z - (k as f32 + random[2]),
+                );
+                closest.insert(offset, 0.0, cell_id);
+            }
+        }
+    }
+
+    closest
+}
+
+/// Finds the two closest feature points of the 4D cellular noise
+fn sdworley4(x: f32, y: f32, z: f32, seed: Seed) -> ClosestPoints {
+    let w = seed.offset();
+    let salt = match seed.kind() {
+        SeedKind::Offset(_) => 0,
+        SeedKind::Integer(_) => seed.bits(),
+    };
+
+    let (cell_x, cell_y, cell_z, cell_w) = (
+        x.floor() as i32,
+        y.floor() as i32,
+        z.floor() as i32,
+        w.floor() as i32,
+    );
+
+    let mut closest = ClosestPoints::new();
+    for l in cell_w - 1..=cell_w + 1 {
+        for i in cell_x - 1..=cell_x + 1 {
+            for j in cell_y - 1..=cell_y + 1 {
+                for k in cell_z - 1..=cell_z + 1 {
+                    let (random, cell_id) = hash(i, j, k, l, salt);
+                    let offset = Vector3::new(
+                        x - (i as f32 + random[0]),
+                        y - (j as f32 + random[1]),
+                        z - (k as f32 + random[2]),
+                    );
+                    let w_offset = w - (l as f32 + random[3]);
+                    closest.insert(offset, w_offset * w_offset, cell_id);
+                }
+            }
+        }
+    }
+
+    closest
+}
+
+/**
+ * Returns a feature of the 3D cellular noise and computes its gradient
+ * @param vector the position to sample the noise at
+ * @param feature the feature to return
+ * @param gradient the recipient for the gradient (will be overridden)
+ */
+pub fn worley3(vector: &Vector3, feature: WorleyFeature, gradient: &mut Vector3) -> f32 {
+    sdworley3(vector.x, vector.y, vector.z).feature(feature, gradient)
+}
+
+/**
+ * Returns a feature of the 4D cellular noise and computes its gradient
+ * @param vector the position to sample the noise at
+ * @param seed the seed salting the cells or offsetting them along the 4th dimension
+ * @param feature the feature to return
+ * @param gradient the recipient for the gradient (will be overridden)
+ */
+pub fn worley4(
+    vector: &Vector3,
+    seed: Seed,
+    feature: WorleyFeature,
+    gradient: &mut Vector3,
+) -> f32 {
+    sdworley4(vector.x, vector.y, vector.z, seed).feature(feature, gradient)
+}
+
+/**
+ * Returns the identifier of the cell holding the closest feature point of the 3D cellular noise
+ * @param vector the position to sample the noise at
+ */
+pub fn worley3_cell_id(vector: &Vector3) -> u32 {
+    sdworley3(vector.x, vector.y, vector.z).cell_id
+}
+
+/**
+ * Returns the identifier of the cell holding the closest feature point of the 4D cellular noise
+ * @param vector the position to sample the noise at
+ * @param seed the seed salting the cells or offsetting them along the 4th dimension
+ */
+pub fn worley4_cell_id(vector: &Vector3, seed: Seed) -> u32 {
+    sdworley4(vector.x, vector.y, vector.z, seed).cell_id
+}
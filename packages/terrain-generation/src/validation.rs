@@ -0,0 +1,205 @@
+use std::fmt;
+
+/// The deepest chunk of the quadtrees, whose size is the planet diameter divided by 2^31
+pub const MAX_CHUNK_DEPTH: u32 = 31;
+/// The smallest resolution of a chunk, which has at least one quad
+pub const MIN_RESOLUTION: u32 = 2;
+/// The largest resolution of a chunk whose vertices (skirt included) can be indexed with 16 bits
+pub const MAX_RESOLUTION: u32 = 254;
+/// The most radial levels sampled by a volumetric chunk, whose every column is sampled at every level
+pub const MAX_RADIAL_LEVELS: usize = 512;
+
+/// A constraint violated by the terrain settings or the build data of a chunk
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// A frequency is negative or not finite
+    InvalidFrequency { field: &'static str, value: f32 },
+    /// A height, a depth, a weight or a temperature is not finite
+    InvalidValue { field: &'static str, value: f32 },
+    /// A width is not strictly positive
+    InvalidWidth { field: &'static str, value: f32 },
+    /// The number of terrace steps is not strictly positive
+    InvalidStepCount(f32),
+    /// The fragmentation of the continents is outside of ]0, 1]
+    InvalidFragmentation(f32),
+    /// A semi-axis of the base shape is not strictly positive
+    InvalidBaseShapeScale { field: &'static str, value: f32 },
+    /// The amplitude of the lumps of the base shape is outside of [0, 1[
+    InvalidLumpAmplitude(f32),
+    /// The size of the caves is not strictly positive
+    InvalidCaveSize(f32),
+    /// The planet diameter is not strictly positive
+    InvalidPlanetDiameter(f32),
+    /// A coordinate of the position of the chunk on the cube sphere is not finite
+    InvalidChunkPosition,
+    /// The resolution is below `MIN_RESOLUTION`
+    ResolutionTooLow(u32),
+    /// The resolution is above `MAX_RESOLUTION`
+    ResolutionTooHigh(u32),
+    /// The depth of the chunk is above `MAX_CHUNK_DEPTH`
+    ChunkDepthTooLarge(u32),
+    /// The offset of the seed is not finite
+    InvalidSeed(f32),
+    /// The caves or the relief of a volumetric chunk span more than `MAX_RADIAL_LEVELS` radial levels
+    TooManyRadialLevels(usize),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::InvalidFrequency { field, value } => {
+                write!(f, "{field} must be finite and non-negative, got {value}")
+            }
+            ValidationError::InvalidValue { field, value } => {
+                write!(f, "{field} must be finite, got {value}")
+            }
+            ValidationError::InvalidWidth { field, value } => {
+                write!(f, "{field} must be strictly positive, got {value}")
+            }
+            ValidationError::InvalidStepCount(value) => {
+                write!(f, "terrace_steps must be strictly positive, got {value}")
+            }
+            ValidationError::InvalidFragmentation(value) => {
+                write!(f, "continents_fragmentation must be in ]0, 1], got {value}")
+            }
+            ValidationError::InvalidBaseShapeScale { field, value } => {
+                write!(
+                    f,
+                    "base_shape.{field} must be strictly positive, got {value}"
+                )
+            }
+            ValidationError::InvalidLumpAmplitude(value) => {
+                write!(
+                    f,
+                    "base_shape.lump_amplitude must be in [0, 1[, got {value}"
+                )
+            }
+            ValidationError::InvalidCaveSize(value) => {
+                write!(f, "caves.size must be strictly positive, got {value}")
+            }
+            ValidationError::InvalidPlanetDiameter(value) => {
+                write!(f, "planet_diameter must be strictly positive, got {value}")
+            }
+            ValidationError::InvalidChunkPosition => {
+                write!(
+                    f,
+                    "the position of the chunk on the cube sphere must be finite"
+                )
+            }
+            ValidationError::ResolutionTooLow(value) => {
+                write!(
+                    f,
+                    "resolution must be at least {MIN_RESOLUTION}, got {value}"
+                )
+            }
+            ValidationError::ResolutionTooHigh(value) => {
+                write!(
+                    f,
+                    "resolution must be at most {MAX_RESOLUTION}, got {value}"
+                )
+            }
+            ValidationError::ChunkDepthTooLarge(value) => {
+                write!(
+                    f,
+                    "chunk_depth must be at most {MAX_CHUNK_DEPTH}, got {value}"
+                )
+            }
+            ValidationError::InvalidSeed(value) => {
+                write!(f, "the seed must be finite, got {value}")
+            }
+            ValidationError::TooManyRadialLevels(value) => {
+                write!(
+                    f,
+                    "a volumetric chunk must span at most {MAX_RADIAL_LEVELS} radial levels, got {value}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Every constraint violated by the inputs of a build
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    /// Fails with the given violations if there is any
+    pub(crate) fn check(errors: Vec<ValidationError>) -> Result<(), ValidationErrors> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid terrain inputs: ")?;
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Why a chunk could not be built
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildError {
+    InvalidData(ValidationErrors),
+    /// The position, normal and index buffers match neither the layout with a skirt nor the one without
+    InvalidBufferSizes {
+        positions: usize,
+        normals: usize,
+        indices: usize,
+    },
+    InvalidBiomeBufferSize {
+        length: usize,
+        expected: usize,
+    },
+    InvalidSplatWeightBufferSize {
+        length: usize,
+        expected: usize,
+    },
+    /// The scatter buffer cannot hold all the instances of the chunk
+    TooManyInstances {
+        nb_instances: usize,
+        capacity: usize,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidData(errors) => write!(f, "{errors}"),
+            BuildError::InvalidBufferSizes {
+                positions,
+                normals,
+                indices,
+            } => write!(
+                f,
+                "Invalid chunk buffer sizes: positions={positions}, normals={normals}, indices={indices}"
+            ),
+            BuildError::InvalidBiomeBufferSize { length, expected } => write!(
+                f,
+                "Invalid biome buffer size: biomes={length}, expected={expected}"
+            ),
+            BuildError::InvalidSplatWeightBufferSize { length, expected } => write!(
+                f,
+                "Invalid splat weight buffer size: splat_weights={length}, expected={expected}"
+            ),
+            BuildError::TooManyInstances {
+                nb_instances,
+                capacity,
+            } => write!(f, "Too many instances: {nb_instances} > {capacity}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,433 @@
+use crate::build_data::BuildData;
+use crate::chunk_plane_position;
+use crate::erosion_bake::erosion_bake;
+use crate::parallel::map_rows;
+use crate::seed::Salt;
+use crate::surface::Surface;
+use crate::utils::simplex::simplex401;
+use crate::utils::vector3::Vector3;
+use crate::utils::vector3d::Vector3d;
+use crate::validation::{ValidationError, ValidationErrors, MAX_RADIAL_LEVELS};
+use crate::with_terrain_function;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// The number of extra radial levels kept above and below the surface
+const RADIAL_MARGIN: i64 = 2;
+
+/// The corners of a cell as offsets along the two grid axes and the radial axis
+const CELL_CORNERS: [(usize, usize, usize); 8] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (0, 1, 1),
+    (1, 1, 1),
+];
+
+/// The edges of a cell as pairs of corner indices
+const CELL_EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[wasm_bindgen]
+pub struct CaveSettings {
+    /// The typical width of the caves in meters
+    pub size: f32,
+    /// The fraction of the noise range that stays solid, in [0, 1] (higher values give fewer caves)
+    pub threshold: f32,
+    /// The depth below the surface down to which caves are carved (0 disables the caves)
+    pub depth: f32,
+}
+
+impl CaveSettings {
+    pub const NONE: CaveSettings = CaveSettings {
+        size: 50.0,
+        threshold: 0.75,
+        depth: 0.0,
+    };
+}
+
+#[wasm_bindgen]
+impl CaveSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CaveSettings {
+        CaveSettings::default()
+    }
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        CaveSettings::NONE
+    }
+}
+
+/// The mesh of a volumetric chunk, with positions relative to the chunk like the heightfield chunks
+#[wasm_bindgen]
+pub struct VolumetricChunk {
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl VolumetricChunk {
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    pub fn normals(&self) -> Vec<f32> {
+        self.normals.clone()
+    }
+
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    pub fn nb_vertices(&self) -> usize {
+        self.positions.len() / 3
+    }
+}
+
+/// A signed distance-like field: positive inside the ground, negative in the air and the caves
+struct Density<'a> {
+    surface: Surface<'a>,
+    caves: CaveSettings,
+    vertex_spacing: f32,
+}
+
+impl Density<'_> {
+    /// Carves the caves into the ground below the surface
+    /// * `position` - The position in planet space
+    /// * `surface_density` - The signed height of the surface above the position
+    /// * `gradient` - The gradient of the surface density WILL STORE THE RESULT
+    fn carve(&self, position: &Vector3d, surface_density: f32, gradient: &mut Vector3) -> f32 {
+        if self.caves.depth <= 0.0 {
+            return surface_density;
+        }
+
+        let mut noise_gradient = Vector3::zero();
+        let noise = simplex401(
+            &(position / self.caves.size as f64),
+            self.surface.seed.derive(Salt::Caves),
+            &mut noise_gradient,
+        );
+
+        // the caves are where the noise exceeds the threshold, the size cancels out in the gradient
+        let mut cave_density = (self.caves.threshold - noise) * self.caves.size;
+        let mut cave_gradient = noise_gradient * -1.0;
+
+        // the ground stays solid deeper than the caves
+        let floor_density = surface_density - self.caves.depth;
+        if floor_density > cave_density {
+            cave_density = floor_density;
+            cave_gradient = gradient.clone();
+        }
+
+        if cave_density < surface_density {
+            *gradient = cave_gradient;
+            return cave_density;
+        }
+        surface_density
+    }
+
+    /// Samples the density and its gradient at a position in planet space
+    fn sample(&self, position: &Vector3d, gradient: &mut Vector3) -> f32 {
+        let distance = position.length();
+        let unit_sphere_direction = position / distance;
+
+        let mut surface_gradient = Vector3::zero();
+        let (elevation, base_radius) = self.surface.elevation(
+            &unit_sphere_direction,
+            self.vertex_spacing,
+            &mut surface_gradient,
+        );
+        let surface_density = (base_radius - distance) as f32 + elevation;
+
+        // only the tangential part of the gradient on the unit sphere moves the surface
+        let unit_sphere_coords = unit_sphere_direction.to_f32();
+        let tangent_gradient = &surface_gradient
+            - &(&unit_sphere_coords * Vector3::dot(&surface_gradient, &unit_sphere_coords));
+        *gradient = tangent_gradient / distance as f32 - unit_sphere_coords;
+
+        self.carve(position, surface_density, gradient)
+    }
+}
+
+/// Builds the mesh of a chunk from a 3D density field, see `VolumetricChunk::build`
+/// * `data` - The data needed to guide the build process
+///
+/// Throws when the data is invalid (see `VolumetricChunk::build`)
+#[wasm_bindgen]
+pub fn build_volumetric_chunk(data: &BuildData) -> Result<VolumetricChunk, JsError> {
+    Ok(VolumetricChunk::build(data)?)
+}
+
+impl VolumetricChunk {
+    /// Builds the mesh of a chunk from a 3D density field, allowing caves, arches and overhangs.
+    /// The volume is sampled on a grid following the chunk in the tangent directions and the radius,
+    /// and polygonized with dual contouring (one vertex per cell placed at the mean of its edge crossings).
+    /// Neighbouring chunks of the same depth share the same lattice so that their meshes connect.
+    /// * `data` - The data needed to guide the build process
+    ///
+    /// Fails when the data is invalid (see `BuildData::validate_volumetric`)
+    /// or when the relief of the chunk spans more than `MAX_RADIAL_LEVELS` radial levels
+    pub fn build(data: &BuildData) -> Result<VolumetricChunk, ValidationErrors> {
+        data.validate_volumetric()?;
+
+        let seed = data.planet_seed;
+        let planet_radius = data.planet_diameter / 2.0;
+        let chunk_cube_position = Vector3d::new(
+            data.chunk_cube_position_x as f64,
+            data.chunk_cube_position_y as f64,
+            data.chunk_cube_position_z as f64,
+        );
+
+        let nb_vertices_per_row = data.resolution as usize;
+        let nb_subdivisions = nb_vertices_per_row - 1;
+        let spacing = data.spacing();
+        let vertex_spacing = spacing / planet_radius;
+
+        // the offset used to bring back the vertices close to the origin (the position of the chunk on the sphere)
+        let chunk_sphere_position = &chunk_cube_position.normalize_to_new() * planet_radius as f64;
+
+        let erosion = erosion_bake(seed, &data.terrain_settings, planet_radius);
+
+        with_terrain_function(
+            seed,
+            data.terrain_settings,
+            None,
+            |terrain_function, noise_seed| {
+                let density = Density {
+                    surface: Surface {
+                        terrain_function,
+                        base_shape: data.terrain_settings.base_shape,
+                        erosion: erosion.as_deref(),
+                        seed: noise_seed,
+                        planet_radius,
+                    },
+                    caves: data.terrain_settings.caves,
+                    vertex_spacing,
+                };
+
+                // the columns include one row of padding before the chunk so that the cells on its border can be closed
+                let nb_columns = nb_vertices_per_row + 1;
+                let column_rows = map_rows(nb_columns, |i| {
+                    let row_directions: Vec<Vector3d> = (0..nb_columns)
+                        .map(|j| {
+                            let plane_position = chunk_plane_position(
+                                data.chunk_tree_direction,
+                                i as f32 - 1.0,
+                                j as f32 - 1.0,
+                                nb_subdivisions,
+                            );
+                            let cube_position = &(&Vector3d::from(&plane_position)
+                                * spacing as f64)
+                                + &chunk_cube_position;
+                            cube_position.normalize_to_new()
+                        })
+                        .collect();
+
+                    let row_surface_radii: Vec<f64> = density
+                        .surface
+                        .elevations(
+                            &row_directions,
+                            vertex_spacing,
+                            &mut vec![Vector3::zero(); nb_columns],
+                        )
+                        .into_iter()
+                        .map(|(elevation, base_radius)| base_radius + elevation as f64)
+                        .collect();
+                    (row_directions, row_surface_radii)
+                });
+                let (directions, surface_radii): (Vec<Vector3d>, Vec<f64>) = column_rows
+                    .into_iter()
+                    .flat_map(|(row_directions, row_surface_radii)| {
+                        row_directions.into_iter().zip(row_surface_radii)
+                    })
+                    .unzip();
+
+                // the radial levels lie on a lattice shared by all the chunks of the same depth
+                let spacing = spacing as f64;
+                let min_radius = surface_radii.iter().cloned().fold(f64::MAX, f64::min)
+                    - f32::max(density.caves.depth, 0.0) as f64;
+                let max_radius = surface_radii.iter().cloned().fold(f64::MIN, f64::max);
+                let first_level = (min_radius / spacing).floor() as i64 - RADIAL_MARGIN;
+                let last_level = (max_radius / spacing).ceil() as i64 + RADIAL_MARGIN;
+                let nb_levels = (last_level - first_level + 1) as usize;
+                if nb_levels > MAX_RADIAL_LEVELS {
+                    return Err(ValidationErrors(vec![
+                        ValidationError::TooManyRadialLevels(nb_levels),
+                    ]));
+                }
+
+                let point_index =
+                    |i: usize, j: usize, k: usize| (i * nb_columns + j) * nb_levels + k;
+                let columns = map_rows(nb_columns * nb_columns, |column| {
+                    (0..nb_levels)
+                        .map(|k| {
+                            let radius = (first_level + k as i64) as f64 * spacing;
+                            let point = &directions[column] * radius;
+                            let mut surface_gradient = Vector3::zero();
+                            let point_density = density.carve(
+                                &point,
+                                (surface_radii[column] - radius) as f32,
+                                &mut surface_gradient,
+                            );
+                            (point, point_density)
+                        })
+                        .collect::<Vec<_>>()
+                });
+                let (points, densities): (Vec<Vector3d>, Vec<f32>) =
+                    columns.into_iter().flatten().unzip();
+
+                // one vertex per cell crossed by the surface
+                let nb_cells = nb_columns - 1;
+                let nb_cell_levels = nb_levels - 1;
+                let cell_index =
+                    |i: usize, j: usize, k: usize| (i * nb_cells + j) * nb_cell_levels + k;
+                let mut cell_vertices = vec![u32::MAX; nb_cells * nb_cells * nb_cell_levels];
+                let mut positions = Vec::new();
+                let mut normals = Vec::new();
+                for i in 0..nb_cells {
+                    for j in 0..nb_cells {
+                        for k in 0..nb_cell_levels {
+                            let corners = CELL_CORNERS
+                                .map(|(di, dj, dk)| point_index(i + di, j + dj, k + dk));
+
+                            let mut vertex = Vector3d::zero();
+                            let mut nb_crossings = 0;
+                            for (a, b) in CELL_EDGES {
+                                let (density_a, density_b) =
+                                    (densities[corners[a]], densities[corners[b]]);
+                                if (density_a > 0.0) == (density_b > 0.0) {
+                                    continue;
+                                }
+                                let t = (density_a / (density_a - density_b)) as f64;
+                                vertex += &(&points[corners[a]]
+                                    + &(&(&points[corners[b]] - &points[corners[a]]) * t));
+                                nb_crossings += 1;
+                            }
+                            if nb_crossings == 0 {
+                                continue;
+                            }
+                            let vertex = &vertex / nb_crossings as f64;
+
+                            // the normal points away from the ground
+                            let mut density_gradient = Vector3::zero();
+                            density.sample(&vertex, &mut density_gradient);
+                            let normal = (density_gradient * -1.0).normalize_to_new();
+
+                            let relative_position = (&vertex - &chunk_sphere_position).to_f32();
+                            cell_vertices[cell_index(i, j, k)] = (positions.len() / 3) as u32;
+                            positions.extend([
+                                relative_position.x,
+                                relative_position.y,
+                                relative_position.z,
+                            ]);
+                            normals.extend([normal.x, normal.y, normal.z]);
+                        }
+                    }
+                }
+
+                // one quad per edge crossed by the surface around which all the cells exist.
+                // The edges on the far borders belong to the next chunk to avoid duplicated faces.
+                // the handedness of the lattice: whether the tangent axes and the radius form a direct basis
+                let origin =
+                    chunk_plane_position(data.chunk_tree_direction, 0.0, 0.0, nb_subdivisions);
+                let axis_i =
+                    chunk_plane_position(data.chunk_tree_direction, 1.0, 0.0, nb_subdivisions)
+                        - origin.clone();
+                let axis_j =
+                    chunk_plane_position(data.chunk_tree_direction, 0.0, 1.0, nb_subdivisions)
+                        - origin.clone();
+                let is_direct = Vector3::dot(
+                    &Vector3::cross(&axis_i, &axis_j),
+                    &chunk_cube_position.to_f32(),
+                ) > 0.0;
+
+                // the cells around each edge are listed counterclockwise around its axis in a direct basis,
+                // except around the second tangent axis where the order is reversed
+                let mut indices = Vec::new();
+                let mut emit_quad = |axis: usize, start_is_inside: bool, cells: [usize; 4]| {
+                    let quad = cells.map(|cell| cell_vertices[cell]);
+                    let faces_outward = (axis == 1) != is_direct;
+                    let faces_outward = faces_outward == start_is_inside;
+
+                    // the heightfield chunks are wound clockwise when seen from outside the ground
+                    if faces_outward {
+                        indices.extend([quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                    } else {
+                        indices.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                    }
+                };
+
+                for i in 1..nb_columns - 1 {
+                    for j in 1..nb_columns - 1 {
+                        for k in 0..nb_levels {
+                            let start = point_index(i, j, k);
+                            let axes = [
+                                (k >= 1 && k < nb_cell_levels).then(|| point_index(i + 1, j, k)),
+                                (k >= 1 && k < nb_cell_levels).then(|| point_index(i, j + 1, k)),
+                                (k + 1 < nb_levels).then(|| point_index(i, j, k + 1)),
+                            ];
+
+                            for (axis, end) in axes.into_iter().enumerate() {
+                                let Some(end) = end else {
+                                    continue;
+                                };
+                                if (densities[start] > 0.0) == (densities[end] > 0.0) {
+                                    continue;
+                                }
+                                // the grid point (i, j, k) is the upper corner of the cell (i - 1, j - 1, k - 1)
+                                let (ci, cj) = (i - 1, j - 1);
+                                let cells = match axis {
+                                    0 => [
+                                        cell_index(ci + 1, cj, k - 1),
+                                        cell_index(ci + 1, cj + 1, k - 1),
+                                        cell_index(ci + 1, cj + 1, k),
+                                        cell_index(ci + 1, cj, k),
+                                    ],
+                                    1 => [
+                                        cell_index(ci, cj + 1, k - 1),
+                                        cell_index(ci + 1, cj + 1, k - 1),
+                                        cell_index(ci + 1, cj + 1, k),
+                                        cell_index(ci, cj + 1, k),
+                                    ],
+                                    _ => [
+                                        cell_index(ci, cj, k),
+                                        cell_index(ci + 1, cj, k),
+                                        cell_index(ci + 1, cj + 1, k),
+                                        cell_index(ci, cj + 1, k),
+                                    ],
+                                };
+                                emit_quad(axis, densities[start] > 0.0, cells);
+                            }
+                        }
+                    }
+                }
+
+                Ok(VolumetricChunk {
+                    positions,
+                    normals,
+                    indices,
+                })
+            },
+        )
+    }
+}
@@ -23,6 +23,7 @@ fn scattered_points_buffer_interleaves_positions_and_normals() {
         10.0,
         &mut excess_instance_number,
         &mut instance_index,
+        &mut 42,
         &mut scattered_points_buffer,
         &positions,
         0,
@@ -0,0 +1,178 @@
+use terrain_generation::base_shape::BaseShape;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+
+mod common;
+
+use common::*;
+
+/// The gradient of the radius along the surface of the unit sphere
+fn tangent_numerical_gradient(shape: &BaseShape, point: &Vector3, epsilon: f32) -> Vector3 {
+    let derivatives = [
+        Vector3::new(epsilon, 0.0, 0.0),
+        Vector3::new(0.0, epsilon, 0.0),
+        Vector3::new(0.0, 0.0, epsilon),
+    ]
+    .iter()
+    .map(|offset| {
+        let forward = shape.radius(
+            &(point + offset).normalize_to_new(),
+            &NOISE_SEED,
+            &mut Vector3::zero(),
+        );
+        let backward = shape.radius(
+            &(point - offset).normalize_to_new(),
+            &NOISE_SEED,
+            &mut Vector3::zero(),
+        );
+        (forward - backward) / (2.0 * epsilon)
+    })
+    .collect::<Vec<f32>>();
+    Vector3::new(derivatives[0], derivatives[1], derivatives[2])
+}
+
+#[test]
+fn spheres_have_a_constant_radius() {
+    let shape = BaseShape::sphere();
+    for point in fibonacci_sphere(100) {
+        let mut gradient = Vector3::zero();
+        assert!((shape.radius(&point, &NOISE_SEED, &mut gradient) - 1.0).abs() < 1e-6);
+
+        let tangent_gradient = &gradient - &(&point * Vector3::dot(&gradient, &point));
+        assert!(tangent_gradient.length() < 1e-5);
+    }
+}
+
+#[test]
+fn ellipsoids_reach_their_semi_axes() {
+    let shape = BaseShape::ellipsoid(1.2, 0.8, 0.5);
+    let radius = |point: Vector3| shape.radius(&point, &NOISE_SEED, &mut Vector3::zero());
+
+    assert!((radius(Vector3::new(1.0, 0.0, 0.0)) - 1.2).abs() < 1e-6);
+    assert!((radius(Vector3::new(0.0, -1.0, 0.0)) - 0.8).abs() < 1e-6);
+    assert!((radius(Vector3::new(0.0, 0.0, 1.0)) - 0.5).abs() < 1e-6);
+
+    let oblate = BaseShape::oblate(0.1);
+    let polar_radius = oblate.radius(
+        &Vector3::new(0.0, 1.0, 0.0),
+        &NOISE_SEED,
+        &mut Vector3::zero(),
+    );
+    assert!((polar_radius - 0.9).abs() < 1e-6);
+}
+
+#[test]
+fn ellipsoid_gradient_matches_finite_differences() {
+    let shape = BaseShape::ellipsoid(1.2, 0.8, 0.5);
+
+    for point in fibonacci_sphere(300) {
+        let mut gradient = Vector3::zero();
+        shape.radius(&point, &NOISE_SEED, &mut gradient);
+        let tangent_gradient = &gradient - &(&point * Vector3::dot(&gradient, &point));
+
+        let numerical = tangent_numerical_gradient(&shape, &point, 1e-3);
+        assert!(
+            (&tangent_gradient - &numerical).length() <= 1e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            tangent_gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn lumps_stay_within_their_amplitude() {
+    let shape = BaseShape::lumpy(0.3, 2.0);
+
+    let radii = fibonacci_sphere(500)
+        .iter()
+        .map(|point| shape.radius(point, &NOISE_SEED, &mut Vector3::zero()))
+        .collect::<Vec<f32>>();
+    assert!(radii.iter().all(|radius| (0.7..=1.3).contains(radius)));
+
+    let min = radii.iter().cloned().fold(f32::MAX, f32::min);
+    let max = radii.iter().cloned().fold(f32::MIN, f32::max);
+    assert!(max - min > 0.1);
+}
+
+#[test]
+fn chunks_follow_the_base_shape() {
+    let scales = (1.0, 0.7, 1.0);
+    let resolution = 16;
+    let build_data = BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 1,
+        chunk_tree_direction: Direction::Up,
+        chunk_cube_position_x: PLANET_RADIUS / 2.0,
+        chunk_cube_position_y: PLANET_RADIUS,
+        chunk_cube_position_z: PLANET_RADIUS / 2.0,
+        planet_seed: SEED,
+        resolution: resolution as u32,
+        // a flat terrain leaves the base shape untouched
+        terrain_settings: TerrainSettings {
+            continent_base_height: 0.0,
+            max_mountain_height: 0.0,
+            max_bump_height: 0.0,
+            base_shape: BaseShape::ellipsoid(scales.0, scales.1, scales.2),
+            ..SETTINGS
+        },
+    };
+
+    let nb_vertices = resolution * resolution;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (resolution - 1) * (resolution - 1) * 6];
+    build_chunk_vertex_data(
+        &build_data,
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut [],
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    let chunk_position = Vector3::new(PLANET_RADIUS / 2.0, PLANET_RADIUS, PLANET_RADIUS / 2.0)
+        .normalize_to_new()
+        * PLANET_RADIUS;
+    for vertex_index in 0..nb_vertices {
+        let position = &chunk_position
+            + &Vector3::new(
+                positions[3 * vertex_index],
+                positions[3 * vertex_index + 1],
+                positions[3 * vertex_index + 2],
+            );
+        let normal = Vector3::new(
+            normals[3 * vertex_index],
+            normals[3 * vertex_index + 1],
+            normals[3 * vertex_index + 2],
+        );
+
+        // the implicit equation of the ellipsoid and its gradient
+        let scaled = Vector3::new(
+            position.x / (scales.0 * PLANET_RADIUS),
+            position.y / (scales.1 * PLANET_RADIUS),
+            position.z / (scales.2 * PLANET_RADIUS),
+        );
+        assert!((scaled.length() - 1.0).abs() < 1e-4);
+
+        let expected_normal = Vector3::new(
+            scaled.x / scales.0,
+            scaled.y / scales.1,
+            scaled.z / scales.2,
+        )
+        .normalize_to_new();
+        assert!(
+            Vector3::dot(&normal, &expected_normal) > 0.9999,
+            "normal {:?} differs from the ellipsoid normal {:?}",
+            normal,
+            expected_normal
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use terrain_generation::landscape::bathymetry_layer::bathymetry_layer;
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const SEA_LEVEL: f32 = OCEAN_DEPTH;
+const SHELF_DEPTH: f32 = 200.0;
+const ABYSSAL_DEPTH: f32 = 4e3;
+
+fn ocean_settings() -> TerrainSettings {
+    TerrainSettings {
+        sea_level: SEA_LEVEL,
+        continental_shelf_depth: SHELF_DEPTH,
+        abyssal_depth: ABYSSAL_DEPTH,
+        continents_fragmentation: 0.95,
+        ..SETTINGS
+    }
+}
+
+#[test]
+fn the_sea_floor_deepens_away_from_the_coast() {
+    let bathymetry = bathymetry_layer(SEA_LEVEL, SHELF_DEPTH, ABYSSAL_DEPTH);
+    let coast = 0.3;
+    let shelf_edge = coast - bathymetry.shelf_width;
+    let abyss = shelf_edge - bathymetry.slope_width;
+
+    let depth = |continent_noise: f32| bathymetry.depth(continent_noise, &mut Vector3::zero());
+    assert_eq!(depth(coast), 0.0);
+    assert_eq!(depth(shelf_edge), SHELF_DEPTH);
+    assert_eq!(depth(abyss), ABYSSAL_DEPTH);
+    assert_eq!(depth(0.0), ABYSSAL_DEPTH);
+
+    // the slopes are much steeper than the shelves
+    let shelf_steepness = SHELF_DEPTH / bathymetry.shelf_width;
+    let slope_steepness = (ABYSSAL_DEPTH - SHELF_DEPTH) / bathymetry.slope_width;
+    assert!(slope_steepness > 5.0 * shelf_steepness);
+
+    let mut previous = depth(coast);
+    for i in 1..=100 {
+        let current = depth(coast - 0.2 * i as f32 / 100.0);
+        assert!(current >= previous);
+        previous = current;
+    }
+}
+
+#[test]
+fn bathymetry_gradient_matches_finite_differences() {
+    let bathymetry = bathymetry_layer(SEA_LEVEL, SHELF_DEPTH, ABYSSAL_DEPTH);
+    let epsilon = 1e-4;
+
+    // the continent noise and mask vary along x and y so that the chain rule is exercised
+    let sample = |point: &Vector3, gradient: &mut Vector3| {
+        let continent_noise = 0.15 + 0.2 * point.x + 0.05 * point.y;
+        let continent_mask = 0.5 * point.y;
+        let continent_mask_gradient = Vector3::new(0.0, 0.5, 0.0);
+        let elevation = continent_mask * 10e3;
+        *gradient = &continent_mask_gradient * 10e3;
+        bathymetry.apply(
+            continent_noise,
+            &Vector3::new(0.2, 0.05, 0.0),
+            continent_mask,
+            &continent_mask_gradient,
+            elevation,
+            gradient,
+        )
+    };
+
+    for i in 0..=50 {
+        let point = Vector3::new(-0.5 + i as f32 / 50.0, 0.3, 0.0);
+        let mut gradient = Vector3::zero();
+        sample(&point, &mut gradient);
+
+        let mut numerical = Vector3::zero();
+        for (axis, component) in [
+            (Vector3::new(epsilon, 0.0, 0.0), &mut numerical.x),
+            (Vector3::new(0.0, epsilon, 0.0), &mut numerical.y),
+        ] {
+            let forward = sample(&(&point + &axis), &mut Vector3::zero());
+            let backward = sample(&(&point - &axis), &mut Vector3::zero());
+            *component = (forward - backward) / (2.0 * epsilon);
+        }
+
+        assert!(
+            (&gradient - &numerical).length() <= 1e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn abyssal_plains_lie_at_the_abyssal_depth() {
+    let settings = ocean_settings();
+    let terrain = make_terrain_function(settings);
+    let continents = continent_layer(
+        settings.continents_frequency,
+        settings.continents_fragmentation,
+    );
+    let bathymetry = bathymetry_layer(SEA_LEVEL, SHELF_DEPTH, ABYSSAL_DEPTH);
+    let abyss = 0.3 - bathymetry.shelf_width - bathymetry.slope_width;
+
+    let mut nb_abyssal_points = 0;
+    for point in fibonacci_sphere(2000) {
+        let continent_noise =
+            continents.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero());
+        if continent_noise > abyss {
+            continue;
+        }
+
+        let mut position = point.clone();
+        terrain(
+            &Vector3d::from(&point),
+            &NOISE_SEED,
+            0.0,
+            &mut position,
+            &mut Vector3::zero(),
+        );
+        let elevation = Vector3::dot(&position, &point) - 1.0;
+        assert!((elevation - (SEA_LEVEL - ABYSSAL_DEPTH)).abs() < 1.0);
+        nb_abyssal_points += 1;
+    }
+    assert!(nb_abyssal_points > 0);
+}
+
+#[test]
+fn bathymetry_is_disabled_without_abyssal_depth() {
+    let flat_floor = make_terrain_function(TerrainSettings {
+        sea_level: SEA_LEVEL,
+        ..SETTINGS
+    });
+    let default_floor = make_terrain_function(SETTINGS);
+
+    for point in fibonacci_sphere(200) {
+        let mut flat_position = point.clone();
+        flat_floor(
+            &Vector3d::from(&point),
+            &NOISE_SEED,
+            0.0,
+            &mut flat_position,
+            &mut Vector3::zero(),
+        );
+        let mut default_position = point.clone();
+        default_floor(
+            &Vector3d::from(&point),
+            &NOISE_SEED,
+            0.0,
+            &mut default_position,
+            &mut Vector3::zero(),
+        );
+        assert!(Vector3::equals(&flat_position, &default_position));
+    }
+}
+
+#[test]
+fn trenches_sink_the_ocean_floor() {
+    let plates = TerrainSettings {
+        nb_tectonic_plates: 12,
+        ..ocean_settings()
+    };
+    let without_trenches = make_terrain_function(TerrainSettings {
+        max_trench_depth: 0.0,
+        ..plates
+    });
+    let with_trenches = make_terrain_function(plates);
+
+    let mut deepest_trench: f32 = 0.0;
+    for point in fibonacci_sphere(2000) {
+        let mut reference = point.clone();
+        without_trenches(
+            &Vector3d::from(&point),
+            &NOISE_SEED,
+            0.0,
+            &mut reference,
+            &mut Vector3::zero(),
+        );
+        let mut trench = point.clone();
+        with_trenches(
+            &Vector3d::from(&point),
+            &NOISE_SEED,
+            0.0,
+            &mut trench,
+            &mut Vector3::zero(),
+        );
+
+        // the floor can sink below the center of the unit sphere
+        let difference = Vector3::dot(&trench, &point) - Vector3::dot(&reference, &point);
+        assert!(difference <= 1e-2);
+        deepest_trench = deepest_trench.min(difference);
+    }
+    assert!(deepest_trench < -1e3);
+}
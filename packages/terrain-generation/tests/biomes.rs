@@ -0,0 +1,164 @@
+use terrain_generation::biomes::{
+    Biome, BiomeCellError, BiomeClassifier, BiomeSettings, BIOME_STRIDE,
+};
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn poles_are_colder_than_the_equator() {
+    let classifier = BiomeClassifier::new(&SETTINGS);
+    let pole = Vector3::new(0.0, 1.0, 0.0);
+    let equator = Vector3::new(1.0, 0.0, 0.0);
+
+    assert!(classifier.temperature(&pole, 0.0) < classifier.temperature(&equator, 0.0));
+
+    let pole_biome = classifier.classify(&pole, 100.0, &NOISE_SEED).biomes[0].0;
+    assert!(pole_biome == Biome::Ice as u8 || pole_biome == Biome::Tundra as u8);
+}
+
+#[test]
+fn altitude_cools_the_climate() {
+    let classifier = BiomeClassifier::new(&SETTINGS);
+    let equator = Vector3::new(0.0, 0.0, 1.0);
+
+    let lowland = classifier.temperature(&equator, 0.0);
+    let summit = classifier.temperature(&equator, 8e3);
+    let expected = SETTINGS.biomes.temperature_lapse_rate * 8e3;
+    assert!((lowland - summit - expected).abs() < 1e-3);
+
+    let summit_biome = classifier.classify(&equator, 8e3, &NOISE_SEED).biomes[0].0;
+    assert!(summit_biome == Biome::Ice as u8 || summit_biome == Biome::Tundra as u8);
+}
+
+#[test]
+fn vertices_below_sea_level_are_ocean() {
+    let settings = TerrainSettings {
+        sea_level: 100.0,
+        ..SETTINGS
+    };
+    let classifier = BiomeClassifier::new(&settings);
+
+    for point in fibonacci_sphere(100) {
+        let sample = classifier.classify(&point, 50.0, &NOISE_SEED);
+        assert_eq!(sample.biomes[0], (Biome::Ocean as u8, 1.0));
+
+        let sample = classifier.classify(&point, 150.0, &NOISE_SEED);
+        assert_ne!(sample.biomes[0].0, Biome::Ocean as u8);
+    }
+}
+
+#[test]
+fn biome_weights_are_normalized() {
+    let classifier = BiomeClassifier::new(&SETTINGS);
+
+    for point in fibonacci_sphere(500) {
+        let sample = classifier.classify(&point, 500.0, &NOISE_SEED);
+        let [(_, dominant), (_, secondary)] = sample.biomes;
+
+        assert!((0.0..=1.0).contains(&sample.moisture));
+        assert!((dominant + secondary - 1.0).abs() < 1e-5);
+        assert!(dominant >= secondary && secondary >= 0.0);
+    }
+}
+
+#[test]
+fn the_table_is_configurable() {
+    let mut biomes = BiomeSettings::new();
+    for temperature_band in 0..4 {
+        for moisture_band in 0..4 {
+            biomes
+                .set_biome(temperature_band, moisture_band, 42)
+                .unwrap();
+        }
+    }
+    assert_eq!(biomes.biome(2, 3), Ok(42));
+
+    let outside = BiomeCellError {
+        temperature_band: 4,
+        moisture_band: 0,
+    };
+    assert_eq!(biomes.biome(4, 0), Err(outside.clone()));
+    assert_eq!(biomes.set_biome(4, 0, 42), Err(outside));
+    assert!(biomes.set_biome(0, 7, 42).is_err());
+
+    let settings = TerrainSettings { biomes, ..SETTINGS };
+    let classifier = BiomeClassifier::new(&settings);
+    for point in fibonacci_sphere(100) {
+        assert_eq!(
+            classifier.classify(&point, 500.0, &NOISE_SEED).biomes[0],
+            (42, 1.0)
+        );
+    }
+}
+
+#[test]
+fn chunks_fill_the_biome_buffer() {
+    let resolution = 16;
+    let build_data = BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 0,
+        chunk_tree_direction: Direction::Forward,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: 0.0,
+        chunk_cube_position_z: -PLANET_RADIUS,
+        planet_seed: SEED,
+        resolution: resolution as u32,
+        terrain_settings: SETTINGS,
+    };
+
+    // base grid and skirt
+    let nb_vertices = resolution * resolution + 4 * resolution;
+    let nb_indices = (resolution - 1) * (resolution - 1) * 6 + 4 * (resolution - 1) * 6;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; nb_indices];
+    let mut biomes = vec![-1.0; nb_vertices * BIOME_STRIDE];
+    build_chunk_vertex_data(
+        &build_data,
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut biomes,
+        &mut [],
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    let classifier = BiomeClassifier::new(&SETTINGS);
+    let chunk_position = Vector3::new(0.0, 0.0, -PLANET_RADIUS);
+    for vertex_index in 0..resolution * resolution {
+        let position = &chunk_position
+            + &Vector3::new(
+                positions[3 * vertex_index],
+                positions[3 * vertex_index + 1],
+                positions[3 * vertex_index + 2],
+            );
+        let elevation = position.length() - PLANET_RADIUS;
+        let sample = classifier.classify(&position.normalize_to_new(), elevation, &NOISE_SEED);
+
+        let vertex_biomes = &biomes[BIOME_STRIDE * vertex_index..BIOME_STRIDE * (vertex_index + 1)];
+        assert_eq!(vertex_biomes[0], sample.biomes[0].0 as f32);
+        assert!((vertex_biomes[1] - sample.biomes[0].1).abs() < 1e-3);
+    }
+
+    // the skirt vertices copy the biomes of the border
+    for vertex_index in 0..nb_vertices {
+        let weights =
+            biomes[BIOME_STRIDE * vertex_index + 1] + biomes[BIOME_STRIDE * vertex_index + 3];
+        assert!((weights - 1.0).abs() < 1e-5);
+    }
+    let first_skirt_vertex = resolution * resolution;
+    assert_eq!(
+        biomes[..BIOME_STRIDE],
+        biomes[BIOME_STRIDE * first_skirt_vertex..BIOME_STRIDE * (first_skirt_vertex + 1)]
+    );
+}
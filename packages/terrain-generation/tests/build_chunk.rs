@@ -79,9 +79,12 @@ pub fn build_chunk_data() {
         &mut positions,
         &mut indices,
         &mut normals,
+        &mut [],
+        &mut [],
         &mut scattered_points_buffer,
         scatter_per_square_meter,
-    );
+    )
+    .unwrap();
 
     assert!(
         nb_instances.nb_instances_created <= max_nb_instances,
@@ -168,9 +171,12 @@ pub fn build_chunk_data_without_skirt_buffers() {
         &mut positions,
         &mut indices,
         &mut normals,
+        &mut [],
+        &mut [],
         &mut scattered_points_buffer,
         0.0,
-    );
+    )
+    .unwrap();
 
     assert_eq!(
         positions.len(),
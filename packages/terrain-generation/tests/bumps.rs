@@ -0,0 +1,186 @@
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::gradient_check::{check_terrain_function, fibonacci_sphere};
+use terrain_generation::landscape::make_terrain_function::{
+    make_terrain_function, TerrainFunction,
+};
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::math::smoothstep;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const PATCH_SIZE: f32 = 2e-3;
+const PATCH_RESOLUTION: usize = 16;
+
+fn patch_centers() -> Vec<Vector3> {
+    [
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.3, 0.9, 0.1),
+        Vector3::new(-0.5, 0.7, 0.4),
+        Vector3::new(0.6, -0.2, 0.7),
+        Vector3::new(-0.4, -0.8, 0.3),
+        Vector3::new(0.9, 0.1, -0.4),
+    ]
+    .map(|point| point.clone() / point.length())
+    .to_vec()
+}
+
+fn elevation(terrain: &TerrainFunction, unit_sample_point: &Vector3) -> f32 {
+    let mut position = unit_sample_point.clone();
+    terrain(
+        &Vector3d::from(unit_sample_point),
+        &NOISE_SEED,
+        0.0,
+        &mut position,
+        &mut Vector3::zero(),
+    );
+    position.length() - 1.0
+}
+
+/// Average variance of the elevation inside small patches of the surface, which isolates small scale features
+fn local_elevation_variance(settings: TerrainSettings) -> f32 {
+    let terrain = make_terrain_function(settings);
+    let mut total_variance = 0.0;
+    for center in patch_centers() {
+        let tangent = Vector3::cross(&center, &Vector3::new(0.0, 0.0, 1.0)).normalize_to_new();
+        let bitangent = Vector3::cross(&center, &tangent);
+
+        let mut elevations = Vec::with_capacity(PATCH_RESOLUTION * PATCH_RESOLUTION);
+        for i in 0..PATCH_RESOLUTION {
+            for j in 0..PATCH_RESOLUTION {
+                let u = PATCH_SIZE * (i as f32 / PATCH_RESOLUTION as f32 - 0.5);
+                let v = PATCH_SIZE * (j as f32 / PATCH_RESOLUTION as f32 - 0.5);
+                let unit_sample_point =
+                    (&(&center + &(&tangent * u)) + &(&bitangent * v)).normalize_to_new();
+                let mut position = unit_sample_point.clone();
+                terrain(
+                    &Vector3d::from(&unit_sample_point),
+                    &NOISE_SEED,
+                    0.0,
+                    &mut position,
+                    &mut Vector3::zero(),
+                );
+                elevations.push(Vector3::dot(&position, &unit_sample_point) - 1.0);
+            }
+        }
+
+        let mean = elevations.iter().sum::<f32>() / elevations.len() as f32;
+        total_variance += elevations
+            .iter()
+            .map(|elevation| (elevation - mean) * (elevation - mean))
+            .sum::<f32>()
+            / elevations.len() as f32;
+    }
+
+    total_variance / patch_centers().len() as f32
+}
+
+fn flat_land_settings() -> TerrainSettings {
+    TerrainSettings {
+        max_mountain_height: 0.0,
+        continents_fragmentation: 0.0,
+        ..SETTINGS
+    }
+}
+
+#[test]
+fn bumps_variance_responds_to_max_bump_height() {
+    let no_bumps = local_elevation_variance(TerrainSettings {
+        max_bump_height: 0.0,
+        ..flat_land_settings()
+    });
+    let low_bumps = local_elevation_variance(flat_land_settings());
+    let high_bumps = local_elevation_variance(TerrainSettings {
+        max_bump_height: flat_land_settings().max_bump_height * 2.0,
+        ..flat_land_settings()
+    });
+
+    assert!(low_bumps > 0.0);
+    assert!(
+        no_bumps <= 1e-3 * low_bumps,
+        "flat terrain should not vary: {no_bumps}"
+    );
+    // the variance scales with the square of the amplitude
+    let ratio = high_bumps / low_bumps;
+    assert!(
+        (ratio - 4.0).abs() <= 0.1,
+        "unexpected variance ratio {ratio}"
+    );
+}
+
+#[test]
+fn bumps_variance_responds_to_bumps_frequency() {
+    let low_frequency = local_elevation_variance(flat_land_settings());
+    let high_frequency = local_elevation_variance(TerrainSettings {
+        bumps_frequency: flat_land_settings().bumps_frequency * 8.0,
+        ..flat_land_settings()
+    });
+
+    assert!(
+        high_frequency > 2.0 * low_frequency,
+        "higher bump frequencies should create more local relief: {low_frequency} vs {high_frequency}"
+    );
+}
+
+#[test]
+fn bumps_are_masked_by_oceans() {
+    let settings = TerrainSettings {
+        max_mountain_height: 0.0,
+        continent_base_height: 0.0,
+        continents_fragmentation: 1.0,
+        ..SETTINGS
+    };
+    let terrain = make_terrain_function(settings);
+    let continents = continent_layer(settings.continents_frequency, 1.0);
+
+    let mut nb_ocean_samples = 0;
+    for i in 0..64 {
+        for j in 0..32 {
+            let phi = 2.0 * std::f32::consts::PI * i as f32 / 64.0;
+            let theta = std::f32::consts::PI * (j as f32 + 0.5) / 32.0;
+            let unit_sample_point = Vector3::new(
+                f32::sin(theta) * f32::cos(phi),
+                f32::cos(theta),
+                f32::sin(theta) * f32::sin(phi),
+            );
+
+            let mut continent_gradient = Vector3::zero();
+            let continent_mask = smoothstep(
+                0.3,
+                0.5,
+                continents.sample(
+                    &Vector3d::from(&unit_sample_point),
+                    &NOISE_SEED,
+                    &mut continent_gradient,
+                ),
+                &mut continent_gradient,
+            );
+            if continent_mask > 0.0 {
+                continue;
+            }
+
+            nb_ocean_samples += 1;
+            assert!(elevation(terrain.as_ref(), &unit_sample_point).abs() <= 1e-6);
+        }
+    }
+
+    assert!(nb_ocean_samples > 0, "expected some ocean in the samples");
+}
+
+#[test]
+fn bump_gradients_match_finite_differences() {
+    // the continents cover the whole planet so that only the bumps shape the terrain
+    let report = check_terrain_function(
+        "bumps",
+        make_terrain_function(flat_land_settings()).as_ref(),
+        &NOISE_SEED,
+        PLANET_RADIUS,
+        &fibonacci_sphere(500),
+        2e-5,
+    );
+    assert!(report.mean_error <= 1e-2, "{report}");
+}
@@ -0,0 +1,145 @@
+use terrain_generation::biomes::BIOME_STRIDE;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::chunk_layout::{ChunkLayout, SCATTER_STRIDE};
+use terrain_generation::splat::SPLAT_STRIDE;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: u32 = 17;
+const CHUNK_DEPTH: u32 = 12;
+const SCATTER_PER_SQUARE_METER: f32 = 0.05;
+
+fn build_data() -> BuildData {
+    BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: CHUNK_DEPTH,
+        chunk_tree_direction: Direction::Forward,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: 0.0,
+        chunk_cube_position_z: -PLANET_RADIUS,
+        planet_seed: SEED,
+        resolution: RESOLUTION,
+        // low hills, so that the instances have some grass to grow on
+        terrain_settings: TerrainSettings {
+            continent_base_height: 200.0,
+            max_mountain_height: 300.0,
+            max_bump_height: 50.0,
+            ..SETTINGS
+        },
+    }
+}
+
+/// The buffers of a chunk allocated from its layout, after the build
+struct ChunkBuffers {
+    indices: Vec<u16>,
+    nb_instances: usize,
+}
+
+fn build(layout: &ChunkLayout) -> ChunkBuffers {
+    let mut positions = vec![0.0; layout.positions_length];
+    let mut normals = vec![0.0; layout.normals_length];
+    let mut indices = vec![0; layout.indices_length];
+    let mut biomes = vec![0.0; layout.biomes_length];
+    let mut splat_weights = vec![0.0; layout.splat_weights_length];
+    let mut scatter = vec![0.0; layout.scatter_length];
+
+    let return_data = build_chunk_vertex_data(
+        &build_data(),
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut biomes,
+        &mut splat_weights,
+        &mut scatter,
+        SCATTER_PER_SQUARE_METER,
+    )
+    .unwrap();
+
+    ChunkBuffers {
+        indices,
+        nb_instances: return_data.nb_instances_created,
+    }
+}
+
+#[test]
+fn layout_counts_the_grid_and_the_skirt() {
+    let n = RESOLUTION as usize;
+    let chunk_size = PLANET_RADIUS * 2.0 / 2f32.powi(CHUNK_DEPTH as i32);
+
+    let without_skirt = ChunkLayout::new(RESOLUTION, chunk_size, false, 0.0);
+    assert_eq!(without_skirt.nb_vertices, n * n);
+    assert_eq!(without_skirt.positions_length, 3 * n * n);
+    assert_eq!(without_skirt.normals_length, 3 * n * n);
+    assert_eq!(without_skirt.indices_length, 6 * (n - 1) * (n - 1));
+    assert_eq!(without_skirt.biomes_length, BIOME_STRIDE * n * n);
+    assert_eq!(without_skirt.splat_weights_length, SPLAT_STRIDE * n * n);
+    assert_eq!(without_skirt.scatter_length, 0);
+    assert_eq!(without_skirt.skirt_vertex_count, 0);
+    assert_eq!(without_skirt.skirt_index_count, 0);
+    assert!(!without_skirt.has_skirt());
+
+    let with_skirt = ChunkLayout::new(RESOLUTION, chunk_size, true, SCATTER_PER_SQUARE_METER);
+    assert_eq!(with_skirt.nb_vertices, n * n + 4 * n);
+    assert_eq!(with_skirt.positions_length, 3 * (n * n + 4 * n));
+    assert_eq!(
+        with_skirt.indices_length,
+        6 * (n - 1) * (n - 1) + 24 * (n - 1)
+    );
+    assert_eq!(with_skirt.skirt_vertex_offset, n * n);
+    assert_eq!(with_skirt.skirt_vertex_count, 4 * n);
+    assert_eq!(with_skirt.skirt_index_offset, 6 * (n - 1) * (n - 1));
+    assert_eq!(
+        with_skirt.skirt_index_offset + with_skirt.skirt_index_count,
+        with_skirt.indices_length
+    );
+    assert!(with_skirt.has_skirt());
+
+    let max_nb_instances = f32::floor(chunk_size * chunk_size * SCATTER_PER_SQUARE_METER * 2.0);
+    assert_eq!(with_skirt.max_nb_instances, max_nb_instances as usize);
+    assert_eq!(
+        with_skirt.scatter_length,
+        SCATTER_STRIDE * with_skirt.max_nb_instances
+    );
+
+    assert_eq!(
+        ChunkLayout::for_chunk(&build_data(), true, SCATTER_PER_SQUARE_METER),
+        with_skirt
+    );
+}
+
+#[test]
+fn buffers_sized_by_the_layout_are_accepted() {
+    for has_skirt in [false, true] {
+        let layout = ChunkLayout::for_chunk(&build_data(), has_skirt, SCATTER_PER_SQUARE_METER);
+        let chunk = build(&layout);
+
+        assert!(chunk.nb_instances > 0);
+        assert!(chunk.nb_instances <= layout.max_nb_instances);
+    }
+}
+
+#[test]
+fn skirt_triangles_come_after_the_grid() {
+    let layout = ChunkLayout::for_chunk(&build_data(), true, SCATTER_PER_SQUARE_METER);
+    let chunk = build(&layout);
+
+    let (grid, skirt) = chunk.indices.split_at(layout.skirt_index_offset);
+    assert!(grid
+        .iter()
+        .all(|&index| (index as usize) < layout.skirt_vertex_offset));
+
+    // every skirt triangle hangs from the border of the grid down to the skirt vertices
+    for triangle in skirt.chunks(3) {
+        assert!(triangle
+            .iter()
+            .any(|&index| index as usize >= layout.skirt_vertex_offset));
+        assert!(triangle
+            .iter()
+            .all(|&index| (index as usize) < layout.nb_vertices));
+    }
+}
@@ -0,0 +1,154 @@
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::chunk_layout::{ChunkLayout, SCATTER_STRIDE};
+use terrain_generation::chunk_mesh::build_chunk_mesh;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::validation::{BuildError, ValidationError};
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: u32 = 17;
+const CHUNK_DEPTH: u32 = 12;
+const SCATTER_PER_SQUARE_METER: f32 = 0.05;
+
+fn build_data() -> BuildData {
+    BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: CHUNK_DEPTH,
+        chunk_tree_direction: Direction::Forward,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: 0.0,
+        chunk_cube_position_z: -PLANET_RADIUS,
+        planet_seed: SEED,
+        resolution: RESOLUTION,
+        terrain_settings: SETTINGS,
+    }
+}
+
+#[test]
+fn mesh_matches_the_wasm_buffers() {
+    let mesh = build_chunk_mesh(&build_data(), true, SCATTER_PER_SQUARE_METER).unwrap();
+
+    let layout = ChunkLayout::for_chunk(&build_data(), true, SCATTER_PER_SQUARE_METER);
+    let mut positions = vec![0.0; layout.positions_length];
+    let mut normals = vec![0.0; layout.normals_length];
+    let mut indices = vec![0; layout.indices_length];
+    let mut scatter = vec![0.0; layout.scatter_length];
+    let return_data = build_chunk_vertex_data(
+        &build_data(),
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut [],
+        &mut scatter,
+        SCATTER_PER_SQUARE_METER,
+    )
+    .unwrap();
+
+    let flatten = |vectors: &[Vector3]| -> Vec<f32> {
+        vectors.iter().flat_map(|v| [v.x, v.y, v.z]).collect()
+    };
+    assert_eq!(flatten(&mesh.positions), positions);
+    assert_eq!(flatten(&mesh.normals), normals);
+    assert_eq!(mesh.indices, indices);
+
+    assert_eq!(mesh.scatter.len(), return_data.nb_instances_created);
+    assert!(!mesh.scatter.is_empty());
+    for (point, instance) in mesh.scatter.iter().zip(scatter.chunks(SCATTER_STRIDE)) {
+        assert_eq!(
+            [
+                point.position.x,
+                point.position.y,
+                point.position.z,
+                point.normal.x,
+                point.normal.y,
+                point.normal.z
+            ],
+            instance
+        );
+    }
+}
+
+#[test]
+fn triangles_cover_the_index_buffer() {
+    for has_skirt in [false, true] {
+        let mesh = build_chunk_mesh(&build_data(), has_skirt, 0.0).unwrap();
+        let layout = ChunkLayout::for_chunk(&build_data(), has_skirt, 0.0);
+
+        assert_eq!(mesh.nb_triangles(), layout.indices_length / 3);
+        assert_eq!(mesh.triangles().count(), mesh.nb_triangles());
+        assert!(mesh.scatter.is_empty());
+
+        for (triangle, positions) in mesh.triangles().zip(mesh.triangle_positions()) {
+            for (index, position) in triangle.into_iter().zip(positions) {
+                assert!(index < layout.nb_vertices);
+                assert!(std::ptr::eq(&mesh.positions[index], position));
+            }
+        }
+    }
+}
+
+#[test]
+fn bounds_enclose_the_vertices() {
+    let mesh = build_chunk_mesh(&build_data(), true, 0.0).unwrap();
+
+    assert!(mesh
+        .positions
+        .iter()
+        .all(|position| mesh.bounds.contains(position)));
+    assert!(mesh.bounds.contains(&mesh.bounds.center()));
+
+    // the bounds are tight: every face touches a vertex
+    let touches = |select: fn(&Vector3) -> f32| {
+        let values: Vec<f32> = mesh.positions.iter().map(select).collect();
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        (min, max)
+    };
+    assert_eq!(touches(|v| v.x), (mesh.bounds.min.x, mesh.bounds.max.x));
+    assert_eq!(touches(|v| v.y), (mesh.bounds.min.y, mesh.bounds.max.y));
+    assert_eq!(touches(|v| v.z), (mesh.bounds.min.z, mesh.bounds.max.z));
+}
+
+#[test]
+fn invalid_data_is_reported_natively() {
+    let data = BuildData {
+        resolution: 1,
+        ..build_data()
+    };
+
+    match build_chunk_mesh(&data, true, 0.0) {
+        Err(BuildError::InvalidData(errors)) => {
+            assert_eq!(errors.0, vec![ValidationError::ResolutionTooLow(1)])
+        }
+        result => panic!("expected invalid data, got {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn steep_chunks_keep_all_their_instances() {
+    // cliffs much higher than the chunk is wide, whose area is many times the flat area assumed by the layout
+    let data = BuildData {
+        terrain_settings: TerrainSettings {
+            max_mountain_height: 2e6,
+            ..SETTINGS
+        },
+        ..build_data()
+    };
+    let layout = ChunkLayout::for_chunk(&data, true, SCATTER_PER_SQUARE_METER);
+    let mesh = build_chunk_mesh(&data, true, SCATTER_PER_SQUARE_METER).unwrap();
+
+    // the instances are scattered on the grid, not on the skirt
+    let area: f32 = mesh
+        .triangle_positions()
+        .take(layout.skirt_index_offset / 3)
+        .map(|[a, b, c]| Vector3::cross(&(b - a), &(c - a)).length() / 2.0)
+        .sum();
+    assert!(mesh.scatter.len() > layout.max_nb_instances);
+    assert!((mesh.scatter.len() as f32 - area * SCATTER_PER_SQUARE_METER).abs() <= 1.0);
+}
@@ -1,5 +1,6 @@
 use terrain_generation::build_chunk_vertex_data;
 use terrain_generation::build_data::BuildData;
+use terrain_generation::seed::Seed;
 use terrain_generation::terrain_settings::TerrainSettings;
 use terrain_generation::utils::direction::Direction;
 use terrain_generation::utils::vector3::Vector3;
@@ -15,7 +16,7 @@ fn make_build_data() -> BuildData {
         chunk_cube_position_x: 0.0,
         chunk_cube_position_y: 0.0,
         chunk_cube_position_z: -PLANET_RADIUS,
-        planet_seed: 42.0,
+        planet_seed: Seed::from_offset(42.0),
         resolution: RESOLUTION as u32,
         terrain_settings: TerrainSettings::new(),
     }
@@ -71,9 +72,12 @@ fn chunk_skirt_triangles_face_toward_chunk_interior_on_all_edges() {
         &mut positions,
         &mut indices,
         &mut normals,
+        &mut [],
+        &mut [],
         &mut scattered_points_buffer,
         0.0,
-    );
+    )
+    .unwrap();
 
     let border_loops = build_border_loops();
     let skirt_loops: [Vec<usize>; 4] = std::array::from_fn(|loop_index| {
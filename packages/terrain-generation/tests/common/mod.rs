@@ -1,4 +1,10 @@
+use std::sync::LazyLock;
+use terrain_generation::base_shape::BaseShape;
+use terrain_generation::biomes::BiomeSettings;
+use terrain_generation::seed::{NoiseSeed, Seed};
+use terrain_generation::splat::SplatSettings;
 use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::volumetric_chunk::CaveSettings;
 
 #[allow(dead_code)]
 pub const OCEAN_DEPTH: f32 = 7e3;
@@ -12,9 +18,33 @@ pub const SETTINGS: TerrainSettings = TerrainSettings {
     continent_base_height: OCEAN_DEPTH * 2.5,
     max_mountain_height: 15e3,
     max_bump_height: 2e3,
+    terrace_steps: 8.0,
+    terrace_sharpness: 4.0,
+    terrace_coverage: 0.0,
+    erosion_strength: 0.0,
+    erosion_octaves: 5,
+    erosion_frequency: 100.0,
+    craters_frequency: 4.0,
+    craters_density: 0.0,
+    max_crater_depth: 5e3,
+    nb_tectonic_plates: 0,
+    tectonic_boundary_width: 0.05,
+    max_ridge_height: 2.5e3,
+    sea_level: 0.0,
+    continental_shelf_depth: 0.0,
+    abyssal_depth: 0.0,
+    max_trench_depth: 5e3,
+    base_shape: BaseShape::SPHERE,
+    biomes: BiomeSettings::WHITTAKER,
+    splat: SplatSettings::DEFAULT,
+    caves: CaveSettings::NONE,
 };
 
-pub const SEED: f32 = 11.0;
+pub const SEED: Seed = Seed::from_offset(11.0);
+
+/// `SEED` resolved for the layers
+#[allow(dead_code)]
+pub static NOISE_SEED: LazyLock<NoiseSeed> = LazyLock::new(|| NoiseSeed::new(SEED));
 
 #[allow(dead_code)]
 pub const PLANET_RADIUS: f32 = 1000e3;
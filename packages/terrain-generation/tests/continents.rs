@@ -1,6 +1,9 @@
 use image::{ImageBuffer, Luma};
 use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
 use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
 
 mod common;
 
@@ -17,7 +20,11 @@ fn continents() {
     let unit_sample_point = &sample_point / sample_point.length();
     let mut gradient = unit_sample_point.clone();
 
-    let elevation = terrain(&unit_sample_point, SEED, &mut gradient);
+    let elevation = terrain.sample(
+        &Vector3d::from(&unit_sample_point),
+        &NOISE_SEED,
+        &mut gradient,
+    );
 
     assert!((0.0..=1.0).contains(&elevation));
 
@@ -37,11 +44,16 @@ fn continents() {
             );
             let mut gradient = Vector3::new(0.0, 0.0, 0.0);
 
-            let elevation = terrain(&sample_point, SEED, &mut gradient);
+            let elevation =
+                terrain.sample(&Vector3d::from(&sample_point), &NOISE_SEED, &mut gradient);
 
             let sample_point_flat = Vector3::new(u as f32 / 40.0, v as f32 / 40.0, 0.0);
 
-            let flat_elevation = terrain(&sample_point_flat, SEED, &mut gradient);
+            let flat_elevation = terrain.sample(
+                &Vector3d::from(&sample_point_flat),
+                &NOISE_SEED,
+                &mut gradient,
+            );
 
             *(value_image.get_pixel_mut(u, v)) = Luma([(elevation * 255.0) as u8]);
 
@@ -73,10 +85,18 @@ fn continents_fragmentation_changes_output() {
 
     for sample_point in sample_points {
         let mut low_gradient = Vector3::zero();
-        let low = low_fragmentation(&sample_point, SEED, &mut low_gradient);
+        let low = low_fragmentation.sample(
+            &Vector3d::from(&sample_point),
+            &NOISE_SEED,
+            &mut low_gradient,
+        );
 
         let mut high_gradient = Vector3::zero();
-        let high = high_fragmentation(&sample_point, SEED, &mut high_gradient);
+        let high = high_fragmentation.sample(
+            &Vector3d::from(&sample_point),
+            &NOISE_SEED,
+            &mut high_gradient,
+        );
 
         if (low - high).abs() > 1e-6 || (&low_gradient - &high_gradient).length() > 1e-6 {
             found_difference = true;
@@ -105,9 +125,33 @@ fn zero_fragmentation_means_land_everywhere() {
 
     for sample_point in sample_points {
         let mut gradient = Vector3::zero();
-        let elevation = no_fragmentation(&sample_point, SEED, &mut gradient);
+        let elevation =
+            no_fragmentation.sample(&Vector3d::from(&sample_point), &NOISE_SEED, &mut gradient);
 
         assert!((elevation - 1.0).abs() <= 1e-6);
         assert!(gradient.length() <= 1e-6);
     }
 }
+
+#[test]
+fn continents_add_to_the_gradient() {
+    let terrain = continent_layer(
+        SETTINGS.continents_frequency,
+        SETTINGS.continents_fragmentation,
+    );
+    let offset = Vector3::new(1.0, -2.0, 3.0);
+
+    for sample_point in fibonacci_sphere(200) {
+        let mut gradient = Vector3::zero();
+        terrain.sample(&Vector3d::from(&sample_point), &NOISE_SEED, &mut gradient);
+
+        // the gradient the caller already accumulated is left untouched
+        let mut accumulated_gradient = offset.clone();
+        terrain.sample(
+            &Vector3d::from(&sample_point),
+            &NOISE_SEED,
+            &mut accumulated_gradient,
+        );
+        assert!((&(&accumulated_gradient - &offset) - &gradient).length() <= 1e-5);
+    }
+}
@@ -0,0 +1,166 @@
+use terrain_generation::landscape::crater_layer::{crater_layer, CraterLayer};
+use terrain_generation::landscape::gradient_check::{fibonacci_sphere, numerical_gradient};
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::seed::{NoiseSeed, Seed};
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn crater_gradient_matches_finite_differences() {
+    let layer = crater_layer(4.0, 3, 1.0);
+
+    let mut nb_cratered_samples = 0;
+    for point in fibonacci_sphere(500) {
+        let mut gradient = Vector3::zero();
+        layer.sample(&Vector3d::from(&point), &NOISE_SEED, &mut gradient);
+        let numerical = numerical_gradient(
+            &|p| layer.sample(&Vector3d::from(p), &NOISE_SEED, &mut Vector3::zero()),
+            &point,
+            1e-4,
+        );
+
+        if gradient.length() > 0.0 {
+            nb_cratered_samples += 1;
+        }
+
+        assert!(
+            (&gradient - &numerical).length() <= 2e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+
+    assert!(nb_cratered_samples > 100, "expected craters on the sphere");
+}
+
+#[test]
+fn craters_have_a_bowl_and_a_rim() {
+    let layer = crater_layer(4.0, 1, 1.0);
+    let (low, high) = layer.bounds();
+
+    let values = fibonacci_sphere(20000)
+        .iter()
+        .map(|point| layer.sample(&Vector3d::from(point), &NOISE_SEED, &mut Vector3::zero()))
+        .collect::<Vec<f32>>();
+    let deepest = values.iter().cloned().fold(f32::MAX, f32::min);
+    let highest = values.iter().cloned().fold(f32::MIN, f32::max);
+
+    assert!(deepest < -0.4, "expected deep bowls, got {deepest}");
+    assert!(highest > 0.1, "expected raised rims, got {highest}");
+    assert!(deepest >= low && highest <= high);
+}
+
+#[test]
+fn craters_are_deterministic_per_seed() {
+    let layer = crater_layer(4.0, 4, 0.8);
+
+    let mut found_difference = false;
+    for point in fibonacci_sphere(500) {
+        let value = layer.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero());
+        assert_eq!(
+            value,
+            layer.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero())
+        );
+
+        if value
+            != layer.sample(
+                &Vector3d::from(&point),
+                &NoiseSeed::new(Seed::from_offset(12.0)),
+                &mut Vector3::zero(),
+            )
+        {
+            found_difference = true;
+        }
+    }
+
+    assert!(found_difference, "another seed should move the craters");
+}
+
+#[test]
+fn small_craters_follow_the_size_exponent() {
+    let cratered_fraction = |size_exponent: f32| {
+        // only keep the small craters of the last octave by setting the depth of the others to zero
+        let layer = CraterLayer {
+            size_exponent,
+            ..crater_layer(4.0, 3, 0.2)
+        };
+        let large_craters = CraterLayer {
+            nb_octaves: 2,
+            ..layer
+        };
+
+        let points = fibonacci_sphere(20000);
+        let nb_cratered = points
+            .iter()
+            .filter(|point| {
+                let all = layer.sample(&Vector3d::from(*point), &NOISE_SEED, &mut Vector3::zero());
+                let large = large_craters.sample(
+                    &Vector3d::from(*point),
+                    &NOISE_SEED,
+                    &mut Vector3::zero(),
+                );
+                all != large
+            })
+            .count();
+        nb_cratered as f32 / points.len() as f32
+    };
+
+    // a steeper power law produces relatively more small craters
+    let shallow = cratered_fraction(1.5);
+    let steep = cratered_fraction(3.0);
+    assert!(
+        steep > 1.5 * shallow,
+        "expected more small craters with a steeper size distribution: {shallow} vs {steep}"
+    );
+}
+
+#[test]
+fn craters_add_to_the_terrain() {
+    let smooth = make_terrain_function(SETTINGS);
+    let cratered = make_terrain_function(TerrainSettings {
+        craters_density: 0.5,
+        ..SETTINGS
+    });
+    let craters = crater_layer(SETTINGS.craters_frequency, 6, 0.5);
+
+    let mut found_difference = false;
+    for unit_sample_point in fibonacci_sphere(200) {
+        let mut smooth_position = unit_sample_point.clone();
+        smooth(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut smooth_position,
+            &mut Vector3::zero(),
+        );
+        let mut cratered_position = unit_sample_point.clone();
+        cratered(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut cratered_position,
+            &mut Vector3::zero(),
+        );
+
+        let crater_elevation = craters.sample(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            &mut Vector3::zero(),
+        ) * SETTINGS.max_crater_depth;
+        let difference = cratered_position.length() - smooth_position.length();
+        assert!((difference - crater_elevation).abs() <= 1e-2 * SETTINGS.max_crater_depth);
+
+        if difference.abs() > 1.0 {
+            found_difference = true;
+        }
+    }
+
+    assert!(found_difference, "expected craters to dent the terrain");
+}
@@ -0,0 +1,143 @@
+use terrain_generation::landscape::domain_warp_layer::{domain_warp_layer, DomainWarpLayer};
+use terrain_generation::landscape::gradient_check::{fibonacci_sphere, numerical_gradient};
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::terrain_graph::TerrainGraph;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::seed::NoiseSeed;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+/// A plane wave in [0, 1] with an exact gradient
+struct WaveLayer {
+    wave_vector: Vector3,
+}
+
+impl TerrainLayer for WaveLayer {
+    fn sample(&self, coords: &Vector3d, seed: &NoiseSeed, gradient: &mut Vector3) -> f32 {
+        let phase = Vector3::dot(&coords.to_f32(), &self.wave_vector) + seed.offset();
+        *gradient += &self.wave_vector * (0.5 * f32::cos(phase));
+        0.5 + 0.5 * f32::sin(phase)
+    }
+
+    fn name(&self) -> &'static str {
+        "wave"
+    }
+
+    fn bounds(&self) -> (f32, f32) {
+        (0.0, 1.0)
+    }
+
+    fn max_frequency(&self) -> f32 {
+        self.wave_vector.length()
+    }
+
+    fn cost(&self) -> u32 {
+        1
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f32)> {
+        Vec::new()
+    }
+}
+
+#[test]
+fn warped_gradient_follows_the_chain_rule() {
+    let layer = DomainWarpLayer {
+        input: WaveLayer {
+            wave_vector: Vector3::new(3.0, -2.0, 5.0),
+        },
+        warp: WaveLayer {
+            wave_vector: Vector3::new(1.0, 2.0, -1.5),
+        },
+        strength: 0.3,
+        frequency: 2.0,
+    };
+
+    for point in fibonacci_sphere(50) {
+        let mut gradient = Vector3::zero();
+        layer.sample(&Vector3d::from(&point), &NOISE_SEED, &mut gradient);
+
+        let numerical = numerical_gradient(
+            &|p| layer.sample(&Vector3d::from(p), &NOISE_SEED, &mut Vector3::zero()),
+            &point,
+            1e-3,
+        );
+
+        assert!(
+            (&gradient - &numerical).length() <= 1e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn zero_strength_leaves_the_input_unchanged() {
+    let mountains = mountain_layer(SETTINGS.mountains_frequency, 7, 2.0, 2.0, 0.4);
+    let warped = domain_warp_layer(
+        mountain_layer(SETTINGS.mountains_frequency, 7, 2.0, 2.0, 0.4),
+        0.0,
+        4.0,
+    );
+
+    for point in fibonacci_sphere(50) {
+        let mut gradient = Vector3::zero();
+        let value = mountains.sample(&Vector3d::from(&point), &NOISE_SEED, &mut gradient);
+        let mut warped_gradient = Vector3::zero();
+        let warped_value =
+            warped.sample(&Vector3d::from(&point), &NOISE_SEED, &mut warped_gradient);
+
+        assert_eq!(value, warped_value);
+        assert!((&gradient - &warped_gradient).length() <= 1e-6);
+    }
+}
+
+#[test]
+fn warp_displaces_features_within_the_input_bounds() {
+    let warped = domain_warp_layer(
+        mountain_layer(SETTINGS.mountains_frequency, 7, 2.0, 2.0, 0.4),
+        0.02,
+        4.0,
+    );
+    let mountains = mountain_layer(SETTINGS.mountains_frequency, 7, 2.0, 2.0, 0.4);
+    let (low, high) = warped.bounds();
+
+    let mut found_difference = false;
+    for point in fibonacci_sphere(50) {
+        let value = warped.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero());
+        assert!(value >= low && value <= high);
+
+        if (value - mountains.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero()))
+            .abs()
+            > 1e-3
+        {
+            found_difference = true;
+        }
+    }
+
+    assert!(found_difference, "expected the warp to move the mountains");
+}
+
+#[test]
+fn domain_warp_is_available_in_the_terrain_graph() {
+    let graph = TerrainGraph::from_json(
+        r#"{
+            "elevation": {
+                "type": "domain_warp",
+                "strength": 0.02,
+                "frequency": 4.0,
+                "input": { "type": "continent", "frequency": 1.0, "fragmentation": 0.3 }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let layer = graph.elevation.compile();
+    assert_eq!(layer.name(), "domain_warp");
+    assert!(layer.parameters().contains(&("strength", 0.02)));
+}
@@ -0,0 +1,122 @@
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::gradient_check::{check_gradient, fibonacci_sphere};
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::utils::dual::Dual;
+use terrain_generation::utils::math::{
+    s_abs, s_max_gradient, s_min_gradient, smoothstep, tanh_sharpen,
+};
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+/// A field of the coordinates written with duals
+type Expression = fn(Dual, Dual, Dual) -> Dual;
+
+fn assert_close(dual: f32, expected: f32) {
+    assert!(
+        (dual - expected).abs() <= 1e-4 * f32::max(1.0, expected.abs()),
+        "dual {dual} instead of {expected}"
+    );
+}
+
+fn assert_dual_close(dual: Dual, value: f32, gradient: &Vector3) {
+    assert_close(dual.value, value);
+    assert_close(dual.derivatives[0], gradient.x);
+    assert_close(dual.derivatives[1], gradient.y);
+    assert_close(dual.derivatives[2], gradient.z);
+}
+
+#[test]
+fn arithmetic_matches_finite_differences() {
+    let expressions: [(&str, Expression); 6] = [
+        ("polynomial", |x, y, z| x * y + z * z * 3.0 - x / 2.0 + 1.0),
+        ("quotient", |x, y, z| {
+            (x - y) / (z * z + 1.5) + 2.0 / (y + 3.0)
+        }),
+        ("trigonometry", |x, y, z| (x * 3.0).sin() * (y + z).cos()),
+        ("exponential", |x, y, z| (x * y).exp() + (z + 2.0).ln()),
+        ("powers", |x, y, z| {
+            (x * x + y * y + 1.0).sqrt() + (z + 2.0).powf(2.5) - (x - 2.0).powi(3)
+        }),
+        ("tanh", |x, y, z| {
+            (x * 2.0 - y).tanh() * (1.0 - z) + (x + 3.0).recip()
+        }),
+    ];
+
+    for (name, expression) in expressions {
+        let report = check_gradient(name, &fibonacci_sphere(500), 1e-3, &|coords, gradient| {
+            let [x, y, z] = Dual::variables(coords);
+            let result = expression(x, y, z);
+            *gradient += &result.gradient();
+            result.value
+        });
+        assert!(report.max_error <= 1e-2, "{report}");
+    }
+}
+
+#[test]
+fn piecewise_functions_pick_the_gradient_of_their_branch() {
+    let [x, y, _] = Dual::variables(&Vector3::new(-0.5, 0.25, 1.0));
+
+    assert_eq!(x.abs(), -x);
+    assert_eq!(x.min(y), x);
+    assert_eq!(x.max(y), y);
+    assert_eq!(y.clamp(0.0, 0.1), Dual::constant(0.1));
+    assert_eq!(y.clamp(0.0, 1.0), y);
+    assert_eq!((x * 3.0).floor(), Dual::constant(-2.0));
+    assert_eq!(y.smoothstep(0.5, 1.0), Dual::ZERO);
+    assert_eq!(y.smoothstep(-1.0, 0.0), Dual::ONE);
+}
+
+#[test]
+fn smooth_helpers_match_utils_math() {
+    for coords in fibonacci_sphere(100) {
+        let [x, y, z] = Dual::variables(&coords);
+        let u = (x * 2.0 + y).sin() * 0.4 + 0.5;
+        let v = (y - z * 3.0).cos() * 0.3 + 0.5;
+
+        let mut gradient = u.gradient();
+        let value = s_abs(u.value - 0.5, 8.0, &mut gradient);
+        assert_dual_close((u - 0.5).s_abs(8.0), value, &gradient);
+
+        let mut gradient = u.gradient();
+        let value = smoothstep(0.3, 0.7, u.value, &mut gradient);
+        assert_dual_close(u.smoothstep(0.3, 0.7), value, &gradient);
+
+        let mut gradient = u.gradient();
+        let value = tanh_sharpen(u.value, 4.0, &mut gradient);
+        assert_dual_close(u.tanh_sharpen(4.0), value, &gradient);
+
+        let mut gradient = u.gradient();
+        let value = s_min_gradient(u.value, v.value, 8.0, &mut gradient, &v.gradient());
+        assert_dual_close(u.s_min(v, 8.0), value, &gradient);
+
+        let (mut u_gradient, mut v_gradient) = (u.gradient(), v.gradient());
+        let value = s_max_gradient(u.value, v.value, 8.0, &mut u_gradient, &mut v_gradient);
+        assert_dual_close(u.s_max(v, 8.0), value, &(u_gradient + v_gradient));
+    }
+}
+
+#[test]
+fn layers_sampled_as_duals_match_their_samples() {
+    let layers: [Box<dyn TerrainLayer>; 2] = [
+        Box::new(continent_layer(1.0, 0.3)),
+        Box::new(mountain_layer(20.0, 7, 2.0, 2.0, 0.4)),
+    ];
+
+    for layer in &layers {
+        for coords in fibonacci_sphere(50) {
+            let mut gradient = Vector3::zero();
+            let value = layer.sample(&Vector3d::from(&coords), &NOISE_SEED, &mut gradient);
+            assert_dual_close(
+                layer.sample_dual(&Vector3d::from(&coords), &NOISE_SEED),
+                value,
+                &gradient,
+            );
+        }
+    }
+}
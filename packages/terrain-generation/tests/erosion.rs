@@ -0,0 +1,155 @@
+use terrain_generation::landscape::erosion_layer::erosion_layer;
+use terrain_generation::landscape::gradient_check::numerical_gradient;
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::erosion::erosion;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+/// A smooth elevation with a known gradient to feed the erosion
+fn ramp(coords: &Vector3) -> (f32, Vector3) {
+    let direction = Vector3::new(0.3, 0.5, 0.2);
+    (
+        0.5 + 0.4 * Vector3::dot(coords, &direction),
+        &direction * 0.4,
+    )
+}
+
+#[test]
+fn erosion_gradient_matches_finite_differences() {
+    let direction = Vector3::new(0.6, -0.3, 0.2);
+    for point in [
+        Vector3::new(0.1, 0.2, 0.3),
+        Vector3::new(-4.4, 0.6, 12.1),
+        Vector3::new(7.7, -3.3, 0.5),
+    ] {
+        let (_, gradient) = erosion(&Vector3d::from(&point), &direction, 7);
+        let numerical = numerical_gradient(
+            &|p| erosion(&Vector3d::from(p), &direction, 7).0,
+            &point,
+            1e-3,
+        );
+
+        assert!(
+            (&gradient - &numerical).length() <= 2e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn erosion_is_stable_at_planet_scale() {
+    let direction = Vector3::new(0.0, 1.0, 0.0);
+    let far_away = Vector3::new(1.5e6, -2.5e6, 3.5e6);
+
+    let mut values = Vec::new();
+    for i in 0..8 {
+        let point = &far_away + &Vector3::new(i as f32, 0.0, 0.0);
+        let (value, gradient) = erosion(&Vector3d::from(&point), &direction, 7);
+        assert!((-1.0..=1.0).contains(&value));
+        assert!(gradient.length().is_finite());
+        values.push(value);
+    }
+
+    assert!(
+        values
+            .windows(2)
+            .any(|pair| (pair[0] - pair[1]).abs() > 1e-3),
+        "the hash should not degenerate for large cell coordinates"
+    );
+    assert_eq!(
+        erosion(&Vector3d::from(&far_away), &direction, 7).0,
+        values[0]
+    );
+}
+
+#[test]
+fn erosion_layer_gradient_matches_finite_differences() {
+    // with a single octave, the gully direction only depends on the slow variations of the sphere normal
+    let layer = erosion_layer(0.1, 1, 100.0);
+
+    let eroded = |coords: &Vector3, gradient: &mut Vector3| {
+        let (value, value_gradient) = ramp(coords);
+        *gradient = value_gradient;
+        layer.apply(&Vector3d::from(coords), &NOISE_SEED, value, gradient)
+    };
+
+    for point in [
+        Vector3::new(0.1, 0.2, 0.3),
+        Vector3::new(-0.4, 0.6, 0.1),
+        Vector3::new(0.7, -0.3, 0.5),
+    ]
+    .map(|point| point.normalize_to_new())
+    {
+        let mut gradient = Vector3::zero();
+        eroded(&point, &mut gradient);
+        let numerical = numerical_gradient(&|p| eroded(p, &mut Vector3::zero()), &point, 1e-4);
+
+        assert!(
+            (&gradient - &numerical).length() <= 2e-2 * (1.0 + numerical.length()),
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn erosion_strength_controls_the_terrain() {
+    let point = Vector3::new(0.1, 0.2, 0.3);
+    let (value, value_gradient) = ramp(&point);
+
+    let mut gradient = value_gradient.clone();
+    let untouched = erosion_layer(0.0, 5, 20.0).apply(
+        &Vector3d::from(&point),
+        &NOISE_SEED,
+        value,
+        &mut gradient,
+    );
+    assert_eq!(untouched, value);
+    assert!(Vector3::equals(&gradient, &value_gradient));
+
+    let smooth = make_terrain_function(SETTINGS);
+    let eroded = make_terrain_function(TerrainSettings {
+        erosion_strength: 0.2,
+        ..SETTINGS
+    });
+
+    let mut found_difference = false;
+    for i in 0..200 {
+        let theta = i as f32 * 0.37;
+        let unit_sample_point =
+            Vector3::new(f32::cos(theta), f32::sin(1.3 * theta), f32::sin(theta))
+                .normalize_to_new();
+
+        let mut smooth_position = unit_sample_point.clone();
+        smooth(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut smooth_position,
+            &mut Vector3::zero(),
+        );
+        let mut eroded_position = unit_sample_point.clone();
+        eroded(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut eroded_position,
+            &mut Vector3::zero(),
+        );
+
+        if (smooth_position.length() - eroded_position.length()).abs() > 1.0 {
+            found_difference = true;
+            break;
+        }
+    }
+
+    assert!(found_difference, "expected erosion to carve the mountains");
+}
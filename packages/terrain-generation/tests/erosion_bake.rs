@@ -0,0 +1,269 @@
+use std::sync::Mutex;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::erosion_bake::{
+    clear_erosion_bake, set_erosion_bake, ErosionBake, ErosionBakeError, ErosionBakeSettings,
+};
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::seed::Seed;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: u32 = 17;
+
+/// The bake is global, so the tests setting it must not run concurrently
+static GLOBAL_BAKE: Mutex<()> = Mutex::new(());
+
+fn bake_settings() -> ErosionBakeSettings {
+    ErosionBakeSettings {
+        resolution: RESOLUTION,
+        nb_droplets: 2000,
+        droplet_lifetime: 30,
+        thermal_iterations: 5,
+        talus_slope: 0.6,
+    }
+}
+
+fn bake(seed: Seed, bake_settings: &ErosionBakeSettings) -> ErosionBake {
+    ErosionBake::bake(&SETTINGS, seed, PLANET_RADIUS, bake_settings).unwrap()
+}
+
+#[test]
+fn bake_is_deterministic_per_seed() {
+    let deltas = bake(SEED, &bake_settings()).deltas();
+    assert_eq!(deltas.len(), 6 * (RESOLUTION * RESOLUTION) as usize);
+    assert!(deltas.iter().any(|delta| delta.abs() > 1.0));
+
+    assert_eq!(deltas, bake(SEED, &bake_settings()).deltas());
+    assert_ne!(
+        deltas,
+        bake(Seed::from_offset(12.0), &bake_settings()).deltas()
+    );
+}
+
+#[test]
+fn thermal_erosion_conserves_material() {
+    let settings = ErosionBakeSettings {
+        nb_droplets: 0,
+        thermal_iterations: 20,
+        talus_slope: 0.0,
+        ..bake_settings()
+    };
+    let deltas = bake(SEED, &settings).deltas();
+
+    // material is only moved around
+    let total = deltas.iter().sum::<f32>();
+    let moved = deltas.iter().map(|delta| delta.abs()).sum::<f32>();
+    assert!(moved > 1e3);
+    assert!(
+        total.abs() <= 0.05 * moved,
+        "thermal erosion should conserve material: {total} out of {moved}"
+    );
+}
+
+#[test]
+fn delta_gradient_matches_finite_differences() {
+    let erosion = bake(SEED, &bake_settings());
+    let epsilon = 1e-4;
+
+    let points = fibonacci_sphere(300);
+    let nb_matching = points
+        .iter()
+        .filter(|point| {
+            let mut gradient = Vector3::zero();
+            erosion.sample(point, &mut gradient);
+
+            // the delta only varies along the surface
+            let tangent_gradient = &gradient - &(*point * Vector3::dot(&gradient, point));
+            let numerical = [
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, 1.0),
+            ]
+            .iter()
+            .map(|axis| {
+                let offset = axis * epsilon;
+                let forward =
+                    erosion.sample(&(*point + &offset).normalize_to_new(), &mut Vector3::zero());
+                let backward =
+                    erosion.sample(&(*point - &offset).normalize_to_new(), &mut Vector3::zero());
+                (forward - backward) / (2.0 * epsilon)
+            })
+            .collect::<Vec<f32>>();
+            let numerical = Vector3::new(numerical[0], numerical[1], numerical[2]);
+
+            (&tangent_gradient - &numerical).length() <= 1e-2 * (1.0 + numerical.length())
+        })
+        .count();
+
+    // bilinear interpolation is not differentiable on the edges of the cells
+    assert!(
+        nb_matching as f32 >= 0.9 * points.len() as f32,
+        "only {nb_matching} of {} gradients match finite differences",
+        points.len()
+    );
+}
+
+#[test]
+fn delta_is_continuous_across_faces() {
+    let erosion = bake(SEED, &bake_settings());
+
+    for i in 0..50 {
+        let t = -0.9 + 1.8 * i as f32 / 49.0;
+        // on the edge between the +x and +y faces
+        let edge = Vector3::new(1.0, 1.0, t).normalize_to_new();
+        let offset = Vector3::new(1e-6, -1e-6, 0.0);
+        let on_x = erosion.sample(&(&edge + &offset).normalize_to_new(), &mut Vector3::zero());
+        let on_y = erosion.sample(&(&edge - &offset).normalize_to_new(), &mut Vector3::zero());
+
+        assert!(
+            (on_x - on_y).abs() <= 0.1,
+            "seam between faces: {on_x} vs {on_y}"
+        );
+    }
+}
+
+#[test]
+fn deltas_can_be_transferred() {
+    let erosion = bake(SEED, &bake_settings());
+    let copy = ErosionBake::from_deltas(
+        &erosion.terrain_settings(),
+        erosion.seed(),
+        erosion.planet_radius(),
+        erosion.resolution(),
+        erosion.deltas(),
+    )
+    .unwrap();
+
+    for point in fibonacci_sphere(50) {
+        assert_eq!(
+            erosion.sample(&point, &mut Vector3::zero()),
+            copy.sample(&point, &mut Vector3::zero())
+        );
+    }
+}
+
+#[test]
+fn invalid_bakes_are_rejected() {
+    for resolution in [0, 1] {
+        let settings = ErosionBakeSettings {
+            resolution,
+            ..bake_settings()
+        };
+        assert_eq!(
+            ErosionBake::bake(&SETTINGS, SEED, PLANET_RADIUS, &settings).err(),
+            Some(ErosionBakeError::ResolutionTooLow(resolution))
+        );
+        assert_eq!(
+            ErosionBake::from_deltas(&SETTINGS, SEED, PLANET_RADIUS, resolution, vec![0.0; 6])
+                .err(),
+            Some(ErosionBakeError::ResolutionTooLow(resolution))
+        );
+    }
+
+    let invalid_settings = TerrainSettings {
+        tectonic_boundary_width: 0.0,
+        ..SETTINGS
+    };
+    assert!(matches!(
+        ErosionBake::bake(&invalid_settings, SEED, PLANET_RADIUS, &bake_settings()),
+        Err(ErosionBakeError::InvalidSettings(_))
+    ));
+
+    assert_eq!(
+        ErosionBake::from_deltas(&SETTINGS, SEED, PLANET_RADIUS, 2, vec![0.0; 23]).err(),
+        Some(ErosionBakeError::InvalidDeltaBufferSize {
+            length: 23,
+            expected: 24
+        })
+    );
+}
+
+fn build_positions(seed: Seed) -> Vec<f32> {
+    build_positions_with(seed, SETTINGS, PLANET_RADIUS)
+}
+
+fn build_positions_with(
+    seed: Seed,
+    terrain_settings: TerrainSettings,
+    planet_radius: f32,
+) -> Vec<f32> {
+    let build_data = BuildData {
+        planet_diameter: planet_radius * 2.0,
+        chunk_depth: 0,
+        chunk_tree_direction: Direction::Forward,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: 0.0,
+        chunk_cube_position_z: -planet_radius,
+        planet_seed: seed,
+        resolution: 16,
+        terrain_settings,
+    };
+
+    let nb_vertices = 16 * 16;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; 15 * 15 * 6];
+    build_chunk_vertex_data(
+        &build_data,
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut [],
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+    positions
+}
+
+#[test]
+fn chunks_apply_the_bake_of_their_seed() {
+    let _lock = GLOBAL_BAKE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    clear_erosion_bake();
+    let procedural = build_positions(SEED);
+
+    set_erosion_bake(bake(SEED, &bake_settings()));
+    let eroded = build_positions(SEED);
+    assert_ne!(procedural, eroded);
+
+    // another planet is not affected by the bake
+    let other_planet = build_positions(Seed::from_offset(12.0));
+    set_erosion_bake(bake(SEED, &bake_settings()));
+    clear_erosion_bake();
+    assert_eq!(other_planet, build_positions(Seed::from_offset(12.0)));
+    assert_eq!(procedural, build_positions(SEED));
+}
+
+#[test]
+fn chunks_ignore_the_bake_of_other_settings() {
+    let _lock = GLOBAL_BAKE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let other_settings = TerrainSettings {
+        continents_frequency: SETTINGS.continents_frequency * 2.0,
+        ..SETTINGS
+    };
+    let other_radius = PLANET_RADIUS * 2.0;
+
+    clear_erosion_bake();
+    let other_terrain = build_positions_with(SEED, other_settings, PLANET_RADIUS);
+    let other_size = build_positions_with(SEED, SETTINGS, other_radius);
+
+    set_erosion_bake(bake(SEED, &bake_settings()));
+    let with_bake = (
+        build_positions_with(SEED, other_settings, PLANET_RADIUS),
+        build_positions_with(SEED, SETTINGS, other_radius),
+    );
+    clear_erosion_bake();
+
+    assert_eq!(with_bake, (other_terrain, other_size));
+}
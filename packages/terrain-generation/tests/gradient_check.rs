@@ -0,0 +1,433 @@
+use terrain_generation::landscape::bathymetry_layer::bathymetry_layer;
+use terrain_generation::landscape::combinator_layers::{
+    AddLayer, MultiplyLayer, SMaxLayer, ScaleLayer, SmoothstepLayer,
+};
+use terrain_generation::landscape::constant_layers::{constant_layer, one_layer, zero_layer};
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::crater_layer::crater_layer;
+use terrain_generation::landscape::domain_warp_layer::domain_warp_layer;
+use terrain_generation::landscape::erosion_layer::erosion_layer;
+use terrain_generation::landscape::gradient_check::{
+    check_gradient, check_layer, check_terrain_function, fibonacci_sphere, GradientReport,
+};
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::simple_fractal_layer::simple_fractal_layer;
+use terrain_generation::landscape::simplex_noise_layer::simplex_noise_layer;
+use terrain_generation::landscape::tectonic_plates::TectonicPlates;
+use terrain_generation::landscape::terrace_layer::terrace_layer;
+use terrain_generation::landscape::terrain_graph::{TerrainGraph, TerrainNode};
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::landscape::uber_noise_layer::uber_noise_layer;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::math::{
+    add, minimum_value, multiply, pow, s_abs, s_ceil, s_floor, s_max_gradient, s_min_gradient,
+    scale, smoothstep, tanh_sharpen, terrace,
+};
+use terrain_generation::utils::simplex::simplex411_hessian;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const NB_POINTS: usize = 500;
+
+/// Checks a report computed with smooth fields, where only the rounding of f32 separates the gradients
+fn assert_exact(report: GradientReport) {
+    assert!(report.max_error <= 1e-2, "{report}");
+}
+
+/// Checks a report computed with simplex noise, whose 4D kernels leave small jumps at the borders of the simplices,
+/// or with creases like the plate boundaries: the max error points at them, so only the mean and the bulk of the points
+/// are bounded
+fn assert_accurate(report: GradientReport) {
+    assert!(report.mean_error <= 1e-2, "{report}");
+    assert!(report.p95_error <= 3e-2, "{report}");
+}
+
+/// Checks a layer with a step small compared with its shortest wavelength
+fn check(layer: &dyn TerrainLayer) -> GradientReport {
+    let epsilon = 1e-2 / f32::max(layer.max_frequency(), 1.0);
+    check_layer(layer, &NOISE_SEED, &fibonacci_sphere(NB_POINTS), epsilon)
+}
+
+/// A smooth field in [0.1, 0.9] with a known gradient
+fn wave(coords: &Vector3, gradient: &mut Vector3) -> f32 {
+    let a = 3.0 * coords.x + 2.0 * coords.y - coords.z;
+    let b = coords.y + 2.0 * coords.z;
+    *gradient += Vector3::new(3.0, 2.0, -1.0) * (0.4 * f32::cos(a) * f32::cos(b))
+        - Vector3::new(0.0, 1.0, 2.0) * (0.4 * f32::sin(a) * f32::sin(b));
+    0.5 + 0.4 * f32::sin(a) * f32::cos(b)
+}
+
+/// Another smooth field in [0.2, 0.8] with a known gradient
+fn ripple(coords: &Vector3, gradient: &mut Vector3) -> f32 {
+    let c = 2.0 * coords.x + coords.y - 3.0 * coords.z;
+    *gradient += Vector3::new(2.0, 1.0, -3.0) * (-0.3 * f32::sin(c));
+    0.5 + 0.3 * f32::cos(c)
+}
+
+/// Checks a unary helper of `utils::math` applied to the wave
+fn check_unary(name: &str, helper: impl Fn(f32, &mut Vector3) -> f32) -> GradientReport {
+    check_gradient(
+        name,
+        &fibonacci_sphere(NB_POINTS),
+        1e-3,
+        &|coords, gradient| {
+            let mut value_gradient = Vector3::zero();
+            let value = wave(coords, &mut value_gradient);
+            let result = helper(value, &mut value_gradient);
+            *gradient += &value_gradient;
+            result
+        },
+    )
+}
+
+/// Checks a binary helper of `utils::math` applied to the wave and the ripple
+fn check_binary(
+    name: &str,
+    helper: impl Fn(f32, f32, &mut Vector3, &mut Vector3) -> f32,
+) -> GradientReport {
+    check_gradient(
+        name,
+        &fibonacci_sphere(NB_POINTS),
+        1e-3,
+        &|coords, gradient| {
+            let mut u_gradient = Vector3::zero();
+            let u = wave(coords, &mut u_gradient);
+            let mut v_gradient = Vector3::zero();
+            let v = ripple(coords, &mut v_gradient);
+            let result = helper(u, v, &mut u_gradient, &mut v_gradient);
+            *gradient += &u_gradient;
+            result
+        },
+    )
+}
+
+#[test]
+fn the_verifier_reports_wrong_gradients() {
+    let points = fibonacci_sphere(NB_POINTS);
+    let correct = check_gradient("wave", &points, 1e-3, &wave);
+    let wrong = check_gradient("wave", &points, 1e-3, &|coords, gradient| {
+        let mut wave_gradient = Vector3::zero();
+        let value = wave(coords, &mut wave_gradient);
+        *gradient += wave_gradient * 1.5;
+        value
+    });
+
+    assert_exact(correct);
+    assert!(wrong.mean_error > 0.1, "{wrong}");
+    assert!(wrong.p95_error > 0.1, "{wrong}");
+    assert!(wrong.to_string().starts_with("wave: max error"));
+}
+
+#[test]
+fn simplex_second_derivatives_match_finite_differences() {
+    let points: Vec<Vector3> = fibonacci_sphere(NB_POINTS)
+        .iter()
+        .map(|point| point * 3.0)
+        .collect();
+    for axis in 0..3 {
+        let report = check_gradient("simplex_hessian", &points, 1e-3, &|coords, gradient| {
+            let mut noise_gradient = Vector3::zero();
+            let mut hessian = [Vector3::zero(), Vector3::zero(), Vector3::zero()];
+            simplex411_hessian(
+                &Vector3d::from(coords),
+                &NOISE_SEED,
+                &mut noise_gradient,
+                &mut hessian,
+            );
+            *gradient += &hessian[axis];
+            [noise_gradient.x, noise_gradient.y, noise_gradient.z][axis]
+        });
+        assert_accurate(report);
+    }
+}
+
+#[test]
+fn noise_layers_match_finite_differences() {
+    assert_accurate(check(&simplex_noise_layer(1.0, 1, 2.0, 2.0, 1.0)));
+    assert_accurate(check(&simplex_noise_layer(10.0, 8, 1.7, 2.0, 1.0)));
+    assert_accurate(check(&simplex_noise_layer(4.0, 4, 2.0, 2.0, 2.0)));
+    assert_accurate(check(&continent_layer(1.0, 0.3)));
+    assert_accurate(check(&mountain_layer(20.0, 7, 2.0, 2.0, 0.4)));
+    assert_accurate(check(&uber_noise_layer(1.0, 5, 2.0, 2.0, 0.5)));
+    assert_accurate(check(&uber_noise_layer(5.0, 5, 2.0, 2.0, 0.4)));
+    assert_accurate(check(&crater_layer(4.0, 3, 1.0)));
+    assert_accurate(check(&simple_fractal_layer(
+        4.0,
+        5,
+        2.0,
+        2.0,
+        2.0,
+        simplex_noise_layer(1.0, 1, 2.0, 2.0, 1.0),
+    )));
+    assert_accurate(check(&domain_warp_layer(
+        simplex_noise_layer(3.0, 3, 2.0, 2.0, 1.0),
+        0.2,
+        2.0,
+    )));
+}
+
+#[test]
+fn combinator_layers_match_finite_differences() {
+    let noise =
+        || -> Box<dyn TerrainLayer> { Box::new(simplex_noise_layer(2.0, 3, 2.0, 2.0, 1.0)) };
+    let mountains = || -> Box<dyn TerrainLayer> { Box::new(mountain_layer(3.0, 3, 2.0, 2.0, 0.4)) };
+
+    assert_exact(check(&constant_layer(0.7)));
+    assert_exact(check(&zero_layer()));
+    assert_exact(check(&one_layer()));
+    assert_accurate(check(&AddLayer {
+        inputs: vec![noise(), mountains(), Box::new(constant_layer(0.2))],
+    }));
+    assert_accurate(check(&MultiplyLayer {
+        inputs: vec![noise(), mountains()],
+    }));
+    assert_accurate(check(&ScaleLayer {
+        input: noise(),
+        factor: 3.0,
+    }));
+    assert_accurate(check(&SmoothstepLayer {
+        input: noise(),
+        edge0: 0.3,
+        edge1: 0.7,
+    }));
+    assert_accurate(check(&SMaxLayer {
+        a: noise(),
+        b: mountains(),
+        k: 8.0,
+    }));
+}
+
+#[test]
+fn compiled_graphs_match_finite_differences() {
+    let continents = TerrainNode::Smoothstep {
+        input: Box::new(TerrainNode::Continent {
+            frequency: 1.0,
+            fragmentation: 0.3,
+        }),
+        edge0: 0.3,
+        edge1: 0.5,
+    };
+    let graph = TerrainGraph {
+        elevation: TerrainNode::Add {
+            inputs: vec![
+                TerrainNode::Scale {
+                    input: Box::new(continents.clone()),
+                    factor: 17500.0,
+                },
+                TerrainNode::Scale {
+                    input: Box::new(TerrainNode::Multiply {
+                        inputs: vec![
+                            TerrainNode::DomainWarp {
+                                input: Box::new(TerrainNode::UberNoise {
+                                    frequency: 4.0,
+                                    nb_octaves: 4,
+                                    decay: 2.0,
+                                    lacunarity: 2.0,
+                                    threshold: 0.3,
+                                }),
+                                strength: 0.05,
+                                frequency: 4.0,
+                            },
+                            continents,
+                        ],
+                    }),
+                    factor: 8000.0,
+                },
+            ],
+        },
+    };
+
+    assert_accurate(check_terrain_function(
+        "graph",
+        graph.compile().as_ref(),
+        &NOISE_SEED,
+        PLANET_RADIUS,
+        &fibonacci_sphere(NB_POINTS),
+        1e-4,
+    ));
+}
+
+#[test]
+fn tectonic_fields_match_finite_differences() {
+    let plates = TectonicPlates::generate(SEED, 12, 0.05);
+    type Field = fn(&TectonicPlates, &Vector3, &mut Vector3) -> f32;
+    let fields: [(&str, Field); 4] = [
+        ("boundary_distance", |plates, coords, gradient| {
+            let (boundary_distance, boundary_distance_gradient) = plates.boundary_distance(coords);
+            *gradient += &boundary_distance_gradient;
+            boundary_distance
+        }),
+        ("continentalness", |plates, coords, gradient| {
+            let sample = plates.sample(coords);
+            *gradient += &sample.continentalness_gradient;
+            sample.continentalness
+        }),
+        ("collision", |plates, coords, gradient| {
+            let sample = plates.sample(coords);
+            *gradient += &sample.collision_gradient;
+            sample.collision
+        }),
+        ("rifting", |plates, coords, gradient| {
+            let sample = plates.sample(coords);
+            *gradient += &sample.rifting_gradient;
+            sample.rifting
+        }),
+    ];
+
+    for (name, field) in fields {
+        assert_accurate(check_gradient(
+            name,
+            &fibonacci_sphere(NB_POINTS),
+            1e-3,
+            &|coords, gradient| field(&plates, coords, gradient),
+        ));
+    }
+}
+
+#[test]
+fn elevation_layers_match_finite_differences() {
+    let points = fibonacci_sphere(NB_POINTS);
+
+    // the gullies follow the slope of the elevation, which the gradient treats as constant,
+    // so the eroded elevation is a ramp and the erosion a single octave, whose direction barely varies
+    let erosion = erosion_layer(0.1, 1, 100.0);
+    let ramp_direction = Vector3::new(0.3, 0.5, 0.2);
+    let report = check_gradient("erosion", &points, 1e-4, &|coords, gradient| {
+        let mut value_gradient = &ramp_direction * 0.4;
+        let value = 0.5 + 0.4 * Vector3::dot(coords, &ramp_direction);
+        let result = erosion.apply(
+            &Vector3d::from(coords),
+            &NOISE_SEED,
+            value,
+            &mut value_gradient,
+        );
+        *gradient += &value_gradient;
+        result
+    });
+    // the direction still turns with the normal of the sphere, hence the tolerance of the other erosion tests
+    assert!(report.mean_error <= 2e-2, "{report}");
+    assert!(report.p95_error <= 1e-1, "{report}");
+
+    let terraces = terrace_layer(20.0, 8.0, 4.0, 0.5);
+    assert_accurate(check_gradient(
+        "terrace",
+        &points,
+        1e-4,
+        &|coords, gradient| {
+            let mut value_gradient = Vector3::zero();
+            let value = wave(coords, &mut value_gradient);
+            let result = terraces.apply(
+                &Vector3d::from(coords),
+                &NOISE_SEED,
+                0.0,
+                value,
+                &mut value_gradient,
+            );
+            *gradient += &value_gradient;
+            result
+        },
+    ));
+
+    let bathymetry = bathymetry_layer(0.0, 0.05, 1.0);
+    assert_exact(check_gradient(
+        "bathymetry",
+        &points,
+        // the steep slopes of the narrow continental shelf need a finer step
+        1e-4,
+        &|coords, gradient| {
+            let mut noise_gradient = Vector3::zero();
+            let noise = wave(coords, &mut noise_gradient);
+            let mut mask_gradient = noise_gradient.clone();
+            let mask = smoothstep(0.3, 0.5, noise, &mut mask_gradient);
+
+            let mut elevation_gradient = &mask_gradient * 0.5;
+            let elevation = bathymetry.apply(
+                noise,
+                &noise_gradient,
+                mask,
+                &mask_gradient,
+                mask * 0.5,
+                &mut elevation_gradient,
+            );
+            *gradient += &elevation_gradient;
+            elevation
+        },
+    ));
+}
+
+#[test]
+fn terrain_functions_match_finite_differences() {
+    let points = fibonacci_sphere(NB_POINTS);
+    // the erosion is left out: it follows the slope of the mountains, which its gradient treats as constant
+    let full_settings = TerrainSettings {
+        nb_tectonic_plates: 12,
+        terrace_coverage: 0.5,
+        craters_density: 0.3,
+        continental_shelf_depth: 200.0,
+        abyssal_depth: 4e3,
+        ..SETTINGS
+    };
+
+    for (name, settings) in [("default", SETTINGS), ("full", full_settings)] {
+        assert_accurate(check_terrain_function(
+            name,
+            make_terrain_function(settings).as_ref(),
+            &NOISE_SEED,
+            PLANET_RADIUS,
+            &points,
+            2e-5,
+        ));
+    }
+}
+
+#[test]
+fn math_helpers_match_finite_differences() {
+    assert_exact(check_unary("s_ceil", |x, gradient| {
+        s_ceil(x, 0.6, 8.0, gradient)
+    }));
+    assert_exact(check_unary("s_floor", |x, gradient| {
+        s_floor(x, 0.4, 8.0, gradient)
+    }));
+    assert_exact(check_unary("s_abs", |x, gradient| {
+        s_abs(x - 0.5, 8.0, gradient)
+    }));
+    assert_exact(check_unary("tanh_sharpen", |x, gradient| {
+        tanh_sharpen(x, 4.0, gradient)
+    }));
+    assert_exact(check_unary("pow", |x, gradient| pow(x, 2.5, gradient)));
+    assert_exact(check_unary("terrace", |x, gradient| {
+        terrace(x, 4.0, 3.0, gradient)
+    }));
+    assert_exact(check_unary("minimum_value", |x, gradient| {
+        minimum_value(x, 0.2, gradient)
+    }));
+    assert_exact(check_unary("scale", |x, gradient| scale(x, 3.0, gradient)));
+    assert_exact(check_unary("smoothstep", |x, gradient| {
+        smoothstep(0.2, 0.8, x, gradient)
+    }));
+
+    assert_exact(check_binary("add", |u, v, u_gradient, v_gradient| {
+        add(u, v, u_gradient, v_gradient)
+    }));
+    assert_exact(check_binary("multiply", |u, v, u_gradient, v_gradient| {
+        multiply(u, v, u_gradient, v_gradient)
+    }));
+    assert_exact(check_binary(
+        "s_min_gradient",
+        |u, v, u_gradient, v_gradient| s_min_gradient(u, v, 8.0, u_gradient, v_gradient),
+    ));
+    // s_max_gradient weights each gradient by its share of the maximum, the total gradient is their sum
+    assert_exact(check_binary(
+        "s_max_gradient",
+        |u, v, u_gradient, v_gradient| {
+            let value = s_max_gradient(u, v, 8.0, u_gradient, v_gradient);
+            *u_gradient += &*v_gradient;
+            value
+        },
+    ));
+}
@@ -0,0 +1,126 @@
+use terrain_generation::biomes::BIOME_STRIDE;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::splat::{SplatSettings, SPLAT_STRIDE};
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: usize = 24;
+
+/// All the buffers filled by the chunk builder
+#[derive(Debug, PartialEq)]
+struct ChunkBuffers {
+    positions: Vec<f32>,
+    indices: Vec<u16>,
+    normals: Vec<f32>,
+    biomes: Vec<f32>,
+    splat_weights: Vec<f32>,
+    scattered_points: Vec<f32>,
+    nb_instances: usize,
+}
+
+fn build_chunk(terrain_settings: TerrainSettings) -> ChunkBuffers {
+    let build_data = BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 1,
+        chunk_tree_direction: Direction::Up,
+        chunk_cube_position_x: PLANET_RADIUS / 2.0,
+        chunk_cube_position_y: PLANET_RADIUS,
+        chunk_cube_position_z: PLANET_RADIUS / 2.0,
+        planet_seed: SEED,
+        resolution: RESOLUTION as u32,
+        terrain_settings,
+    };
+
+    let nb_vertices = RESOLUTION * RESOLUTION;
+    let mut chunk = ChunkBuffers {
+        positions: vec![0.0; nb_vertices * 3],
+        indices: vec![0; (RESOLUTION - 1) * (RESOLUTION - 1) * 6],
+        normals: vec![0.0; nb_vertices * 3],
+        biomes: vec![0.0; nb_vertices * BIOME_STRIDE],
+        splat_weights: vec![0.0; nb_vertices * SPLAT_STRIDE],
+        scattered_points: vec![0.0; 6 * 2000],
+        nb_instances: 0,
+    };
+    chunk.nb_instances = build_chunk_vertex_data(
+        &build_data,
+        &mut chunk.positions,
+        &mut chunk.indices,
+        &mut chunk.normals,
+        &mut chunk.biomes,
+        &mut chunk.splat_weights,
+        &mut chunk.scattered_points,
+        1e-9,
+    )
+    .unwrap()
+    .nb_instances_created;
+    chunk
+}
+
+/// The test settings without snow, so that the grass carries the scattered instances
+fn smooth_settings() -> TerrainSettings {
+    TerrainSettings {
+        splat: SplatSettings {
+            equator_snow_line: 100e3,
+            ..SplatSettings::DEFAULT
+        },
+        ..SETTINGS
+    }
+}
+
+fn tectonic_settings() -> TerrainSettings {
+    TerrainSettings {
+        nb_tectonic_plates: 12,
+        ..smooth_settings()
+    }
+}
+
+#[test]
+fn chunks_built_concurrently_match_chunks_built_alone() {
+    let smooth = build_chunk(smooth_settings());
+    let tectonic = build_chunk(tectonic_settings());
+    assert!(smooth.nb_instances > 0);
+    assert_ne!(smooth.positions, tectonic.positions);
+
+    // the threads share the terrain cache, which must tell the settings of the planets apart
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                scope.spawn(move || {
+                    if i % 2 == 0 {
+                        build_chunk(smooth_settings())
+                    } else {
+                        build_chunk(tectonic_settings())
+                    }
+                })
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            let chunk = handle.join().expect("the chunk should be built");
+            let expected = if i % 2 == 0 { &smooth } else { &tectonic };
+            assert_eq!(&chunk, expected);
+        }
+    });
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn chunks_do_not_depend_on_the_number_of_threads() {
+    let build_with_threads = |nb_threads: usize| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(nb_threads)
+            .build()
+            .expect("the thread pool should be created")
+            .install(|| build_chunk(tectonic_settings()))
+    };
+
+    let sequential = build_with_threads(1);
+    for nb_threads in [2, 3, 8] {
+        assert_eq!(build_with_threads(nb_threads), sequential);
+    }
+}
@@ -0,0 +1,141 @@
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: usize = 17;
+
+/// The vertex positions of a deep chunk of a flat planet, relative to the chunk
+fn deep_chunk_positions(planet_radius: f32, depth: u32) -> Vec<Vector3> {
+    let chunk_size = planet_radius * 2.0 / 2f32.powi(depth as i32);
+    let build_data = BuildData {
+        planet_diameter: planet_radius * 2.0,
+        chunk_depth: depth,
+        chunk_tree_direction: Direction::Up,
+        // away from the center of the face so that all the coordinates are large
+        chunk_cube_position_x: planet_radius / 2.0 + chunk_size / 2.0,
+        chunk_cube_position_y: planet_radius,
+        chunk_cube_position_z: planet_radius / 2.0 + chunk_size / 2.0,
+        planet_seed: SEED,
+        resolution: RESOLUTION as u32,
+        terrain_settings: TerrainSettings {
+            continent_base_height: 0.0,
+            max_mountain_height: 0.0,
+            max_bump_height: 0.0,
+            max_ridge_height: 0.0,
+            max_trench_depth: 0.0,
+            ..SETTINGS
+        },
+    };
+
+    let nb_vertices = RESOLUTION * RESOLUTION;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (RESOLUTION - 1) * (RESOLUTION - 1) * 6];
+    build_chunk_vertex_data(
+        &build_data,
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut [],
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    positions
+        .chunks(3)
+        .map(|position| Vector3::new(position[0], position[1], position[2]))
+        .collect()
+}
+
+/// The distances between the neighbouring vertices along the rows of the chunk
+fn row_distances(positions: &[Vector3]) -> Vec<f32> {
+    (0..RESOLUTION)
+        .flat_map(|x| {
+            (1..RESOLUTION).map(move |y| {
+                (&positions[x * RESOLUTION + y] - &positions[x * RESOLUTION + y - 1]).length()
+            })
+        })
+        .collect()
+}
+
+/// Checks that the grid is not distorted by the rounding of the positions
+fn assert_regular(distances: &[f32]) {
+    let mean = distances.iter().sum::<f32>() / distances.len() as f32;
+    assert!(mean > 0.0);
+    for distance in distances {
+        assert!(
+            (distance - mean).abs() < 0.01 * mean,
+            "distance {} between neighbouring vertices instead of {}",
+            distance,
+            mean
+        );
+    }
+}
+
+#[test]
+fn deep_chunks_keep_a_regular_grid() {
+    // at this depth the vertices are 3 cm apart, less than the precision of f32 positions on the planet
+    let positions = deep_chunk_positions(PLANET_RADIUS, 22);
+    assert_regular(&row_distances(&positions));
+}
+
+#[test]
+fn gas_giants_keep_a_regular_grid() {
+    // the vertices are 8 m apart, about the precision of f32 positions on the planet
+    let positions = deep_chunk_positions(70_000e3, 20);
+    assert_regular(&row_distances(&positions));
+}
+
+#[test]
+fn terrain_is_smooth_between_close_directions() {
+    // the directions are 2 cm apart on the planet, less than the precision of f32 directions
+    // with hills rather than mountains, so that the rounding of the elevation does not hide the steps
+    let terrain = make_terrain_function(TerrainSettings {
+        continent_base_height: 200.0,
+        max_mountain_height: 300.0,
+        max_bump_height: 50.0,
+        ..SETTINGS
+    });
+    let start = Vector3d::new(0.6, 0.64, 0.48);
+    let step = Vector3d::new(0.0, 0.6, -0.8);
+    let elevations: Vec<f32> = (0..RESOLUTION)
+        .map(|i| {
+            let direction = (&start + &(&step * (2.3e-8 * i as f64))).normalize_to_new();
+            let mut position = Vector3::zero();
+            terrain(
+                &direction,
+                &NOISE_SEED,
+                0.0,
+                &mut position,
+                &mut Vector3::zero(),
+            );
+            Vector3::dot(&position, &direction.to_f32())
+        })
+        .collect();
+
+    let differences: Vec<f32> = elevations
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect();
+    let mean = differences.iter().sum::<f32>() / differences.len() as f32;
+    // the elevation itself is rounded to f32, so each step is only exact up to an ulp
+    let tolerance = 2.0 * elevations[0].abs() * f32::EPSILON;
+    for difference in differences {
+        assert!(
+            (difference - mean).abs() <= tolerance,
+            "elevation steps by {} between close directions instead of {}",
+            difference,
+            mean
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use terrain_generation::seed::{NoiseSeed, Salt, Seed};
+use terrain_generation::utils::simplex::simplex401;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+fn sample_points() -> Vec<Vector3> {
+    (0..1000)
+        .map(|i| {
+            let i = i as f32;
+            Vector3::new(
+                f32::sin(i * 0.37) * 7.0,
+                f32::cos(i * 0.11) * 7.0,
+                i * 0.013,
+            )
+        })
+        .collect()
+}
+
+fn resolved_noise(seed: &NoiseSeed) -> Vec<f32> {
+    sample_points()
+        .iter()
+        .map(|point| simplex401(&Vector3d::from(point), seed, &mut Vector3::zero()))
+        .collect()
+}
+
+fn noise(seed: Seed) -> Vec<f32> {
+    resolved_noise(&NoiseSeed::new(seed))
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / a.len() as f32;
+    let mean_b = b.iter().sum::<f32>() / b.len() as f32;
+    let covariance = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (x - mean_a) * (y - mean_b))
+        .sum::<f32>();
+    let variance_a = a.iter().map(|x| (x - mean_a).powi(2)).sum::<f32>();
+    let variance_b = b.iter().map(|y| (y - mean_b).powi(2)).sum::<f32>();
+    covariance / f32::sqrt(variance_a * variance_b)
+}
+
+#[test]
+fn integer_seeds_are_deterministic() {
+    let seed = Seed::from_integer(42);
+    assert_eq!(noise(seed), noise(Seed::from_integer(42)));
+    assert!(noise(seed).iter().all(|value| (0.0..=1.0).contains(value)));
+}
+
+#[test]
+fn nearby_integer_seeds_are_not_correlated() {
+    for seed in [1, 1 << 40] {
+        let correlation = correlation(
+            &noise(Seed::from_integer(seed)),
+            &noise(Seed::from_integer(seed + 1)),
+        );
+        assert!(correlation.abs() < 0.2, "correlation {correlation}");
+    }
+}
+
+#[test]
+fn sub_seeds_are_not_correlated() {
+    let seed = Seed::from_integer(7);
+    assert_ne!(seed.derive(Salt::Continents), seed.derive(Salt::Mountains));
+    assert_eq!(
+        seed.derive(Salt::Continents),
+        Seed::from_integer(7).derive(Salt::Continents)
+    );
+
+    let correlation = correlation(
+        &noise(seed.derive(Salt::Continents)),
+        &noise(seed.derive(Salt::Mountains)),
+    );
+    assert!(correlation.abs() < 0.2, "correlation {correlation}");
+}
+
+#[test]
+fn offset_seeds_keep_the_legacy_noise() {
+    // sub-seeds of offset seeds are left untouched so that existing planets do not change
+    assert_eq!(SEED.derive(Salt::Continents), SEED);
+    assert_eq!(SEED.offset(), 11.0);
+    assert_eq!(SEED.bits(), 11f32.to_bits());
+    assert_eq!(Seed::from_integer(11).offset(), 0.0);
+}
+
+#[test]
+fn resolved_sub_seeds_match_derived_seeds() {
+    for seed in [SEED, Seed::from_integer(7)] {
+        let resolved = NoiseSeed::new(seed);
+        assert_eq!(
+            resolved.derive(Salt::Mountains).seed(),
+            seed.derive(Salt::Mountains)
+        );
+        assert_eq!(
+            resolved_noise(resolved.derive(Salt::Mountains)),
+            noise(seed.derive(Salt::Mountains))
+        );
+        assert!(std::ptr::eq(
+            resolved.derive(Salt::Mountains),
+            resolved.derive(Salt::Mountains)
+        ));
+    }
+}
+
+#[test]
+fn offset_seeds_migrate_to_uncorrelated_sub_seeds() {
+    let migrated = SEED.to_integer();
+    assert_eq!(migrated, SEED.to_integer());
+    assert_eq!(migrated.to_integer(), migrated);
+    assert_ne!(
+        migrated.derive(Salt::Continents),
+        migrated.derive(Salt::Mountains)
+    );
+
+    let correlation = correlation(
+        &noise(migrated.derive(Salt::Continents)),
+        &noise(migrated.derive(Salt::Mountains)),
+    );
+    assert!(correlation.abs() < 0.2, "correlation {correlation}");
+}
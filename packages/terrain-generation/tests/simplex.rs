@@ -1,6 +1,8 @@
 use float_eq::assert_float_eq;
 use image::{ImageBuffer, Luma, Rgb};
+use terrain_generation::seed::{NoiseSeed, Seed};
 use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
 
 use terrain_generation::utils::simplex;
 
@@ -29,7 +31,11 @@ pub fn simplex() {
             );
             let mut gradient_sphere = Vector3::new(0.0, 0.0, 0.0);
 
-            let noise_value_sphere = simplex401(&sample_point_sphere, SEED, &mut gradient_sphere);
+            let noise_value_sphere = simplex401(
+                &Vector3d::from(&sample_point_sphere),
+                &NOISE_SEED,
+                &mut gradient_sphere,
+            );
 
             let image_value_sphere = (noise_value_sphere * 255.0) as u8;
             *(value_sphere_image.get_pixel_mut(u, v)) = Luma([image_value_sphere]);
@@ -44,7 +50,8 @@ pub fn simplex() {
 
             let mut gradient = Vector3::new(0.0, 0.0, 0.0);
             let sample_point = Vector3::new(u as f32 / 10.0, v as f32 / 10.0, 0.0);
-            let noise_value = simplex::simplex401(&sample_point, SEED, &mut gradient);
+            let noise_value =
+                simplex::simplex401(&Vector3d::from(&sample_point), &NOISE_SEED, &mut gradient);
 
             let image_value = (noise_value * 255.0) as u8;
             *(value_image.get_pixel_mut(u, v)) = Luma([image_value]);
@@ -72,13 +79,14 @@ pub fn simplex() {
 #[test]
 pub fn average_simplex() {
     let nb_samples = 100;
+    let seed = NoiseSeed::new(Seed::from_offset(0.0));
     let mut acc = 0.0;
     for x in 0..nb_samples {
         for y in 0..nb_samples {
             for z in 0..nb_samples {
                 acc += simplex401(
-                    &Vector3::new(x as f32, y as f32, z as f32),
-                    0.0,
+                    &Vector3d::from(&Vector3::new(x as f32, y as f32, z as f32)),
+                    &seed,
                     &mut Vector3::zero(),
                 );
             }
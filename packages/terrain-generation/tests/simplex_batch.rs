@@ -0,0 +1,194 @@
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::crater_layer::crater_layer;
+use terrain_generation::landscape::make_terrain_function::{
+    make_terrain_function, make_terrain_row_function,
+};
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::simplex_noise_layer::simplex_noise_layer;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::seed::{NoiseSeed, Seed};
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::simplex::{simplex401, simplex411};
+use terrain_generation::utils::simplex_batch::{
+    simplex401_batch, simplex401_lanes, simplex411_batch, simplex411_lanes, NoiseBatch, LANES,
+};
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+// the batched path performs the same operations as the scalar one, only the SIMD backend may round differently
+const TOLERANCE: f32 = 1e-5;
+
+const SEEDS: [Seed; 2] = [SEED, Seed::from_integer(42)];
+
+/// Points on a sphere with a few lattice points where the simplex ordering has ties
+fn sample_points(nb_points: usize) -> Vec<Vector3d> {
+    let mut points: Vec<Vector3d> = (0..nb_points)
+        .map(|i| {
+            let i = i as f64;
+            let theta = i * 0.37;
+            let phi = i * 0.11;
+            &Vector3d::new(
+                f64::sin(theta) * f64::cos(phi),
+                f64::cos(theta),
+                f64::sin(theta) * f64::sin(phi),
+            ) * 7.0
+        })
+        .collect();
+    points.extend([
+        Vector3d::zero(),
+        Vector3d::new(1.0, 1.0, 1.0),
+        Vector3d::new(-2.0, 3.0, -2.0),
+    ]);
+    points
+}
+
+/// The sample points moved to the unit sphere like the inputs of the layers
+fn unit_sphere_points(nb_points: usize) -> Vec<Vector3d> {
+    sample_points(nb_points)
+        .iter()
+        .filter(|point| point.length() > 0.0)
+        .map(|point| point.normalize_to_new())
+        .collect()
+}
+
+fn assert_close(batched: f32, scalar: f32) {
+    assert!(
+        (batched - scalar).abs() <= TOLERANCE * f32::max(1.0, scalar.abs()),
+        "batched {batched} instead of {scalar}"
+    );
+}
+
+fn assert_vector_close(batched: &Vector3, scalar: &Vector3) {
+    assert_close(batched.x, scalar.x);
+    assert_close(batched.y, scalar.y);
+    assert_close(batched.z, scalar.z);
+}
+
+#[test]
+fn lanes_match_the_scalar_noise() {
+    type Lanes = fn(&[f64; LANES], &[f64; LANES], &[f64; LANES], &NoiseSeed, &mut NoiseBatch);
+    type Scalar = fn(&Vector3d, &NoiseSeed, &mut Vector3) -> f32;
+    let noises: [(Lanes, Scalar); 2] = [
+        (simplex401_lanes, simplex401),
+        (simplex411_lanes, simplex411),
+    ];
+
+    let points = sample_points(400);
+    for seed in &SEEDS.map(NoiseSeed::new) {
+        for (lanes, scalar) in noises {
+            for batch_points in points.chunks_exact(LANES) {
+                let mut batch = NoiseBatch::zero();
+                lanes(
+                    &std::array::from_fn(|lane| batch_points[lane].x),
+                    &std::array::from_fn(|lane| batch_points[lane].y),
+                    &std::array::from_fn(|lane| batch_points[lane].z),
+                    seed,
+                    &mut batch,
+                );
+
+                for (lane, point) in batch_points.iter().enumerate() {
+                    let mut gradient = Vector3::zero();
+                    let value = scalar(point, seed, &mut gradient);
+                    assert_close(batch.values[lane], value);
+                    assert_vector_close(
+                        &Vector3::new(
+                            batch.gradients_x[lane],
+                            batch.gradients_y[lane],
+                            batch.gradients_z[lane],
+                        ),
+                        &gradient,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn batches_of_any_length_match_the_scalar_noise() {
+    type Batch = fn(&[Vector3d], &NoiseSeed, &mut [f32], &mut [Vector3]);
+    type Scalar = fn(&Vector3d, &NoiseSeed, &mut Vector3) -> f32;
+    let noises: [(Batch, Scalar); 2] = [
+        (simplex401_batch, simplex401),
+        (simplex411_batch, simplex411),
+    ];
+
+    for seed in &SEEDS.map(NoiseSeed::new) {
+        for (batch, scalar) in noises {
+            // the lengths that do not fill the last batch are padded
+            for nb_points in [1, 3, LANES, 33] {
+                let points = sample_points(nb_points);
+                let mut values = vec![0.0; points.len()];
+                let mut gradients = vec![Vector3::new(5.0, 5.0, 5.0); points.len()];
+                batch(&points, seed, &mut values, &mut gradients);
+
+                for (point, (value, gradient)) in points.iter().zip(values.iter().zip(&gradients)) {
+                    let mut scalar_gradient = Vector3::zero();
+                    assert_close(*value, scalar(point, seed, &mut scalar_gradient));
+                    assert_vector_close(gradient, &scalar_gradient);
+                }
+            }
+        }
+    }
+}
+
+fn assert_batch_matches_scalar(layer: &dyn TerrainLayer) {
+    let points = unit_sphere_points(61);
+
+    for seed in &SEEDS.map(NoiseSeed::new) {
+        let mut values = vec![0.0; points.len()];
+        let mut gradients = vec![Vector3::zero(); points.len()];
+        layer.sample_batch(&points, seed, &mut values, &mut gradients);
+
+        for (point, (value, gradient)) in points.iter().zip(values.iter().zip(&gradients)) {
+            let mut scalar_gradient = Vector3::zero();
+            assert_close(*value, layer.sample(point, seed, &mut scalar_gradient));
+            assert_vector_close(gradient, &scalar_gradient);
+        }
+    }
+}
+
+#[test]
+fn batched_layers_match_their_scalar_samples() {
+    assert_batch_matches_scalar(&continent_layer(1.0, 0.3));
+    assert_batch_matches_scalar(&mountain_layer(20.0, 7, 2.0, 2.0, 0.4));
+    assert_batch_matches_scalar(&simplex_noise_layer(10.0, 8, 1.7, 2.0, 1.0));
+    // layers without a batched path fall back to their scalar samples
+    assert_batch_matches_scalar(&crater_layer(4.0, 6, 0.5));
+}
+
+#[test]
+fn terrain_rows_match_the_terrain_function() {
+    let settings = TerrainSettings {
+        nb_tectonic_plates: 12,
+        terrace_coverage: 0.5,
+        erosion_strength: 0.5,
+        ..SETTINGS
+    };
+    let terrain = make_terrain_function(settings);
+    let terrain_row = make_terrain_row_function(settings);
+
+    let points = unit_sphere_points(45);
+    let mut positions: Vec<Vector3> = points.iter().map(Vector3d::to_f32).collect();
+    let mut gradients = vec![Vector3::zero(); points.len()];
+    terrain_row(&points, &NOISE_SEED, 0.0, &mut positions, &mut gradients);
+
+    for (point, (position, gradient)) in points.iter().zip(positions.iter().zip(&gradients)) {
+        let mut scalar_position = point.to_f32();
+        let mut scalar_gradient = Vector3::zero();
+        terrain(
+            point,
+            &NOISE_SEED,
+            0.0,
+            &mut scalar_position,
+            &mut scalar_gradient,
+        );
+
+        assert_vector_close(position, &scalar_position);
+        assert_vector_close(gradient, &scalar_gradient);
+    }
+}
@@ -0,0 +1,269 @@
+use terrain_generation::base_shape::BaseShape;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::chunk_layout::SCATTER_STRIDE;
+use terrain_generation::splat::{splat_weights, SplatSettings, GRASS_WEIGHT, SPLAT_STRIDE};
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const ROCK: usize = 0;
+const SAND: usize = 1;
+const GRASS: usize = 2;
+const SNOW: usize = 3;
+
+fn equator() -> Vector3 {
+    Vector3::new(1.0, 0.0, 0.0)
+}
+
+#[test]
+fn steep_slopes_are_rock() {
+    let settings = SplatSettings::new();
+    let steep_normal = Vector3::new(1.0, 1.0, 0.0).normalize_to_new();
+
+    let weights = splat_weights(&settings, &equator(), &steep_normal, 1e3);
+    assert_eq!(weights[ROCK], 1.0);
+
+    let weights = splat_weights(&settings, &equator(), &equator(), 1e3);
+    assert_eq!(weights[ROCK], 0.0);
+    assert_eq!(weights[GRASS], 1.0);
+}
+
+#[test]
+fn beaches_and_sea_beds_are_sand() {
+    let settings = SplatSettings::new();
+
+    let sea_bed = splat_weights(&settings, &equator(), &equator(), -100.0);
+    assert_eq!(sea_bed[SAND], 1.0);
+
+    let beach = splat_weights(&settings, &equator(), &equator(), 20.0);
+    assert!(beach[SAND] > 0.5 && beach[GRASS] > 0.0);
+
+    let inland = splat_weights(&settings, &equator(), &equator(), settings.beach_height);
+    assert_eq!(inland[GRASS], 1.0);
+}
+
+#[test]
+fn the_snow_line_goes_down_toward_the_poles() {
+    let settings = SplatSettings::new();
+    let high_latitude = Vector3::new(0.3, 1.0, 0.0).normalize_to_new();
+    let elevation = 2e3;
+
+    let equator_weights = splat_weights(&settings, &equator(), &equator(), elevation);
+    let polar_weights = splat_weights(&settings, &high_latitude, &high_latitude, elevation);
+    assert_eq!(equator_weights[SNOW], 0.0);
+    assert!(polar_weights[SNOW] > 0.99);
+
+    let summit_weights = splat_weights(&settings, &equator(), &equator(), 5e3);
+    assert_eq!(summit_weights[SNOW], 1.0);
+}
+
+const RESOLUTION: usize = 16;
+
+/// A chunk covering a face of the planet
+/// * `direction` - The face of the chunk
+/// * `cube_position` - The center of the face
+fn face_chunk(direction: Direction, cube_position: Vector3) -> BuildData {
+    BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 0,
+        chunk_tree_direction: direction,
+        chunk_cube_position_x: cube_position.x,
+        chunk_cube_position_y: cube_position.y,
+        chunk_cube_position_z: cube_position.z,
+        planet_seed: SEED,
+        resolution: RESOLUTION as u32,
+        terrain_settings: SETTINGS,
+    }
+}
+
+#[test]
+fn chunks_fill_the_splat_buffer() {
+    let nb_vertices = RESOLUTION * RESOLUTION;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (RESOLUTION - 1) * (RESOLUTION - 1) * 6];
+    let mut splat = vec![-1.0; nb_vertices * SPLAT_STRIDE];
+    build_chunk_vertex_data(
+        &face_chunk(Direction::Up, Vector3::new(0.0, PLANET_RADIUS, 0.0)),
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut splat,
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    let chunk_position = Vector3::new(0.0, PLANET_RADIUS, 0.0);
+    for vertex_index in 0..nb_vertices {
+        let weights = &splat[SPLAT_STRIDE * vertex_index..SPLAT_STRIDE * (vertex_index + 1)];
+        assert!(weights.iter().all(|weight| (0.0..=1.0).contains(weight)));
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+        let position = &chunk_position
+            + &Vector3::new(
+                positions[3 * vertex_index],
+                positions[3 * vertex_index + 1],
+                positions[3 * vertex_index + 2],
+            );
+        let normal = Vector3::new(
+            normals[3 * vertex_index],
+            normals[3 * vertex_index + 1],
+            normals[3 * vertex_index + 2],
+        );
+        let expected = splat_weights(
+            &SETTINGS.splat,
+            &position.normalize_to_new(),
+            &normal,
+            position.length() - PLANET_RADIUS - SETTINGS.sea_level,
+        );
+        for (weight, expected) in weights.iter().zip(expected) {
+            assert!((weight - expected).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn splat_heights_are_measured_from_the_base_shape() {
+    let nb_vertices = RESOLUTION * RESOLUTION;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (RESOLUTION - 1) * (RESOLUTION - 1) * 6];
+    let mut splat = vec![0.0; nb_vertices * SPLAT_STRIDE];
+
+    // the chunk lies 200 km above the planet radius, where a height measured from it would only give snow
+    let settings = TerrainSettings {
+        continent_base_height: 200.0,
+        max_mountain_height: 300.0,
+        max_bump_height: 50.0,
+        sea_level: 260.0,
+        base_shape: BaseShape::ellipsoid(1.0, 1.0, 1.2),
+        ..SETTINGS
+    };
+    let chunk_position = Vector3::new(0.0, 0.0, -PLANET_RADIUS);
+    build_chunk_vertex_data(
+        &BuildData {
+            terrain_settings: settings,
+            ..face_chunk(Direction::Forward, chunk_position.clone())
+        },
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut splat,
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    let mut has_grass = false;
+    for vertex_index in 0..nb_vertices {
+        let position = &chunk_position
+            + &Vector3::new(
+                positions[3 * vertex_index],
+                positions[3 * vertex_index + 1],
+                positions[3 * vertex_index + 2],
+            );
+        let normal = Vector3::new(
+            normals[3 * vertex_index],
+            normals[3 * vertex_index + 1],
+            normals[3 * vertex_index + 2],
+        );
+        let direction = position.normalize_to_new();
+        let base_radius = PLANET_RADIUS
+            * settings.base_shape.precise_radius(
+                &Vector3d::from(&direction),
+                &NOISE_SEED,
+                &mut Vector3::zero(),
+            ) as f32;
+
+        let weights = &splat[SPLAT_STRIDE * vertex_index..SPLAT_STRIDE * (vertex_index + 1)];
+        let expected = splat_weights(
+            &settings.splat,
+            &direction,
+            &normal,
+            position.length() - base_radius - settings.sea_level,
+        );
+        for (weight, expected) in weights.iter().zip(expected) {
+            assert!((weight - expected).abs() < 1e-2);
+        }
+        has_grass |= weights[GRASS] > 0.5;
+    }
+    assert!(has_grass);
+}
+
+#[test]
+fn instances_are_scattered_on_the_grass() {
+    let scatter_per_square_meter = 1e-9;
+    let nb_vertices = RESOLUTION * RESOLUTION;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (RESOLUTION - 1) * (RESOLUTION - 1) * 6];
+    let mut splat = vec![0.0; nb_vertices * SPLAT_STRIDE];
+    let mut scatter = vec![0.0; 100_000 * SCATTER_STRIDE];
+
+    // hills around the equator, so that the snow does not cover the grass, half drowned to get beaches too
+    let build_data = BuildData {
+        terrain_settings: TerrainSettings {
+            continent_base_height: 200.0,
+            max_mountain_height: 300.0,
+            max_bump_height: 50.0,
+            sea_level: 260.0,
+            ..SETTINGS
+        },
+        ..face_chunk(Direction::Forward, Vector3::new(0.0, 0.0, -PLANET_RADIUS))
+    };
+    let mut nb_instances = |splat: &mut [f32]| {
+        build_chunk_vertex_data(
+            &build_data,
+            &mut positions,
+            &mut indices,
+            &mut normals,
+            &mut [],
+            splat,
+            &mut scatter,
+            scatter_per_square_meter,
+        )
+        .unwrap()
+        .nb_instances_created as f32
+    };
+    let without_weights = nb_instances(&mut []);
+    let with_weights = nb_instances(&mut splat);
+
+    // the density of each triangle is scaled by the grass weight of its vertices
+    let vertex = |index: u16| {
+        let index = index as usize;
+        Vector3::new(
+            positions[3 * index],
+            positions[3 * index + 1],
+            positions[3 * index + 2],
+        )
+    };
+    let (mut area, mut grass_area) = (0.0, 0.0);
+    for triangle in indices.chunks(3) {
+        let triangle_area = Vector3::cross(
+            &(&vertex(triangle[1]) - &vertex(triangle[0])),
+            &(&vertex(triangle[2]) - &vertex(triangle[0])),
+        )
+        .length()
+            / 2.0;
+        let grass = triangle
+            .iter()
+            .map(|&index| splat[SPLAT_STRIDE * index as usize + GRASS_WEIGHT])
+            .sum::<f32>()
+            / 3.0;
+        area += triangle_area;
+        grass_area += triangle_area * grass;
+    }
+
+    assert!(grass_area > 0.1 * area && grass_area < 0.9 * area);
+    assert!((without_weights - area * scatter_per_square_meter).abs() <= 1.0);
+    assert!((with_weights - grass_area * scatter_per_square_meter).abs() <= 1.0);
+}
@@ -0,0 +1,190 @@
+use terrain_generation::landscape::gradient_check::{fibonacci_sphere, numerical_gradient};
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::tectonic_plates::{TectonicPlates, TectonicSample};
+use terrain_generation::seed::Seed;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+const NB_PLATES: u32 = 12;
+const BOUNDARY_WIDTH: f32 = 0.05;
+
+/// Extracts a field and its gradient from a sample
+type Field = fn(&TectonicSample) -> (f32, Vector3);
+
+#[test]
+fn tectonic_gradients_match_finite_differences() {
+    let plates = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+
+    let fields: [(&str, Field); 3] = [
+        ("continentalness", |sample| {
+            (
+                sample.continentalness,
+                sample.continentalness_gradient.clone(),
+            )
+        }),
+        ("collision", |sample| {
+            (sample.collision, sample.collision_gradient.clone())
+        }),
+        ("rifting", |sample| {
+            (sample.rifting, sample.rifting_gradient.clone())
+        }),
+    ];
+
+    for (name, field) in fields {
+        for point in fibonacci_sphere(300) {
+            let (_, gradient) = field(&plates.sample(&point));
+            let numerical = numerical_gradient(&|p| field(&plates.sample(p)).0, &point, 1e-3);
+
+            assert!(
+                (&gradient - &numerical).length() <= 2e-2 * (1.0 + numerical.length()),
+                "{name}: analytic gradient {:?} differs from numerical gradient {:?}",
+                gradient,
+                numerical
+            );
+        }
+    }
+}
+
+#[test]
+fn boundary_distance_is_measured_from_the_closest_boundary() {
+    let plates = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+
+    for point in fibonacci_sphere(500) {
+        let (boundary_distance, boundary_distance_gradient) = plates.boundary_distance(&point);
+        assert!(boundary_distance >= 0.0);
+
+        // moving toward the boundary crosses it at the predicted distance
+        let toward_boundary = &point - &(&boundary_distance_gradient * boundary_distance);
+        let crossed = &toward_boundary - &(&boundary_distance_gradient * 1e-3);
+        assert_ne!(plates.plate_index(&crossed), plates.plate_index(&point));
+    }
+}
+
+#[test]
+fn plates_are_continental_or_oceanic() {
+    let plates = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+    assert_eq!(plates.plates.len(), NB_PLATES as usize);
+    assert!(plates.plates.iter().any(|plate| plate.is_oceanic));
+    assert!(plates.plates.iter().any(|plate| !plate.is_oceanic));
+
+    for (index, plate) in plates.plates.iter().enumerate() {
+        let sample = plates.sample(&plate.center);
+        assert_eq!(plates.plate_index(&plate.center), index);
+
+        // boundaries are far from the center of the plates
+        let expected = if plate.is_oceanic { 0.0 } else { 1.0 };
+        if plates.boundary_distance(&plate.center).0 > 2.0 * BOUNDARY_WIDTH {
+            assert!((sample.continentalness - expected).abs() < 0.2);
+            assert!(sample.collision + sample.rifting < 0.2);
+        }
+    }
+}
+
+#[test]
+fn plates_are_deterministic_per_seed() {
+    let plates = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+
+    let other_plates = TectonicPlates::generate(Seed::from_offset(12.0), NB_PLATES, BOUNDARY_WIDTH);
+    assert!(!Vector3::equals(
+        &plates.plates[0].center,
+        &other_plates.plates[0].center
+    ));
+
+    let regenerated = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+    for (plate, regenerated_plate) in plates.plates.iter().zip(&regenerated.plates) {
+        assert!(Vector3::equals(&plate.center, &regenerated_plate.center));
+        assert!(Vector3::equals(
+            &plate.angular_velocity,
+            &regenerated_plate.angular_velocity
+        ));
+        assert_eq!(plate.is_oceanic, regenerated_plate.is_oceanic);
+    }
+}
+
+#[test]
+fn mountain_belts_are_no_taller_than_the_mountains() {
+    // only the mountains are kept, the collisions of the plates widen their mask but must not raise them further
+    let highest_mountain = |nb_tectonic_plates: u32, tectonic_boundary_width: f32| {
+        let terrain = make_terrain_function(TerrainSettings {
+            nb_tectonic_plates,
+            tectonic_boundary_width,
+            continent_base_height: 0.0,
+            max_bump_height: 0.0,
+            max_ridge_height: 0.0,
+            max_trench_depth: 0.0,
+            ..SETTINGS
+        });
+        fibonacci_sphere(5000)
+            .iter()
+            .map(|point| {
+                let mut position = point.clone();
+                terrain(
+                    &Vector3d::from(point),
+                    &NOISE_SEED,
+                    0.0,
+                    &mut position,
+                    &mut Vector3::zero(),
+                );
+                position.length() - 1.0
+            })
+            .fold(0.0, f32::max)
+    };
+
+    let without_plates = highest_mountain(0, BOUNDARY_WIDTH);
+    for boundary_width in [BOUNDARY_WIDTH, 0.2] {
+        let with_plates = highest_mountain(NB_PLATES, boundary_width);
+        assert!(
+            with_plates < 1.25 * without_plates,
+            "mountain belts reach {with_plates} m, the mountains {without_plates} m"
+        );
+    }
+}
+
+#[test]
+fn plates_shape_the_terrain() {
+    let settings = TerrainSettings {
+        nb_tectonic_plates: NB_PLATES,
+        tectonic_boundary_width: BOUNDARY_WIDTH,
+        ..SETTINGS
+    };
+    let terrain = make_terrain_function(settings);
+    let plates = TectonicPlates::generate(SEED, NB_PLATES, BOUNDARY_WIDTH);
+
+    let elevation = |unit_sample_point: &Vector3| {
+        let mut position = unit_sample_point.clone();
+        terrain(
+            &Vector3d::from(unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut position,
+            &mut Vector3::zero(),
+        );
+        position.length() - 1.0
+    };
+
+    let (mut oceanic_elevation, mut nb_oceanic) = (0.0, 0);
+    let (mut continental_elevation, mut nb_continental) = (0.0, 0);
+    for point in fibonacci_sphere(2000) {
+        if plates.boundary_distance(&point).0 < 2.0 * BOUNDARY_WIDTH {
+            continue;
+        }
+        if plates.plates[plates.plate_index(&point)].is_oceanic {
+            oceanic_elevation += elevation(&point);
+            nb_oceanic += 1;
+        } else {
+            continental_elevation += elevation(&point);
+            nb_continental += 1;
+        }
+    }
+
+    assert!(nb_oceanic > 0 && nb_continental > 0);
+    assert!(
+        continental_elevation / nb_continental as f32 > oceanic_elevation / nb_oceanic as f32,
+        "oceanic plates should be lower than continental plates"
+    );
+}
@@ -0,0 +1,170 @@
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::terrace_layer::terrace_layer;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::math::terrace;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+/// A smooth elevation with a known gradient to feed the terraces
+fn ramp(coords: &Vector3) -> (f32, Vector3) {
+    let direction = Vector3::new(0.3, 0.5, 0.2);
+    (
+        0.5 + 0.4 * Vector3::dot(coords, &direction),
+        &direction * 0.4,
+    )
+}
+
+#[test]
+fn terrace_creates_plateaus() {
+    let nb_steps = 5.0;
+    for i in 0..5 {
+        for offset in [0.3, 0.5, 0.7] {
+            let x = (i as f32 + offset) / nb_steps;
+            let mut gradient = Vector3::new(1.0, 0.0, 0.0);
+            let terraced = terrace(x, nb_steps, 20.0, &mut gradient);
+
+            // away from the middle of the step, the value is flattened toward the plateau
+            let plateau = if offset < 0.5 {
+                i as f32 / nb_steps
+            } else if offset > 0.5 {
+                (i + 1) as f32 / nb_steps
+            } else {
+                x
+            };
+            assert!((terraced - plateau).abs() < 0.2 / nb_steps);
+            if offset != 0.5 {
+                assert!(gradient.x < 0.1);
+            }
+        }
+    }
+}
+
+#[test]
+fn terrace_is_continuous_between_steps() {
+    let nb_steps = 4.0;
+    let epsilon = 1e-4;
+    for i in 1..4 {
+        let edge = i as f32 / nb_steps;
+        let below = terrace(edge - epsilon, nb_steps, 6.0, &mut Vector3::zero());
+        let above = terrace(edge + epsilon, nb_steps, 6.0, &mut Vector3::zero());
+        assert!((above - below).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn terrace_gradient_matches_finite_differences() {
+    let layer = terrace_layer(SETTINGS.mountains_frequency, 6.0, 3.0, 1.0);
+    let epsilon = 1e-3;
+
+    let terraced = |coords: &Vector3, gradient: &mut Vector3| {
+        let (value, value_gradient) = ramp(coords);
+        *gradient = value_gradient;
+        layer.apply(&Vector3d::from(coords), &NOISE_SEED, 0.0, value, gradient)
+    };
+
+    for point in [
+        Vector3::new(0.1, 0.2, 0.3),
+        Vector3::new(-0.4, 0.6, 0.1),
+        Vector3::new(0.7, -0.3, 0.5),
+        Vector3::new(-0.2, -0.9, 0.4),
+    ] {
+        let mut gradient = Vector3::zero();
+        terraced(&point, &mut gradient);
+
+        let axes = [
+            Vector3::new(epsilon, 0.0, 0.0),
+            Vector3::new(0.0, epsilon, 0.0),
+            Vector3::new(0.0, 0.0, epsilon),
+        ];
+        let numerical = axes
+            .iter()
+            .map(|offset| {
+                let forward = terraced(&(&point + offset), &mut Vector3::zero());
+                let backward = terraced(&(&point - offset), &mut Vector3::zero());
+                (forward - backward) / (2.0 * epsilon)
+            })
+            .collect::<Vec<f32>>();
+        let numerical = Vector3::new(numerical[0], numerical[1], numerical[2]);
+
+        assert!(
+            (&gradient - &numerical).length() <= 1e-2,
+            "analytic gradient {:?} differs from numerical gradient {:?}",
+            gradient,
+            numerical
+        );
+    }
+}
+
+#[test]
+fn terraces_fade_out_at_coarse_levels_of_detail() {
+    let layer = terrace_layer(SETTINGS.mountains_frequency, 8.0, 4.0, 1.0);
+    let point = Vector3::new(0.1, 0.2, 0.3);
+    let (value, value_gradient) = ramp(&point);
+
+    assert_eq!(layer.level_of_detail(0.0), 1.0);
+    assert!(layer.level_of_detail(1e-6) > 0.99);
+
+    let coarse_spacing = 1e-2;
+    assert_eq!(layer.level_of_detail(coarse_spacing), 0.0);
+
+    let mut gradient = value_gradient.clone();
+    let terraced = layer.apply(
+        &Vector3d::from(&point),
+        &NOISE_SEED,
+        coarse_spacing,
+        value,
+        &mut gradient,
+    );
+    assert_eq!(terraced, value);
+    assert!(Vector3::equals(&gradient, &value_gradient));
+}
+
+#[test]
+fn terrace_settings_change_the_terrain() {
+    let flat = make_terrain_function(SETTINGS);
+    let terraced = make_terrain_function(TerrainSettings {
+        terrace_coverage: 1.0,
+        terrace_steps: 6.0,
+        terrace_sharpness: 8.0,
+        ..SETTINGS
+    });
+
+    let mut found_difference = false;
+    for i in 0..200 {
+        let theta = i as f32 * 0.37;
+        let unit_sample_point =
+            Vector3::new(f32::cos(theta), f32::sin(1.3 * theta), f32::sin(theta))
+                .normalize_to_new();
+
+        let mut flat_position = unit_sample_point.clone();
+        flat(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut flat_position,
+            &mut Vector3::zero(),
+        );
+        let mut terraced_position = unit_sample_point.clone();
+        terraced(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut terraced_position,
+            &mut Vector3::zero(),
+        );
+
+        if (flat_position.length() - terraced_position.length()).abs() > 1.0 {
+            found_difference = true;
+            break;
+        }
+    }
+
+    assert!(
+        found_difference,
+        "expected terraces to reshape the mountains"
+    );
+}
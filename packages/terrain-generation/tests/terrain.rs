@@ -4,6 +4,7 @@ use image::{ImageBuffer, Luma, Rgb};
 
 use terrain_generation::landscape::make_terrain_function::make_terrain_function;
 use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
 
 mod common;
 
@@ -23,7 +24,13 @@ fn see_terrain_function() {
             let mut sample_point = Vector3::new(x, y, 0.0) * 0.05;
             let unit_sample_point = sample_point.clone();
             let mut gradient = Vector3::new(0.0, 0.0, 0.0);
-            terrain(&unit_sample_point, SEED, &mut sample_point, &mut gradient);
+            terrain(
+                &Vector3d::from(&unit_sample_point),
+                &NOISE_SEED,
+                0.0,
+                &mut sample_point,
+                &mut gradient,
+            );
 
             (sample_point.length() - unit_sample_point.length()) / 10e3
         },
@@ -43,8 +50,9 @@ fn see_terrain_function() {
 
             let base_elevation = sample_point_sphere.length();
             terrain(
-                &unit_sample_point,
-                SEED,
+                &Vector3d::from(&unit_sample_point),
+                &NOISE_SEED,
+                0.0,
                 &mut sample_point_sphere,
                 &mut gradient_sphere,
             );
@@ -70,7 +78,13 @@ fn terrain_function() {
     let unit_sample_point = &sample_point / elevation;
     let mut gradient = Vector3::zero();
 
-    terrain(&unit_sample_point, SEED, &mut sample_point, &mut gradient);
+    terrain(
+        &Vector3d::from(&unit_sample_point),
+        &NOISE_SEED,
+        0.0,
+        &mut sample_point,
+        &mut gradient,
+    );
     gradient /= PLANET_RADIUS;
 
     assert!(sample_point.length() >= elevation);
@@ -96,7 +110,13 @@ fn terrain_function() {
 
             let mut gradient = Vector3::zero();
 
-            terrain(&unit_sphere_coords, SEED, &mut sample_point, &mut gradient);
+            terrain(
+                &Vector3d::from(&unit_sphere_coords),
+                &NOISE_SEED,
+                0.0,
+                &mut sample_point,
+                &mut gradient,
+            );
 
             gradient /= PLANET_RADIUS;
 
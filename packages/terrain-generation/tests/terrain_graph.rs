@@ -0,0 +1,328 @@
+use terrain_generation::build_data::BuildData;
+use terrain_generation::chunk_layout::ChunkLayout;
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::terrain_graph::{SubSeed, TerrainGraph, TerrainNode};
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::seed::{NoiseSeed, Seed};
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::math::{multiply, smoothstep};
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+use terrain_generation::{build_chunk_vertex_data, build_chunk_vertex_data_with_graph};
+
+mod common;
+
+use common::*;
+
+const ARCHETYPE: &str = r#"{
+    "elevation": {
+        "type": "add",
+        "inputs": [
+            {
+                "type": "scale",
+                "factor": 17500.0,
+                "input": {
+                    "type": "smoothstep",
+                    "edge0": 0.3,
+                    "edge1": 0.5,
+                    "input": { "type": "continent", "frequency": 1.0, "fragmentation": 0.3 }
+                }
+            },
+            {
+                "type": "scale",
+                "factor": 15000.0,
+                "input": {
+                    "type": "multiply",
+                    "inputs": [
+                        {
+                            "type": "mountain",
+                            "frequency": 20.0,
+                            "nb_octaves": 7,
+                            "decay": 2.0,
+                            "lacunarity": 2.0,
+                            "threshold": 0.4
+                        },
+                        {
+                            "type": "smoothstep",
+                            "edge0": 0.3,
+                            "edge1": 0.5,
+                            "input": { "type": "continent", "frequency": 1.0, "fragmentation": 0.3 }
+                        }
+                    ]
+                }
+            }
+        ]
+    }
+}"#;
+
+#[test]
+fn json_graph_matches_hand_written_composition() {
+    let graph = TerrainGraph::from_json(ARCHETYPE).unwrap();
+    let terrain = graph.compile();
+
+    let continents = continent_layer(1.0, 0.3);
+    let mountains = mountain_layer(20.0, 7, 2.0, 2.0, 0.4);
+
+    for unit_sample_point in fibonacci_sphere(50) {
+        let mut expected_gradient = Vector3::zero();
+        let mut continent_mask = continents.sample(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            &mut expected_gradient,
+        );
+        continent_mask = smoothstep(0.3, 0.5, continent_mask, &mut expected_gradient);
+        let mut mountain_gradient = Vector3::zero();
+        let mut mountain_elevation = mountains.sample(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            &mut mountain_gradient,
+        );
+        mountain_elevation = multiply(
+            mountain_elevation,
+            continent_mask,
+            &mut mountain_gradient,
+            &expected_gradient,
+        );
+        let expected_elevation = continent_mask * 17500.0 + mountain_elevation * 15000.0;
+        expected_gradient *= 17500.0;
+        expected_gradient += &mountain_gradient * 15000.0;
+
+        let mut position = unit_sample_point.clone();
+        let mut gradient = Vector3::zero();
+        terrain(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut position,
+            &mut gradient,
+        );
+
+        let elevation = Vector3::dot(&(&position - &unit_sample_point), &unit_sample_point);
+        assert!((elevation - expected_elevation).abs() <= 1e-2);
+        assert!((&gradient - &expected_gradient).length() <= 1e-2);
+    }
+}
+
+#[test]
+fn graph_round_trips_through_json() {
+    let graph = TerrainGraph::from_json(ARCHETYPE).unwrap();
+    let json = graph.to_json().unwrap();
+
+    assert_eq!(TerrainGraph::from_json(&json).unwrap(), graph);
+}
+
+#[test]
+fn unknown_node_type_is_rejected() {
+    let result =
+        TerrainGraph::from_json(r#"{ "elevation": { "type": "volcano", "height": 1.0 } }"#);
+
+    assert!(result.is_err());
+}
+
+/// The terrain of `make_terrain_function` without tectonics, erosion, terraces, craters or bathymetry, as a graph
+fn planet_graph(settings: &TerrainSettings) -> TerrainGraph {
+    let with_sub_seed = |input: TerrainNode, sub_seed: SubSeed| TerrainNode::SubSeed {
+        input: Box::new(input),
+        sub_seed,
+    };
+    let continent_mask = TerrainNode::Smoothstep {
+        input: Box::new(with_sub_seed(
+            TerrainNode::Continent {
+                frequency: settings.continents_frequency,
+                fragmentation: settings.continents_fragmentation,
+            },
+            SubSeed::Continents,
+        )),
+        edge0: 0.3,
+        edge1: 0.5,
+    };
+
+    TerrainGraph {
+        elevation: TerrainNode::Add {
+            inputs: vec![
+                TerrainNode::Scale {
+                    input: Box::new(continent_mask.clone()),
+                    factor: settings.continent_base_height,
+                },
+                TerrainNode::Scale {
+                    input: Box::new(TerrainNode::Multiply {
+                        inputs: vec![
+                            with_sub_seed(
+                                TerrainNode::Mountain {
+                                    frequency: settings.mountains_frequency,
+                                    nb_octaves: 7,
+                                    decay: 2.0,
+                                    lacunarity: 2.0,
+                                    threshold: 0.4,
+                                },
+                                SubSeed::Mountains,
+                            ),
+                            continent_mask.clone(),
+                            with_sub_seed(
+                                TerrainNode::MountainMask {
+                                    frequency: settings.mountains_frequency,
+                                },
+                                SubSeed::MountainMask,
+                            ),
+                        ],
+                    }),
+                    factor: settings.max_mountain_height,
+                },
+                TerrainNode::Scale {
+                    input: Box::new(TerrainNode::Multiply {
+                        inputs: vec![
+                            with_sub_seed(
+                                TerrainNode::Bumps {
+                                    frequency: settings.bumps_frequency,
+                                },
+                                SubSeed::Bumps,
+                            ),
+                            continent_mask,
+                        ],
+                    }),
+                    factor: settings.max_bump_height,
+                },
+            ],
+        },
+    }
+}
+
+#[test]
+fn default_planet_graph_matches_make_terrain_function() {
+    let graph = planet_graph(&SETTINGS);
+    // the graph survives its serialization, as authored by designers
+    let graph = TerrainGraph::from_json(&graph.to_json().unwrap()).unwrap();
+    let graph_terrain = graph.compile();
+    let terrain = make_terrain_function(SETTINGS);
+
+    // integer seeds give every layer its own sub-seed
+    for seed in [SEED, Seed::from_integer(42)].map(NoiseSeed::new) {
+        for unit_sample_point in fibonacci_sphere(200) {
+            let mut expected_position = unit_sample_point.clone();
+            let mut expected_gradient = Vector3::zero();
+            terrain(
+                &Vector3d::from(&unit_sample_point),
+                &seed,
+                0.0,
+                &mut expected_position,
+                &mut expected_gradient,
+            );
+
+            let mut position = unit_sample_point.clone();
+            let mut gradient = Vector3::zero();
+            graph_terrain(
+                &Vector3d::from(&unit_sample_point),
+                &seed,
+                0.0,
+                &mut position,
+                &mut gradient,
+            );
+
+            assert!((&position - &expected_position).length() <= 1e-2);
+            assert!(
+                (&gradient - &expected_gradient).length()
+                    <= 1e-4 * expected_gradient.length() + 1e-2
+            );
+        }
+    }
+}
+
+#[test]
+fn chunks_are_built_from_the_graph() {
+    let data = BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 4,
+        chunk_tree_direction: Direction::Up,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: PLANET_RADIUS,
+        chunk_cube_position_z: 0.0,
+        planet_seed: Seed::from_integer(42),
+        resolution: 17,
+        terrain_settings: SETTINGS,
+    };
+    let build = |graph: Option<&TerrainGraph>| {
+        let layout = ChunkLayout::for_chunk(&data, false, 0.0);
+        let mut positions = vec![0.0; layout.positions_length];
+        let mut indices = vec![0; layout.indices_length];
+        let mut normals = vec![0.0; layout.normals_length];
+        let buffers = (&mut positions, &mut indices, &mut normals);
+        match graph {
+            Some(graph) => build_chunk_vertex_data_with_graph(
+                &data,
+                graph,
+                buffers.0,
+                buffers.1,
+                buffers.2,
+                &mut [],
+                &mut [],
+                &mut [],
+                0.0,
+            ),
+            None => build_chunk_vertex_data(
+                &data,
+                buffers.0,
+                buffers.1,
+                buffers.2,
+                &mut [],
+                &mut [],
+                &mut [],
+                0.0,
+            ),
+        }
+        .unwrap();
+        positions
+    };
+
+    let expected = build(None);
+    let from_graph = build(Some(&planet_graph(&SETTINGS)));
+    for (position, expected_position) in from_graph.iter().zip(&expected) {
+        assert!((position - expected_position).abs() <= 1e-1);
+    }
+
+    // a different graph is not served from the cache of the previous one
+    let flat_graph = TerrainGraph {
+        elevation: TerrainNode::Constant { value: 0.0 },
+    };
+    assert_ne!(build(Some(&flat_graph)), from_graph);
+}
+
+#[test]
+fn smooth_maximum_of_elevations_does_not_overflow() {
+    // elevations in meters would overflow the exponentials of a naive smooth maximum
+    let graph = TerrainGraph::from_json(
+        r#"{
+            "elevation": {
+                "type": "s_max",
+                "k": 10.0,
+                "a": { "type": "constant", "value": 5000.0 },
+                "b": {
+                    "type": "scale",
+                    "factor": 15000.0,
+                    "input": { "type": "continent", "frequency": 1.0, "fragmentation": 0.3 }
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+    let terrain = graph.compile();
+
+    for unit_sample_point in fibonacci_sphere(100) {
+        let mut position = unit_sample_point.clone();
+        let mut gradient = Vector3::zero();
+        terrain(
+            &Vector3d::from(&unit_sample_point),
+            &NOISE_SEED,
+            0.0,
+            &mut position,
+            &mut gradient,
+        );
+
+        let elevation = position.length() - 1.0;
+        assert!(elevation.is_finite() && gradient.length().is_finite());
+        assert!(elevation >= 5000.0 - 1e-2);
+    }
+}
@@ -0,0 +1,121 @@
+use terrain_generation::landscape::constant_layers::constant_layer;
+use terrain_generation::landscape::continent_layer::continent_layer;
+use terrain_generation::landscape::crater_layer::crater_layer;
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::landscape::mountain_layer::mountain_layer;
+use terrain_generation::landscape::simplex_noise_layer::simplex_noise_layer;
+use terrain_generation::landscape::terrain_graph::TerrainNode;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
+use terrain_generation::landscape::uber_noise_layer::uber_noise_layer;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+fn assert_within_bounds(layer: &dyn TerrainLayer) {
+    let (low, high) = layer.bounds();
+    assert!(low <= high, "{}: invalid bounds", layer.name());
+
+    for point in fibonacci_sphere(2000) {
+        let value = layer.sample(&Vector3d::from(&point), &NOISE_SEED, &mut Vector3::zero());
+        assert!(
+            value >= low - 1e-4 && value <= high + 1e-4,
+            "{}: {} is outside of [{}, {}]",
+            layer.name(),
+            value,
+            low,
+            high
+        );
+    }
+}
+
+#[test]
+fn layers_stay_within_their_bounds() {
+    assert_within_bounds(&constant_layer(0.5));
+    assert_within_bounds(&continent_layer(
+        SETTINGS.continents_frequency,
+        SETTINGS.continents_fragmentation,
+    ));
+    assert_within_bounds(&mountain_layer(
+        SETTINGS.mountains_frequency,
+        7,
+        2.0,
+        2.0,
+        0.4,
+    ));
+    assert_within_bounds(&simplex_noise_layer(
+        SETTINGS.bumps_frequency,
+        8,
+        1.7,
+        2.0,
+        1.0,
+    ));
+    assert_within_bounds(&uber_noise_layer(1.0, 5, 2.0, 2.0, 0.5));
+    assert_within_bounds(&crater_layer(SETTINGS.craters_frequency, 4, 1.0));
+}
+
+#[test]
+fn combinator_bounds_are_derived_from_their_inputs() {
+    let node = TerrainNode::Add {
+        inputs: vec![
+            TerrainNode::Scale {
+                input: Box::new(TerrainNode::Continent {
+                    frequency: 1.0,
+                    fragmentation: 0.3,
+                }),
+                factor: 100.0,
+            },
+            TerrainNode::Multiply {
+                inputs: vec![
+                    TerrainNode::Constant { value: -2.0 },
+                    TerrainNode::SimplexNoise {
+                        frequency: 3.0,
+                        nb_octaves: 4,
+                        decay: 2.0,
+                        lacunarity: 2.0,
+                        power: 1.0,
+                    },
+                ],
+            },
+        ],
+    };
+    let layer = node.compile();
+
+    let (_, simplex_high) = simplex_noise_layer(3.0, 4, 2.0, 2.0, 1.0).bounds();
+    let (low, high) = layer.bounds();
+    assert!((low - (70.0 - 2.0 * simplex_high)).abs() <= 1e-3);
+    assert!((high - 100.0).abs() <= 1e-3);
+    assert_within_bounds(&layer);
+}
+
+#[test]
+fn layers_report_their_metadata() {
+    let mountains = mountain_layer(20.0, 7, 2.0, 2.0, 0.4);
+
+    assert_eq!(mountains.name(), "mountain");
+    assert_eq!(mountains.cost(), 7);
+    assert_eq!(mountains.max_frequency(), 20.0 * 64.0);
+    assert!(mountains.parameters().contains(&("threshold", 0.4)));
+
+    let continents = continent_layer(1.0, 0.3);
+    assert_eq!(continents.name(), "continent");
+    assert_eq!(continents.max_frequency(), 32.0);
+    assert_eq!(
+        continents.parameters(),
+        vec![("frequency", 1.0), ("continents_fragmentation", 0.3)]
+    );
+
+    let simplex = simplex_noise_layer(2.0, 3, 2.0, 2.0, 1.0);
+    assert_eq!(simplex.cost(), 3);
+    assert_eq!(simplex.max_frequency(), 8.0);
+}
+
+#[test]
+fn costs_do_not_wrap() {
+    assert_eq!(mountain_layer(20.0, -1, 2.0, 2.0, 0.4).cost(), 0);
+    assert_eq!(simplex_noise_layer(2.0, -3, 2.0, 2.0, 1.0).cost(), 0);
+    assert_eq!(uber_noise_layer(2.0, -3, 2.0, 2.0, 0.5).cost(), 0);
+    assert_eq!(crater_layer(1.0, u32::MAX, 0.5).cost(), u32::MAX);
+}
@@ -0,0 +1,185 @@
+use terrain_generation::landscape::gradient_check::fibonacci_sphere;
+use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::terrain_settings::{
+    SettingsError, TerrainSettings, PRESET_NAMES, SETTINGS_VERSION,
+};
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
+
+mod common;
+
+use common::*;
+
+/// The settings of the Earth as saved by the game before the settings were versioned
+const LEGACY_EARTH: &str = r#"{
+    "continents_frequency": 1,
+    "continents_fragmentation": 0.65,
+    "bumps_frequency": 30,
+    "max_bump_height": 1500,
+    "max_mountain_height": 10000,
+    "continent_base_height": 19000,
+    "mountains_frequency": 360
+}"#;
+
+fn presets() -> Vec<TerrainSettings> {
+    PRESET_NAMES
+        .iter()
+        .map(|name| TerrainSettings::preset(name).unwrap())
+        .collect()
+}
+
+/// The fraction of the surface above sea level
+fn land_fraction(settings: TerrainSettings) -> f32 {
+    let terrain_function = make_terrain_function(settings);
+    let points = fibonacci_sphere(2000);
+    let nb_land_points = points
+        .iter()
+        .filter(|point| {
+            let mut position = Vector3::zero();
+            terrain_function(
+                &Vector3d::from(*point),
+                &NOISE_SEED,
+                0.0,
+                &mut position,
+                &mut Vector3::zero(),
+            );
+            Vector3::dot(&position, point) > settings.sea_level
+        })
+        .count();
+    nb_land_points as f32 / points.len() as f32
+}
+
+#[test]
+fn settings_survive_a_json_round_trip() {
+    for settings in presets().into_iter().chain([SETTINGS]) {
+        let json = settings.to_json().unwrap();
+        assert_eq!(TerrainSettings::from_json(&json).unwrap(), settings);
+    }
+}
+
+#[test]
+#[cfg(feature = "ron")]
+fn settings_survive_a_ron_round_trip() {
+    for settings in presets().into_iter().chain([SETTINGS]) {
+        let ron = settings.to_ron().unwrap();
+        assert_eq!(TerrainSettings::from_ron(&ron).unwrap(), settings);
+    }
+}
+
+#[test]
+fn serialized_settings_carry_the_current_version() {
+    let json = TerrainSettings::EARTHLIKE.to_json().unwrap();
+    let document: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(document["version"], SETTINGS_VERSION);
+    assert_eq!(document["settings"]["sea_level"], 10e3);
+}
+
+#[test]
+fn legacy_presets_are_migrated() {
+    let settings = TerrainSettings::from_json(LEGACY_EARTH).unwrap();
+
+    assert_eq!(settings.continents_fragmentation, 0.65);
+    assert_eq!(settings.continent_base_height, 19e3);
+    assert_eq!(settings.mountains_frequency, 360.0);
+    assert_eq!(settings.bumps_frequency, 30.0);
+
+    // the bumps were not generated back then
+    assert_eq!(settings.max_bump_height, 0.0);
+
+    // the features added since are disabled
+    let defaults = TerrainSettings::default();
+    assert_eq!(
+        settings,
+        TerrainSettings {
+            continents_frequency: 1.0,
+            continents_fragmentation: 0.65,
+            bumps_frequency: 30.0,
+            max_bump_height: 0.0,
+            max_mountain_height: 10e3,
+            continent_base_height: 19e3,
+            mountains_frequency: 360.0,
+            ..defaults
+        }
+    );
+}
+
+#[test]
+#[cfg(feature = "ron")]
+fn legacy_ron_presets_are_migrated() {
+    let settings = TerrainSettings::from_ron(
+        "(continents_frequency: 2.0, max_bump_height: 1500.0, sea_level: 10.0)",
+    )
+    .unwrap();
+    assert_eq!(settings.continents_frequency, 2.0);
+    assert_eq!(settings.max_bump_height, 0.0);
+    assert_eq!(settings.sea_level, 10.0);
+}
+
+#[test]
+fn missing_fields_take_their_default_values() {
+    let settings = TerrainSettings::from_json(
+        r#"{ "version": 2, "settings": { "sea_level": 500, "splat": { "beach_height": 10 } } }"#,
+    )
+    .unwrap();
+
+    assert_eq!(settings.sea_level, 500.0);
+    assert_eq!(settings.splat.beach_height, 10.0);
+    assert_eq!(
+        settings.splat.snow_transition,
+        TerrainSettings::default().splat.snow_transition
+    );
+    assert_eq!(
+        settings.max_bump_height,
+        TerrainSettings::default().max_bump_height
+    );
+}
+
+#[test]
+fn unsupported_versions_are_rejected() {
+    for version in [0, SETTINGS_VERSION + 1] {
+        let json = format!(r#"{{ "version": {version}, "settings": {{}} }}"#);
+        assert!(matches!(
+            TerrainSettings::from_json(&json),
+            Err(SettingsError::UnsupportedVersion(v)) if v == version
+        ));
+    }
+}
+
+#[test]
+fn malformed_settings_are_rejected() {
+    for json in [
+        "",
+        "[1, 2]",
+        r#"{ "version": 2 }"#,
+        r#"{ "version": 2, "settings": { "sea_level": "deep" } }"#,
+        r#"{ "continents_frequency": true }"#,
+    ] {
+        let error = TerrainSettings::from_json(json).unwrap_err();
+        assert!(matches!(error, SettingsError::Json(_)));
+        assert!(error.to_string().starts_with("invalid terrain settings"));
+    }
+}
+
+#[test]
+fn presets_are_found_by_name() {
+    let presets = presets();
+    for (i, a) in presets.iter().enumerate() {
+        for b in &presets[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    assert_eq!(
+        TerrainSettings::preset("earthlike"),
+        Some(TerrainSettings::EARTHLIKE)
+    );
+    assert_eq!(TerrainSettings::preset("pluto"), None);
+}
+
+#[test]
+fn presets_shape_their_seas() {
+    let earthlike = land_fraction(TerrainSettings::EARTHLIKE);
+    assert!(earthlike > 0.1 && earthlike < 0.6, "{earthlike}");
+
+    let ocean_world = land_fraction(TerrainSettings::OCEAN_WORLD);
+    assert!(ocean_world < earthlike / 2.0, "{ocean_world}");
+}
@@ -2,11 +2,13 @@ use gilgamesh::mesh::Mesh;
 use gilgamesh::{init_gilgamesh, start_gilgamesh};
 use image::{ImageBuffer, Luma, Rgb};
 use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
 
 mod common;
 
 use common::*;
 
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
 use terrain_generation::landscape::uber_noise_layer::uber_noise_layer;
 
 use std::f32::consts::PI;
@@ -35,7 +37,11 @@ pub fn test_uber_noise() {
             );
 
             let mut gradient_sphere = Vector3::zero();
-            let noise_value_sphere = uber_layer(&sample_point_sphere, SEED, &mut gradient_sphere);
+            let noise_value_sphere = uber_layer.sample(
+                &Vector3d::from(&sample_point_sphere),
+                &NOISE_SEED,
+                &mut gradient_sphere,
+            );
 
             //gradient_sphere
             let image_value_sphere = (noise_value_sphere * 255.0) as u8;
@@ -55,7 +61,8 @@ pub fn test_uber_noise() {
             let mut gradient = Vector3::new(0.0, 0.0, 0.0);
             let sample_point = Vector3::new(u as f32 / 100.0, v as f32 / 100.0, 0.0);
 
-            let noise_value = uber_layer(&sample_point, SEED, &mut gradient);
+            let noise_value =
+                uber_layer.sample(&Vector3d::from(&sample_point), &NOISE_SEED, &mut gradient);
 
             let image_value = (noise_value * 255.0) as u8;
             *(value_image.get_pixel_mut(u, v)) = Luma([image_value]);
@@ -85,9 +92,9 @@ pub fn test_uber_noise() {
         10.0,
         2048,
         &|x, y| {
-            0.5 * uber_layer(
-                &Vector3::new(x, y, 0.0),
-                SEED,
+            0.5 * uber_layer.sample(
+                &Vector3d::from(&Vector3::new(x, y, 0.0)),
+                &NOISE_SEED,
                 &mut Vector3::new(0.0, 0.0, 0.0),
             )
         },
@@ -96,7 +103,7 @@ pub fn test_uber_noise() {
     );
 
     /*let sphere = Mesh::new_procedural_sphere(4.0, 128, &|x, y, z| {
-        0.8 * uber_layer(&Vector3::new(x, y, z), SEED, &mut Vector3::new(0.0, 0.0, 0.0))
+        0.8 * uber_layer(&Vector3::new(x, y, z), &NOISE_SEED, &mut Vector3::new(0.0, 0.0, 0.0))
     }, 0.9, &mut app.engine);*/
 
     app.scene.add_mesh(procedural_plane);
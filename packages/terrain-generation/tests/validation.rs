@@ -0,0 +1,208 @@
+use terrain_generation::base_shape::BaseShape;
+use terrain_generation::build_chunk_vertex_data;
+use terrain_generation::build_data::BuildData;
+use terrain_generation::seed::Seed;
+use terrain_generation::splat::SplatSettings;
+use terrain_generation::terrain_settings::{TerrainSettings, PRESET_NAMES};
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::validation::{
+    ValidationError, ValidationErrors, MAX_CHUNK_DEPTH, MAX_RESOLUTION,
+};
+use terrain_generation::volumetric_chunk::CaveSettings;
+
+mod common;
+
+use common::*;
+
+fn build_data() -> BuildData {
+    BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: 0,
+        chunk_tree_direction: Direction::Forward,
+        chunk_cube_position_x: 0.0,
+        chunk_cube_position_y: 0.0,
+        chunk_cube_position_z: -PLANET_RADIUS,
+        planet_seed: SEED,
+        resolution: 16,
+        terrain_settings: SETTINGS,
+    }
+}
+
+fn violations(result: Result<(), ValidationErrors>) -> Vec<ValidationError> {
+    result.err().map(|errors| errors.0).unwrap_or_default()
+}
+
+#[test]
+fn valid_inputs_pass() {
+    assert_eq!(build_data().validate(), Ok(()));
+    assert_eq!(TerrainSettings::default().validate(), Ok(()));
+    for name in PRESET_NAMES {
+        assert_eq!(TerrainSettings::preset(name).unwrap().validate(), Ok(()));
+    }
+
+    for resolution in [2, MAX_RESOLUTION] {
+        let data = BuildData {
+            resolution,
+            chunk_depth: MAX_CHUNK_DEPTH,
+            planet_seed: Seed::from_integer(u64::MAX),
+            ..build_data()
+        };
+        assert_eq!(data.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn invalid_settings_report_every_violation() {
+    let settings = TerrainSettings {
+        mountains_frequency: -2.0,
+        craters_frequency: f32::NAN,
+        continents_fragmentation: 0.0,
+        base_shape: BaseShape {
+            scale_y: 0.0,
+            lump_amplitude: 1.0,
+            ..BaseShape::SPHERE
+        },
+        caves: CaveSettings {
+            size: 0.0,
+            ..CaveSettings::NONE
+        },
+        ..SETTINGS
+    };
+
+    let errors = violations(settings.validate());
+    assert_eq!(errors.len(), 6, "{errors:?}");
+    assert!(errors.contains(&ValidationError::InvalidFrequency {
+        field: "mountains_frequency",
+        value: -2.0
+    }));
+    assert!(errors.iter().any(|error| matches!(
+        error,
+        ValidationError::InvalidFrequency {
+            field: "craters_frequency",
+            value
+        } if value.is_nan()
+    )));
+    assert!(errors.contains(&ValidationError::InvalidFragmentation(0.0)));
+    assert!(errors.contains(&ValidationError::InvalidBaseShapeScale {
+        field: "scale_y",
+        value: 0.0
+    }));
+    assert!(errors.contains(&ValidationError::InvalidLumpAmplitude(1.0)));
+    assert!(errors.contains(&ValidationError::InvalidCaveSize(0.0)));
+}
+
+#[test]
+fn non_finite_values_and_empty_widths_are_reported() {
+    let mut settings = TerrainSettings {
+        sea_level: f32::INFINITY,
+        tectonic_boundary_width: 0.0,
+        terrace_steps: -1.0,
+        splat: SplatSettings {
+            snow_transition: f32::NAN,
+            ..SplatSettings::DEFAULT
+        },
+        ..SETTINGS
+    };
+    settings.biomes.temperature_lapse_rate = f32::NEG_INFINITY;
+
+    let errors = violations(settings.validate());
+    assert_eq!(errors.len(), 5, "{errors:?}");
+    assert!(errors.contains(&ValidationError::InvalidValue {
+        field: "sea_level",
+        value: f32::INFINITY
+    }));
+    assert!(errors.contains(&ValidationError::InvalidValue {
+        field: "biomes.temperature_lapse_rate",
+        value: f32::NEG_INFINITY
+    }));
+    assert!(errors.contains(&ValidationError::InvalidWidth {
+        field: "tectonic_boundary_width",
+        value: 0.0
+    }));
+    assert!(errors.iter().any(|error| matches!(
+        error,
+        ValidationError::InvalidWidth {
+            field: "splat.snow_transition",
+            value
+        } if value.is_nan()
+    )));
+    assert!(errors.contains(&ValidationError::InvalidStepCount(-1.0)));
+}
+
+#[test]
+fn invalid_build_data_report_every_violation() {
+    let data = BuildData {
+        planet_diameter: -1.0,
+        chunk_depth: MAX_CHUNK_DEPTH + 1,
+        chunk_cube_position_x: f32::INFINITY,
+        planet_seed: Seed::from_offset(f32::NAN),
+        resolution: 1,
+        terrain_settings: TerrainSettings {
+            continents_fragmentation: 1.5,
+            ..SETTINGS
+        },
+        ..build_data()
+    };
+
+    let errors = violations(data.validate());
+    assert_eq!(errors.len(), 6, "{errors:?}");
+    assert!(errors.contains(&ValidationError::InvalidPlanetDiameter(-1.0)));
+    assert!(errors.contains(&ValidationError::ChunkDepthTooLarge(MAX_CHUNK_DEPTH + 1)));
+    assert!(errors.contains(&ValidationError::InvalidChunkPosition));
+    assert!(errors
+        .iter()
+        .any(|error| matches!(error, ValidationError::InvalidSeed(offset) if offset.is_nan())));
+    assert!(errors.contains(&ValidationError::ResolutionTooLow(1)));
+    assert!(errors.contains(&ValidationError::InvalidFragmentation(1.5)));
+
+    let too_fine = BuildData {
+        resolution: MAX_RESOLUTION + 1,
+        ..build_data()
+    };
+    assert_eq!(
+        violations(too_fine.validate()),
+        vec![ValidationError::ResolutionTooHigh(MAX_RESOLUTION + 1)]
+    );
+}
+
+#[test]
+fn errors_explain_the_constraints() {
+    let data = BuildData {
+        resolution: 0,
+        chunk_depth: 40,
+        ..build_data()
+    };
+    assert_eq!(
+        data.validate().unwrap_err().to_string(),
+        "invalid terrain inputs: chunk_depth must be at most 31, got 40; resolution must be at least 2, got 0"
+    );
+}
+
+#[test]
+fn the_deepest_chunks_can_be_built() {
+    let resolution = 2;
+    let data = BuildData {
+        chunk_depth: MAX_CHUNK_DEPTH,
+        resolution: resolution as u32,
+        ..build_data()
+    };
+
+    let nb_vertices = resolution * resolution;
+    let mut positions = vec![0.0; nb_vertices * 3];
+    let mut normals = vec![0.0; nb_vertices * 3];
+    let mut indices = vec![0; (resolution - 1) * (resolution - 1) * 6];
+    build_chunk_vertex_data(
+        &data,
+        &mut positions,
+        &mut indices,
+        &mut normals,
+        &mut [],
+        &mut [],
+        &mut [],
+        0.0,
+    )
+    .unwrap();
+
+    assert!(positions.iter().all(|coordinate| coordinate.is_finite()));
+    assert!(normals.iter().all(|coordinate| coordinate.is_finite()));
+}
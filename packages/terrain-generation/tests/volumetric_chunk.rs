@@ -0,0 +1,222 @@
+use terrain_generation::build_data::BuildData;
+use terrain_generation::terrain_settings::TerrainSettings;
+use terrain_generation::utils::direction::Direction;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::validation::{ValidationError, MAX_RADIAL_LEVELS};
+use terrain_generation::volumetric_chunk::{CaveSettings, VolumetricChunk};
+
+mod common;
+
+use common::*;
+
+const RESOLUTION: u32 = 16;
+const CHUNK_DEPTH: u32 = 10;
+
+fn chunk_size() -> f32 {
+    PLANET_RADIUS * 2.0 / 2f32.powi(CHUNK_DEPTH as i32)
+}
+
+fn build_data(x: f32, z: f32, terrain_settings: TerrainSettings) -> BuildData {
+    BuildData {
+        planet_diameter: PLANET_RADIUS * 2.0,
+        chunk_depth: CHUNK_DEPTH,
+        chunk_tree_direction: Direction::Up,
+        chunk_cube_position_x: x,
+        chunk_cube_position_y: PLANET_RADIUS,
+        chunk_cube_position_z: z,
+        planet_seed: SEED,
+        resolution: RESOLUTION,
+        terrain_settings,
+    }
+}
+
+fn chunk_sphere_position(data: &BuildData) -> Vector3 {
+    Vector3::new(
+        data.chunk_cube_position_x,
+        data.chunk_cube_position_y,
+        data.chunk_cube_position_z,
+    )
+    .normalize_to_new()
+        * PLANET_RADIUS
+}
+
+/// The vertex positions in planet space
+fn planet_positions(chunk: &VolumetricChunk, data: &BuildData) -> Vec<Vector3> {
+    let offset = chunk_sphere_position(data);
+    chunk
+        .positions()
+        .chunks(3)
+        .map(|position| &offset + &Vector3::new(position[0], position[1], position[2]))
+        .collect()
+}
+
+fn normals(chunk: &VolumetricChunk) -> Vec<Vector3> {
+    chunk
+        .normals()
+        .chunks(3)
+        .map(|normal| Vector3::new(normal[0], normal[1], normal[2]))
+        .collect()
+}
+
+fn flat_settings() -> TerrainSettings {
+    TerrainSettings {
+        continent_base_height: 0.0,
+        max_mountain_height: 0.0,
+        max_bump_height: 0.0,
+        ..SETTINGS
+    }
+}
+
+fn cave_settings() -> TerrainSettings {
+    TerrainSettings {
+        caves: CaveSettings {
+            size: 1000.0,
+            threshold: 0.55,
+            depth: 600.0,
+        },
+        ..flat_settings()
+    }
+}
+
+#[test]
+fn flat_terrain_gives_the_sphere() {
+    let data = build_data(0.0, 0.0, flat_settings());
+    let chunk = VolumetricChunk::build(&data).unwrap();
+    assert!(chunk.nb_vertices() > 0);
+
+    let spacing = chunk_size() / (RESOLUTION - 1) as f32;
+    for (position, normal) in planet_positions(&chunk, &data).iter().zip(normals(&chunk)) {
+        assert!((position.length() - PLANET_RADIUS).abs() < 0.1 * spacing);
+        assert!(Vector3::dot(&normal, &position.normalize_to_new()) > 0.999);
+    }
+}
+
+#[test]
+fn triangles_follow_the_heightfield_winding() {
+    let data = build_data(0.0, 0.0, cave_settings());
+    let chunk = VolumetricChunk::build(&data).unwrap();
+    let positions = planet_positions(&chunk, &data);
+    let normals = normals(&chunk);
+    let indices = chunk.indices();
+
+    assert!(!indices.is_empty());
+    assert_eq!(indices.len() % 3, 0);
+    assert!(indices
+        .iter()
+        .all(|&index| (index as usize) < positions.len()));
+
+    // the heightfield chunks are wound clockwise when seen from outside the ground.
+    // Weighted by area since the sharp creases of the cave floors fold a few small quads
+    let (consistent_area, total_area) =
+        indices
+            .chunks(3)
+            .fold((0.0, 0.0), |(consistent_area, total_area), triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| &positions[triangle[i] as usize]);
+                let normal = &(&normals[triangle[0] as usize] + &normals[triangle[1] as usize])
+                    + &normals[triangle[2] as usize];
+                let cross = Vector3::cross(&(b - a), &(c - a));
+                let area = cross.length() / 2.0;
+                if Vector3::dot(&cross, &normal) < 0.0 {
+                    (consistent_area + area, total_area + area)
+                } else {
+                    (consistent_area, total_area + area)
+                }
+            });
+    assert!(consistent_area >= 0.95 * total_area);
+}
+
+#[test]
+fn caves_carve_overhangs() {
+    let data = build_data(0.0, 0.0, cave_settings());
+    let chunk = VolumetricChunk::build(&data).unwrap();
+    let positions = planet_positions(&chunk, &data);
+
+    // cave ceilings face the center of the planet
+    let nb_ceilings = positions
+        .iter()
+        .zip(normals(&chunk))
+        .filter(|(position, normal)| Vector3::dot(normal, &position.normalize_to_new()) < -0.5)
+        .count();
+    assert!(nb_ceilings > 0);
+
+    // nothing is carved deeper than the caves
+    let deepest = positions
+        .iter()
+        .map(|position| position.length())
+        .fold(f32::MAX, f32::min);
+    let spacing = chunk_size() / (RESOLUTION - 1) as f32;
+    assert!(deepest >= PLANET_RADIUS - cave_settings().caves.depth - spacing);
+}
+
+#[test]
+fn neighbouring_chunks_share_their_border() {
+    let size = chunk_size();
+    let data = build_data(size / 2.0, size / 2.0, cave_settings());
+    let neighbour_data = build_data(-size / 2.0, size / 2.0, cave_settings());
+
+    let positions = planet_positions(&VolumetricChunk::build(&data).unwrap(), &data);
+    let neighbour_positions = planet_positions(
+        &VolumetricChunk::build(&neighbour_data).unwrap(),
+        &neighbour_data,
+    );
+
+    // the quads on the border of a chunk are closed with vertices computed by its neighbour
+    let nb_shared = positions
+        .iter()
+        .filter(|position| {
+            neighbour_positions
+                .iter()
+                .any(|other| (*position - other).length() < 1e-2)
+        })
+        .count();
+    assert!(nb_shared >= RESOLUTION as usize);
+}
+
+#[test]
+fn invalid_chunks_are_rejected() {
+    let data = BuildData {
+        resolution: 1,
+        ..build_data(
+            0.0,
+            0.0,
+            TerrainSettings {
+                caves: CaveSettings {
+                    size: 0.0,
+                    ..CaveSettings::NONE
+                },
+                ..SETTINGS
+            },
+        )
+    };
+
+    let Err(errors) = VolumetricChunk::build(&data) else {
+        panic!("the chunk should be rejected");
+    };
+    assert!(errors.0.contains(&ValidationError::ResolutionTooLow(1)));
+    assert!(errors.0.contains(&ValidationError::InvalidCaveSize(0.0)));
+}
+
+#[test]
+fn caves_deeper_than_the_radial_levels_are_rejected() {
+    let spacing = chunk_size() / (RESOLUTION - 1) as f32;
+    let data = build_data(
+        0.0,
+        0.0,
+        TerrainSettings {
+            caves: CaveSettings {
+                depth: spacing * (MAX_RADIAL_LEVELS + 1) as f32,
+                ..cave_settings().caves
+            },
+            ..flat_settings()
+        },
+    );
+
+    let Err(errors) = VolumetricChunk::build(&data) else {
+        panic!("the chunk should be rejected");
+    };
+    assert!(matches!(
+        errors.0[..],
+        [ValidationError::TooManyRadialLevels(levels)] if levels > MAX_RADIAL_LEVELS
+    ));
+    assert!(data.validate().is_ok());
+}
@@ -12,9 +12,12 @@ use rand::Rng;
 
 use std::fmt;
 use terrain_generation::landscape::make_terrain_function::make_terrain_function;
+use terrain_generation::landscape::terrain_layer::TerrainLayer;
 use terrain_generation::landscape::*;
+use terrain_generation::seed::{NoiseSeed, Seed};
 use terrain_generation::terrain_settings::TerrainSettings;
 use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::vector3d::Vector3d;
 use terrain_generation::utils::*;
 use terrain_generation::*;
 use wasm_bindgen_test::__rt::log;
@@ -25,18 +28,49 @@ wasm_bindgen_test_configure!(run_in_browser);
 #[test]
 #[wasm_bindgen_test]
 fn constant_layers() {
+    let seed = NoiseSeed::new(Seed::from_offset(0.0));
     let value = 0.5;
     let c_layer = constant_layers::constant_layer(value);
-    assert_eq!(c_layer(0.0, 0.0, 0.0), value);
-    assert_eq!(c_layer(10000.0, -5000.0, 12.0), value);
+    assert_eq!(
+        c_layer.sample(&Vector3d::new(0.0, 0.0, 0.0), &seed, &mut Vector3::zero()),
+        value
+    );
+    assert_eq!(
+        c_layer.sample(
+            &Vector3d::new(10000.0, -5000.0, 12.0),
+            &seed,
+            &mut Vector3::zero()
+        ),
+        value
+    );
 
     let z_layer = constant_layers::zero_layer();
-    assert_eq!(z_layer(0.0, 0.0, 0.0), 0.0);
-    assert_eq!(z_layer(100.0, 600.0, -73.0), 0.0);
+    assert_eq!(
+        z_layer.sample(&Vector3d::new(0.0, 0.0, 0.0), &seed, &mut Vector3::zero()),
+        0.0
+    );
+    assert_eq!(
+        z_layer.sample(
+            &Vector3d::new(100.0, 600.0, -73.0),
+            &seed,
+            &mut Vector3::zero()
+        ),
+        0.0
+    );
 
     let o_layer = constant_layers::one_layer();
-    assert_eq!(o_layer(0.0, 0.0, 0.0), 1.0);
-    assert_eq!(o_layer(-456.0, 5.0, -13.0), 1.0);
+    assert_eq!(
+        o_layer.sample(&Vector3d::new(0.0, 0.0, 0.0), &seed, &mut Vector3::zero()),
+        1.0
+    );
+    assert_eq!(
+        o_layer.sample(
+            &Vector3d::new(-456.0, 5.0, -13.0),
+            &seed,
+            &mut Vector3::zero()
+        ),
+        1.0
+    );
 }
 
 #[test]
@@ -69,3 +103,28 @@ fn dot_vector() {
     let v2 = Vector3::new(0.0, 0.0, 1.0);
     assert!(Vector3::dot(&v1, &v2) == 0.0);
 }
+
+#[test]
+#[wasm_bindgen_test]
+fn simplex_batch_matches_the_scalar_noise() {
+    // in the browser the batch runs on the simd128 backend when it is enabled
+    let seed = NoiseSeed::new(Seed::from_offset(11.0));
+    let points: Vec<Vector3d> = (0..37)
+        .map(|i| {
+            let i = i as f64;
+            Vector3d::new(f64::sin(i * 0.37), f64::cos(i * 0.37), f64::sin(i * 0.11))
+                .normalize_to_new()
+        })
+        .collect();
+
+    let mut values = vec![0.0; points.len()];
+    let mut gradients = vec![Vector3::zero(); points.len()];
+    simplex_batch::simplex411_batch(&points, &seed, &mut values, &mut gradients);
+
+    for (i, point) in points.iter().enumerate() {
+        let mut gradient = Vector3::zero();
+        let value = simplex::simplex411(point, &seed, &mut gradient);
+        assert!((values[i] - value).abs() < 1e-5);
+        assert!((&gradients[i] - &gradient).length() < 1e-5 * f32::max(1.0, gradient.length()));
+    }
+}
@@ -0,0 +1,142 @@
+use terrain_generation::landscape::gradient_check::numerical_gradient;
+use terrain_generation::seed::Seed;
+use terrain_generation::utils::vector3::Vector3;
+use terrain_generation::utils::worley::{
+    worley3, worley3_cell_id, worley4, worley4_cell_id, WorleyFeature,
+};
+
+mod common;
+
+use common::*;
+
+const FEATURES: [WorleyFeature; 3] = [
+    WorleyFeature::F1,
+    WorleyFeature::F2,
+    WorleyFeature::F2MinusF1,
+];
+
+fn sample_points() -> Vec<Vector3> {
+    (0..400)
+        .map(|i| {
+            let t = i as f32 * 0.173;
+            Vector3::new(
+                3.0 * f32::cos(t) + 0.01 * i as f32,
+                2.0 * f32::sin(1.7 * t) - 0.02 * i as f32,
+                4.0 * f32::sin(0.3 * t),
+            )
+        })
+        .collect()
+}
+
+/// The cellular noise has creases where two feature points are equidistant, so a few samples may straddle one
+fn assert_gradient_matches(noise: &dyn Fn(&Vector3, &mut Vector3) -> f32) {
+    let epsilon = 1e-3;
+    let points = sample_points();
+    let nb_matching = points
+        .iter()
+        .filter(|point| {
+            let mut gradient = Vector3::zero();
+            noise(point, &mut gradient);
+            let numerical = numerical_gradient(&|p| noise(p, &mut Vector3::zero()), point, epsilon);
+            (&gradient - &numerical).length() <= 1e-2
+        })
+        .count();
+
+    assert!(
+        nb_matching as f32 >= 0.95 * points.len() as f32,
+        "only {nb_matching} of {} gradients match finite differences",
+        points.len()
+    );
+}
+
+#[test]
+fn worley_gradients_match_finite_differences() {
+    for feature in FEATURES {
+        assert_gradient_matches(&|p, gradient| worley3(p, feature, gradient));
+        assert_gradient_matches(&|p, gradient| worley4(p, SEED, feature, gradient));
+    }
+}
+
+#[test]
+fn worley_features_are_consistent() {
+    for point in sample_points() {
+        let mut f1_gradient = Vector3::zero();
+        let f1 = worley3(&point, WorleyFeature::F1, &mut f1_gradient);
+        let mut f2_gradient = Vector3::zero();
+        let f2 = worley3(&point, WorleyFeature::F2, &mut f2_gradient);
+        let mut difference_gradient = Vector3::zero();
+        let difference = worley3(&point, WorleyFeature::F2MinusF1, &mut difference_gradient);
+
+        // the feature point of the sample's own cell is at most a cell diagonal away
+        assert!(f1 >= 0.0 && f1 <= f32::sqrt(3.0));
+        assert!(f1 <= f2);
+        assert_eq!(difference, f2 - f1);
+        assert!(Vector3::equals(
+            &difference_gradient,
+            &(&f2_gradient - &f1_gradient)
+        ));
+
+        // the distance to a point grows by one unit per unit of length
+        assert!((f1_gradient.length() - 1.0).abs() <= 1e-4);
+
+        // in 4D, only the first 3 components of the gradient are returned
+        let mut gradient = Vector3::zero();
+        worley4(&point, SEED, WorleyFeature::F1, &mut gradient);
+        assert!(gradient.length() <= 1.0 + 1e-4);
+    }
+}
+
+#[test]
+fn worley_is_seeded_along_the_fourth_dimension() {
+    let mut found_difference = false;
+    for point in sample_points() {
+        let value = worley4(&point, SEED, WorleyFeature::F1, &mut Vector3::zero());
+        assert_eq!(
+            value,
+            worley4(&point, SEED, WorleyFeature::F1, &mut Vector3::zero())
+        );
+
+        if value
+            != worley4(
+                &point,
+                Seed::from_offset(18.0),
+                WorleyFeature::F1,
+                &mut Vector3::zero(),
+            )
+        {
+            found_difference = true;
+        }
+    }
+
+    assert!(found_difference, "another seed should move the cells");
+}
+
+#[test]
+fn cell_ids_partition_space() {
+    let points = sample_points();
+    let ids = points
+        .iter()
+        .map(worley3_cell_id)
+        .collect::<std::collections::HashSet<u32>>();
+    assert!(ids.len() > 10, "expected many distinct cells");
+
+    for point in points {
+        // the identifier is constant close to the sample unless it lies on a cell boundary
+        let nudged = &point + &Vector3::new(1e-4, 0.0, 0.0);
+
+        let f1 = worley3(&point, WorleyFeature::F1, &mut Vector3::zero());
+        let f2 = worley3(&point, WorleyFeature::F2, &mut Vector3::zero());
+        if f2 - f1 > 1e-3 {
+            assert_eq!(worley3_cell_id(&point), worley3_cell_id(&nudged));
+        }
+
+        let f1 = worley4(&point, SEED, WorleyFeature::F1, &mut Vector3::zero());
+        let f2 = worley4(&point, SEED, WorleyFeature::F2, &mut Vector3::zero());
+        if f2 - f1 > 1e-3 {
+            assert_eq!(
+                worley4_cell_id(&point, SEED),
+                worley4_cell_id(&nudged, SEED)
+            );
+        }
+    }
+}